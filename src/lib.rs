@@ -0,0 +1,237 @@
+//! nanopm as a library: open or create a project, plan and apply setup
+//! changes and run queries without going through the command line.
+//!
+//! ```no_run
+//! use nanopm::{Project, Query, QuerySettings, SortType};
+//!
+//! let project = Project::open(".")?;
+//! let results = project.query(&Query::General(SortType::BySize), &QuerySettings::default())?;
+//! println!("{} folders", results.len());
+//! # Ok::<(), nanopm::ProjectError>(())
+//! ```
+
+pub mod util;
+
+use util::{
+    config,
+    init::{self, ProjectSetup},
+    iterable, manifest, migration, plan, project, query,
+};
+pub use util::{
+    config::{Config, ConfigError, FolderPattern, Query, QuerySettings, QueryType, SortType},
+    plan::SetupPlan,
+    project::{Project, ProjectError, SetupReport},
+    query::{QueryError, QueryResult},
+};
+
+/// Every folder path of the project, relative to the directory holding its
+/// config and starting with the project folder itself.
+pub fn generate_folder_paths(config: &Config) -> Result<Vec<String>, ConfigError> {
+    let mut paths: Vec<String> = Vec::new();
+    paths.push(config.setup.name.clone());
+
+    for folder in &config.file_structure.folders_list {
+        let folder_paths = build_folder_path(folder, config, &config.setup.name)?;
+        paths.extend(folder_paths);
+    }
+
+    Ok(paths)
+}
+
+/// Every path generated for the folder with ID `id`, e.g. one per shoot day
+/// for `video`.
+pub fn folder_paths(config: &Config, id: &str) -> Result<Vec<String>, ConfigError> {
+    Ok(folder_entries(config, id)?
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// Like [`folder_paths`], alongside the shoot day each path sits in (if any).
+pub fn folder_entries(
+    config: &Config,
+    id: &str,
+) -> Result<Vec<(String, Option<usize>)>, ConfigError> {
+    let folder = config
+        .file_structure
+        .folders_list
+        .iter()
+        .find(|folder| folder.id == id)
+        .ok_or_else(|| ConfigError::ParseError(format!("Unknown folder ID \"{}\"", id)))?;
+    build_folder_entries(folder, config, &config.setup.name)
+}
+
+fn build_folder_path(
+    folder: &config::Folder,
+    config: &Config,
+    project_name: &str,
+) -> Result<Vec<String>, ConfigError> {
+    Ok(build_folder_entries(folder, config, project_name)?
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// Builds every path of `folder`, alongside the shoot day each path sits in
+/// (if any) so per-day overrides can decide how many cams/sound sources a
+/// day gets.
+fn build_folder_entries(
+    folder: &config::Folder,
+    config: &Config,
+    project_name: &str,
+) -> Result<Vec<(String, Option<usize>)>, ConfigError> {
+    let parent_entries = match &folder.parent_id {
+        Some(parent_id) => find_parent_entries(parent_id, config, project_name)?,
+        None => vec![(project_name.to_string(), None)],
+    };
+
+    let mut entries = Vec::new();
+    for (parent_path, day) in &parent_entries {
+        for (folder_name, day) in expand_folder_name(&folder.name, config, *day)? {
+            entries.push((format!("{}/{}", parent_path, folder_name), day));
+        }
+    }
+
+    Ok(entries)
+}
+
+fn find_parent_entries(
+    parent_id: &str,
+    config: &Config,
+    project_name: &str,
+) -> Result<Vec<(String, Option<usize>)>, ConfigError> {
+    for folder in &config.file_structure.folders_list {
+        if folder.id == parent_id {
+            return build_folder_entries(folder, config, project_name);
+        }
+    }
+    Err(ConfigError::ParseError(format!(
+        "Parent folder with ID '{}' not found",
+        parent_id
+    )))
+}
+
+fn expand_folder_name(
+    name: &str,
+    config: &Config,
+    day: Option<usize>,
+) -> Result<Vec<(String, Option<usize>)>, ConfigError> {
+    let Some(id) = name.strip_prefix('%') else {
+        return Ok(vec![(name.to_string(), day)]);
+    };
+    let iterable = iterable::resolve(id, config, day)
+        .ok_or_else(|| ConfigError::ParseError(format!("Unknown iterable \"{}\"", name)))?;
+
+    Ok((1..=iterable.count)
+        .map(|i| {
+            let day = if id == iterable::DAYS { Some(i) } else { day };
+            (iterable.folder_name(i), day)
+        })
+        .collect())
+}
+
+pub fn day_folder_name(setup: &ProjectSetup, index: usize) -> String {
+    iterable::render_day(setup.day_template(), index, setup.shoot_day(index))
+}
+
+pub fn cam_folder_name(setup: &ProjectSetup, index: usize) -> String {
+    iterable::render_template(
+        iterable::UNIT_TEMPLATE,
+        index,
+        Some(&setup.camera_label(index)),
+    )
+}
+
+pub fn sound_folder_name(setup: &ProjectSetup, index: usize) -> String {
+    iterable::render_template(
+        iterable::UNIT_TEMPLATE,
+        index,
+        Some(&setup.sound_source_label(index)),
+    )
+}
+
+/// Spreadsheet-style column letters: 1 = A, 26 = Z, 27 = AA, 28 = AB, ...
+pub fn num_to_letters(num: usize) -> String {
+    if num == 0 {
+        return String::from("_");
+    }
+    let mut letters = Vec::new();
+    let mut remaining = num;
+    while remaining > 0 {
+        remaining -= 1;
+        letters.push((remaining % 26) as u8 + b'A');
+        remaining /= 26;
+    }
+    letters.iter().rev().map(|&c| c as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_num_to_letters() {
+        assert_eq!(num_to_letters(1), "A");
+        assert_eq!(num_to_letters(26), "Z");
+        assert_eq!(num_to_letters(0), "_");
+        assert_eq!(num_to_letters(27), "AA");
+        assert_eq!(num_to_letters(28), "AB");
+        assert_eq!(num_to_letters(52), "AZ");
+        assert_eq!(num_to_letters(703), "AAA");
+    }
+
+    #[test]
+    fn test_generate_folder_paths_expands_nested_iterables() {
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.setup.days = 2;
+        config.setup.cameras = 2;
+
+        let paths = generate_folder_paths(&config).unwrap();
+        assert!(paths.contains(&"Demo/02_RUSHES/02_DAY02/01_VIDEO/02_B_CAM".to_string()));
+        assert!(paths.contains(&"Demo/02_RUSHES/01_DAY01/02_AUDIO/01_A_REC".to_string()));
+        assert!(!paths.iter().any(|path| path.contains('%')));
+    }
+
+    #[test]
+    fn test_generate_folder_paths_expands_custom_iterables() {
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.file_structure.iterables = vec![iterable::Iterable {
+            id: "scenes".to_string(),
+            count: 2,
+            template: "{index:02}_SC{index:03}".to_string(),
+            names: Vec::new(),
+        }];
+        config.file_structure.folders_list.push(config::Folder::new(
+            "scenes",
+            Some("days"),
+            "%scenes",
+        ));
+
+        let paths = generate_folder_paths(&config).unwrap();
+        assert!(paths.contains(&"Demo/02_RUSHES/02_DAY02/02_SC002".to_string()));
+
+        config.file_structure.folders_list[0].name = "%unknown".to_string();
+        assert!(generate_folder_paths(&config).is_err());
+    }
+
+    #[test]
+    fn test_generate_folder_paths_respects_day_overrides() {
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.setup.days = 2;
+        config.setup.cameras = 2;
+        config.setup.day_overrides = vec![init::DayOverride {
+            day: 2,
+            cameras: Some(1),
+            sound_sources: Some(2),
+        }];
+
+        let paths = generate_folder_paths(&config).unwrap();
+        assert!(paths.contains(&"Demo/02_RUSHES/01_DAY01/01_VIDEO/02_B_CAM".to_string()));
+        assert!(!paths.contains(&"Demo/02_RUSHES/02_DAY02/01_VIDEO/02_B_CAM".to_string()));
+        assert!(!paths.contains(&"Demo/02_RUSHES/01_DAY01/02_AUDIO/02_B_REC".to_string()));
+        assert!(paths.contains(&"Demo/02_RUSHES/02_DAY02/02_AUDIO/02_B_REC".to_string()));
+    }
+}
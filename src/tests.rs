@@ -251,7 +251,7 @@ mod tests {
 
         #[test]
         fn test_get_version() {
-            assert_eq!(get_version(), "v2");
+            assert_eq!(get_version(), "v3");
         }
 
         #[test]
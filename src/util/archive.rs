@@ -0,0 +1,489 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, offset::Utc};
+use serde::{Deserialize, Serialize};
+use tar::{Builder, EntryType, Header};
+use walkdir::WalkDir;
+
+use super::{
+    config::{ArchiveInfo, Config, ConfigError, RestoreInfo},
+    manifest::{HashAlgorithm, VerifyReport, hash_file},
+    migration::{self, MigrationError},
+    project::CONFIG_FILE,
+};
+use crate::{folder_paths, generate_folder_paths, util::util::get_version};
+
+/// The first entry of every archive, listing the files that follow with
+/// their size and checksum.
+pub const ARCHIVE_MANIFEST: &str = "nanopm_archive.toml";
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    IoError(std::io::Error),
+    ConfigError(ConfigError),
+    MigrationError(MigrationError),
+    InvalidArchive(String),
+    AlreadyExists(PathBuf),
+    Failed(VerifyReport),
+}
+
+impl std::fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveError::IoError(e) => write!(f, "IO error: {}", e),
+            ArchiveError::ConfigError(e) => write!(f, "Config error: {}", e),
+            ArchiveError::MigrationError(e) => write!(f, "Migration error: {}", e),
+            ArchiveError::InvalidArchive(msg) => write!(f, "Invalid archive: {}", msg),
+            ArchiveError::AlreadyExists(path) => {
+                write!(
+                    f,
+                    "{} already exists, refusing to overwrite it",
+                    path.display()
+                )
+            }
+            ArchiveError::Failed(report) => write!(
+                f,
+                "{} missing, {} changed and {} extra file(s)",
+                report.missing.len(),
+                report.changed.len(),
+                report.extra.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<std::io::Error> for ArchiveError {
+    fn from(error: std::io::Error) -> Self {
+        ArchiveError::IoError(error)
+    }
+}
+
+impl From<ConfigError> for ArchiveError {
+    fn from(error: ConfigError) -> Self {
+        ArchiveError::ConfigError(error)
+    }
+}
+
+impl From<MigrationError> for ArchiveError {
+    fn from(error: MigrationError) -> Self {
+        ArchiveError::MigrationError(error)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ArchiveManifest {
+    pub version: String,
+    pub creator: String,
+    pub created: String,
+    pub project_name: String,
+    pub hash_algorithm: HashAlgorithm,
+    /// Folder IDs left out of the archive. Their folders are recreated empty
+    /// on restore.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<String>,
+    #[serde(default)]
+    pub files: Vec<ArchiveEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ArchiveEntry {
+    /// Relative to the directory holding the config, always with `/`.
+    pub path: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+impl ArchiveManifest {
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|entry| entry.size).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArchiveOptions {
+    pub output: PathBuf,
+    /// zstd level, or `None` for a plain tar.
+    pub compression: Option<i32>,
+    /// Folder IDs from `file_structure` to leave out.
+    pub exclude: Vec<String>,
+    pub hash_algorithm: HashAlgorithm,
+}
+
+/// Where `archive` writes to when no output is given: next to the config,
+/// named after the project.
+pub fn default_output(root: &Path, config: &Config, compressed: bool) -> PathBuf {
+    let extension = if compressed { "tar.zst" } else { "tar" };
+    root.join(format!("{}.{}", config.setup.name, extension))
+}
+
+/// Packs `config.toml` and the project folder under `root` into a tar
+/// (optionally zstd-compressed) archive, led by a manifest of every file.
+pub fn create_archive(
+    root: &Path,
+    config: &Config,
+    options: &ArchiveOptions,
+) -> Result<ArchiveManifest, ArchiveError> {
+    if options.output.exists() {
+        return Err(ArchiveError::AlreadyExists(options.output.clone()));
+    }
+
+    let mut excluded_paths = Vec::new();
+    for id in &options.exclude {
+        excluded_paths.extend(folder_paths(config, id)?);
+    }
+    let is_excluded = |relative: &str| {
+        excluded_paths
+            .iter()
+            .any(|path| relative == path || relative.starts_with(&format!("{}/", path)))
+    };
+
+    let mut manifest = ArchiveManifest {
+        version: get_version(),
+        creator: format!("nanopm {}", env!("CARGO_PKG_VERSION")),
+        created: DateTime::<Utc>::from(SystemTime::now()).to_rfc3339(),
+        project_name: config.setup.name.clone(),
+        hash_algorithm: options.hash_algorithm,
+        excluded: options.exclude.clone(),
+        files: Vec::new(),
+    };
+    let mut directories = Vec::new();
+
+    let config_path = root.join(CONFIG_FILE);
+    manifest.files.push(ArchiveEntry {
+        path: CONFIG_FILE.to_string(),
+        size: fs::metadata(&config_path)?.len(),
+        hash: hash_file(&config_path, options.hash_algorithm)?,
+    });
+
+    let walker = WalkDir::new(root.join(&config.setup.name))
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| !is_excluded(&relative_path(root, entry.path())));
+    for entry in walker {
+        let entry = entry.map_err(|e| ArchiveError::IoError(e.into()))?;
+        let relative = relative_path(root, entry.path());
+        if entry.file_type().is_dir() {
+            directories.push(relative);
+        } else if entry.file_type().is_file() {
+            manifest.files.push(ArchiveEntry {
+                path: relative,
+                size: entry
+                    .metadata()
+                    .map_err(|e| ArchiveError::IoError(e.into()))?
+                    .len(),
+                hash: hash_file(entry.path(), options.hash_algorithm)?,
+            });
+        }
+    }
+
+    let file = File::create(&options.output)?;
+    let written = match options.compression {
+        Some(level) => {
+            let encoder = zstd::Encoder::new(file, level)?;
+            write_tar(encoder, root, &manifest, &directories)
+                .and_then(|encoder| encoder.finish().map(drop))
+        }
+        None => write_tar(file, root, &manifest, &directories).map(drop),
+    };
+    if let Err(e) = written {
+        // Don't leave a truncated archive behind.
+        let _ = fs::remove_file(&options.output);
+        return Err(e.into());
+    }
+
+    Ok(manifest)
+}
+
+fn write_tar<W: Write>(
+    writer: W,
+    root: &Path,
+    manifest: &ArchiveManifest,
+    directories: &[String],
+) -> io::Result<W> {
+    let manifest_text = toml::to_string(manifest).map_err(io::Error::other)?;
+    let mut builder = Builder::new(writer);
+
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Regular);
+    header.set_size(manifest_text.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs()),
+    );
+    builder.append_data(&mut header, ARCHIVE_MANIFEST, manifest_text.as_bytes())?;
+
+    // Directories go in on their own so empty ones survive a round trip.
+    for directory in directories {
+        builder.append_dir(directory, root.join(directory))?;
+    }
+    for entry in &manifest.files {
+        builder.append_path_with_name(root.join(&entry.path), &entry.path)?;
+    }
+
+    builder.into_inner()
+}
+
+/// Unpacks an archive into `destination`, re-hashes every file against the
+/// archive's manifest and recreates the folders that were excluded from it.
+/// Refuses to touch an existing config or project folder.
+pub fn restore_archive(
+    archive: &Path,
+    destination: &Path,
+) -> Result<(ArchiveManifest, VerifyReport), ArchiveError> {
+    let mut file = File::open(archive)?;
+    let mut magic = [0u8; 4];
+    let is_zstd = file.read_exact(&mut magic).is_ok() && magic == ZSTD_MAGIC;
+    file.seek(SeekFrom::Start(0))?;
+    let reader: Box<dyn Read> = if is_zstd {
+        Box::new(zstd::Decoder::new(file)?)
+    } else {
+        Box::new(file)
+    };
+
+    let mut tar = tar::Archive::new(reader);
+    let mut entries = tar.entries()?;
+
+    let manifest: ArchiveManifest = match entries.next() {
+        Some(entry) => {
+            let mut entry = entry?;
+            if entry.path()?.as_ref() != Path::new(ARCHIVE_MANIFEST) {
+                return Err(ArchiveError::InvalidArchive(format!(
+                    "{} does not start with a {}",
+                    archive.display(),
+                    ARCHIVE_MANIFEST
+                )));
+            }
+            let mut text = String::new();
+            entry.read_to_string(&mut text)?;
+            toml::from_str(&text).map_err(|e| ArchiveError::InvalidArchive(e.to_string()))?
+        }
+        None => {
+            return Err(ArchiveError::InvalidArchive(format!(
+                "{} is empty",
+                archive.display()
+            )));
+        }
+    };
+
+    for existing in [
+        destination.join(CONFIG_FILE),
+        destination.join(&manifest.project_name),
+    ] {
+        if existing.exists() {
+            return Err(ArchiveError::AlreadyExists(existing));
+        }
+    }
+    fs::create_dir_all(destination)?;
+
+    for entry in entries {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if !is_project_entry(&path, &manifest.project_name) {
+            return Err(ArchiveError::InvalidArchive(format!(
+                "Unexpected entry {}",
+                path.display()
+            )));
+        }
+        entry.unpack_in(destination)?;
+    }
+
+    let report = validate(destination, &manifest)?;
+    if !report.is_clean() {
+        return Err(ArchiveError::Failed(report));
+    }
+
+    let (config, _) = migration::load_config(&destination.join(CONFIG_FILE), false)?;
+    for path in generate_folder_paths(&config)? {
+        fs::create_dir_all(destination.join(path))?;
+    }
+
+    Ok((manifest, report))
+}
+
+/// Only the config and paths inside the project folder may be unpacked.
+fn is_project_entry(path: &Path, project_name: &str) -> bool {
+    if path == Path::new(CONFIG_FILE) {
+        return true;
+    }
+    let mut components = path.components();
+    components.next() == Some(Component::Normal(project_name.as_ref()))
+        && components.all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn validate(destination: &Path, manifest: &ArchiveManifest) -> Result<VerifyReport, ArchiveError> {
+    let mut report = VerifyReport::default();
+
+    for entry in &manifest.files {
+        let file = destination.join(&entry.path);
+        if !file.is_file() {
+            report.missing.push(entry.path.clone());
+            continue;
+        }
+        if fs::metadata(&file)?.len() != entry.size
+            || hash_file(&file, manifest.hash_algorithm)? != entry.hash
+        {
+            report.changed.push(entry.path.clone());
+            continue;
+        }
+        report.verified += 1;
+    }
+
+    for file in WalkDir::new(destination.join(&manifest.project_name)).sort_by_file_name() {
+        let file = file.map_err(|e| ArchiveError::IoError(e.into()))?;
+        if !file.file_type().is_file() {
+            continue;
+        }
+        let relative = relative_path(destination, file.path());
+        if !manifest.files.iter().any(|entry| entry.path == relative) {
+            report.extra.push(relative);
+        }
+    }
+
+    Ok(report)
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .map(|path| path.to_string_lossy().replace("\\", "/"))
+        .unwrap_or_default()
+}
+
+pub fn archive(archive_info: ArchiveInfo) -> Result<ArchiveManifest, ArchiveError> {
+    let config = &archive_info.config;
+    let options = ArchiveOptions {
+        output: archive_info.output.unwrap_or_else(|| {
+            default_output(
+                &archive_info.root,
+                config,
+                archive_info.compression.is_some(),
+            )
+        }),
+        compression: archive_info.compression,
+        exclude: archive_info.exclude,
+        hash_algorithm: archive_info.hash_algorithm,
+    };
+
+    let manifest = create_archive(&archive_info.root, config, &options)?;
+    println!(
+        "Archived {} file(s) ({} bytes) to {}",
+        manifest.files.len(),
+        manifest.total_size(),
+        options.output.display()
+    );
+    if !manifest.excluded.is_empty() {
+        println!("    Excluded: {}", manifest.excluded.join(", "));
+    }
+    Ok(manifest)
+}
+
+pub fn restore(restore_info: RestoreInfo) -> Result<VerifyReport, ArchiveError> {
+    let (manifest, report) = restore_archive(&restore_info.archive, &restore_info.root)
+        .inspect_err(|e| {
+            if let ArchiveError::Failed(report) = e {
+                report.print();
+            }
+        })?;
+    println!(
+        "Restored {} into {}",
+        manifest.project_name,
+        restore_info.root.display()
+    );
+    report.print();
+    if !manifest.excluded.is_empty() {
+        println!(
+            "    Recreated excluded folders empty: {}",
+            manifest.excluded.join(", ")
+        );
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(dir: &Path) -> Config {
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.setup.days = 1;
+        config.setup.cameras = 1;
+        Config::write_config(&config, dir.join(CONFIG_FILE).to_str().unwrap()).unwrap();
+        for path in generate_folder_paths(&config).unwrap() {
+            fs::create_dir_all(dir.join(path)).unwrap();
+        }
+        fs::write(
+            dir.join("Demo/02_RUSHES/01_DAY01/01_VIDEO/01_A_CAM/clip.mov"),
+            b"rushes",
+        )
+        .unwrap();
+        fs::write(dir.join("Demo/04_PRE-RENDERS/render.mov"), b"render").unwrap();
+        config
+    }
+
+    #[test]
+    fn test_archive_round_trip_with_excludes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let dir = dir.path();
+        let source = dir.join("source");
+        fs::create_dir_all(&source).unwrap();
+        let config = project(&source);
+
+        for compression in [None, Some(3)] {
+            let options = ArchiveOptions {
+                output: default_output(dir, &config, compression.is_some()),
+                compression,
+                exclude: vec!["prerenders".to_string()],
+                hash_algorithm: HashAlgorithm::XxHash64,
+            };
+            let manifest = create_archive(&source, &config, &options).unwrap();
+            assert_eq!(manifest.files.len(), 2);
+            assert!(matches!(
+                create_archive(&source, &config, &options),
+                Err(ArchiveError::AlreadyExists(_))
+            ));
+
+            let target = dir.join(format!("restored_{}", compression.is_some()));
+            let (_, report) = restore_archive(&options.output, &target).unwrap();
+            assert_eq!(report.verified, 2);
+            assert!(
+                target
+                    .join("Demo/02_RUSHES/01_DAY01/01_VIDEO/01_A_CAM/clip.mov")
+                    .is_file()
+            );
+            assert!(target.join("Demo/04_PRE-RENDERS").is_dir());
+            assert!(!target.join("Demo/04_PRE-RENDERS/render.mov").exists());
+            assert!(matches!(
+                restore_archive(&options.output, &target),
+                Err(ArchiveError::AlreadyExists(_))
+            ));
+        }
+
+        let options = ArchiveOptions {
+            output: dir.join("unknown.tar"),
+            compression: None,
+            exclude: vec!["unknown".to_string()],
+            hash_algorithm: HashAlgorithm::XxHash64,
+        };
+        assert!(create_archive(&source, &config, &options).is_err());
+        assert!(!options.output.exists());
+    }
+
+    #[test]
+    fn test_is_project_entry() {
+        assert!(is_project_entry(Path::new("config.toml"), "Demo"));
+        assert!(is_project_entry(Path::new("Demo/02_RUSHES/a.mov"), "Demo"));
+        assert!(!is_project_entry(Path::new("Other/a.mov"), "Demo"));
+        assert!(!is_project_entry(Path::new("Demo/../escape"), "Demo"));
+    }
+}
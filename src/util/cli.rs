@@ -0,0 +1,1127 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::NaiveDate;
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+
+use super::{
+    config::{
+        ArchiveInfo, Breakdown, Config, FolderPattern, IngestInfo, IngestTarget, LintInfo,
+        OutputFormat, ParsedReturn, Query, QueryInfo, QuerySettings, QueryType, RestoreInfo,
+        SortAction, SortInfo, SortType, TemplateAction, TemplatesInfo, TierAction, TierInfo,
+        VerifyInfo, WorkspaceAction, WorkspaceInfo,
+    },
+    init::{OperationType, ProjectSetup},
+    iterable,
+    manifest::HashAlgorithm,
+    migration, project, template,
+    tier::{self, TierSelection},
+    watch::WatchOptions,
+};
+
+/// A whole day range like "1-3,5" in one value. Spelled as an alias so clap
+/// doesn't read `Option<Vec<_>>` as a repeatable flag.
+type DayList = Vec<usize>;
+
+/// Multi-letter short flags from before the parser was declarative. They are
+/// rewritten to their long form so existing scripts keep working.
+const LEGACY_FLAGS: [(&str, &str); 14] = [
+    ("-dn", "--deadname"),
+    ("-cn", "--camera-names"),
+    ("-sn", "--sound-source-names"),
+    ("-cl", "--clean"),
+    ("-dr", "--dry-run"),
+    ("-ar", "--archive"),
+    ("-fmt", "--format"),
+    ("-rt", "--runtime"),
+    ("-ss", "--sort-size"),
+    ("-sd", "--sort-default"),
+    ("-it", "--iterable"),
+    ("-src", "--source"),
+    ("-ha", "--hash"),
+    ("-ds", "--description"),
+];
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(clap::Error),
+    ConfigLoad(String),
+    Invalid(String),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(e) => write!(f, "{}", e),
+            CliError::ConfigLoad(msg) => write!(
+                f,
+                "Problem opening the config: {}\n\nIf the file was not found, consider \
+                 reinitializing the project with nanopm new (optionally add the argument \
+                 --deadname to consider an existing directory as the project directory).",
+                msg
+            ),
+            CliError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<clap::Error> for CliError {
+    fn from(error: clap::Error) -> Self {
+        CliError::Usage(error)
+    }
+}
+
+impl CliError {
+    /// 0 for --help/--version, 2 when the config can't be loaded, 1 for
+    /// everything else the user typed wrong.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(e) if !e.use_stderr() => 0,
+            CliError::ConfigLoad(_) => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn print(&self) {
+        match self {
+            CliError::Usage(e) => {
+                let _ = e.print();
+            }
+            other => eprintln!("{}", other),
+        }
+    }
+}
+
+/// Lightweight project manager for film and video shoots.
+#[derive(Debug, Parser)]
+#[command(name = "nanopm", version, arg_required_else_help = true)]
+pub struct Cli {
+    /// The project directory (or its config.toml) to work in, instead of the nearest one above
+    /// the current directory.
+    #[arg(long, global = true, env = "NANOPM_PROJECT", value_name = "PATH")]
+    pub project: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Initialize a new project in the current (or --project) directory, creating a new config
+    /// file from the provided arguments and using defaults where missing.
+    #[command(visible_alias = "n")]
+    New(NewArgs),
+    /// Update the current config file and project folders. The project must already have been
+    /// initialized.
+    #[command(visible_alias = "u")]
+    Update(SetupArgs),
+    /// Query the current project. Defaults to a general query if no query type is given.
+    #[command(visible_alias = "q")]
+    Query {
+        #[command(flatten)]
+        args: QueryArgs,
+        #[command(flatten)]
+        watch: WatchArgs,
+    },
+    /// Copy every file from a source directory (e.g. a camera card) into the folder of a day and
+    /// camera or sound source, keeping the source's layout. Never overwrites. Every copy is
+    /// hashed against its source and recorded in the day's manifest.
+    #[command(visible_alias = "i")]
+    Ingest(IngestArgs),
+    /// Re-hash ingested files against their day manifests, reporting missing, changed and extra
+    /// files. Exits with an error if anything does not match.
+    #[command(visible_alias = "v")]
+    Verify(VerifyArgs),
+    /// Check that files are where the file structure says they go: no audio in camera folders,
+    /// no video in sound source folders, no media loose in a day folder and no folders the file
+    /// structure doesn't know. Rules are set per folder ID in the [lint] table of the config.
+    /// Exits with an error if anything is out of place.
+    #[command(visible_alias = "check")]
+    Lint,
+    /// File loose media from a drop folder into the file structure by the rules in the [sort]
+    /// table of the config: extension, media kind, a file name regex and the shoot day it was
+    /// recorded on. Never overwrites. Every sort is journaled so it can be undone.
+    #[command(visible_alias = "file-in")]
+    Sort(SortArgs),
+    /// List, print or export project templates. User templates live in
+    /// $XDG_CONFIG_HOME/nanopm/templates.
+    #[command(visible_alias = "t")]
+    Templates {
+        #[command(subcommand)]
+        action: Option<TemplatesCommand>,
+    },
+    /// Register projects in a workspace and query or total all of them at once.
+    #[command(visible_alias = "ws")]
+    Workspace(WorkspaceArgs),
+    /// Pack the config and project folder into a single tar (or tar.zst) archive, led by a
+    /// manifest with the size and checksum of every file.
+    #[command(visible_alias = "a")]
+    Archive(ArchiveArgs),
+    /// Unpack an archive into the current (or --project) directory and check every file against
+    /// its manifest. Never overwrites an existing project.
+    Restore {
+        /// The .tar or .tar.zst archive to restore.
+        archive: PathBuf,
+    },
+    /// Move folders to a slower volume, leaving an empty folder with a nanopm_offline.toml stub
+    /// behind. Queries keep reporting their size as offline.
+    #[command(visible_alias = "tier")]
+    Offload {
+        /// The ID of the folders in file_structure to move, e.g. days or video.
+        id: String,
+        /// Only moves the folders in these shoot days, e.g. 1-3 or 1,4.
+        #[arg(short, long, value_name = "DAYS", value_parser = tier::parse_days)]
+        days: Option<DayList>,
+        /// The volume to offload to. Recorded in the config, so it only has to be passed once.
+        #[arg(long, value_name = "PATH")]
+        to: Option<PathBuf>,
+    },
+    /// Bring offloaded folders back into the project.
+    Recall {
+        /// The ID of the folders to bring back. Offloaded folders inside them come back too.
+        #[arg(required_unless_present = "all")]
+        id: Option<String>,
+        /// Only recalls the folders in these shoot days, e.g. 1-3 or 1,4.
+        #[arg(short, long, value_name = "DAYS", value_parser = tier::parse_days)]
+        days: Option<DayList>,
+        /// Recalls every offloaded folder.
+        #[arg(long, conflicts_with_all = ["id", "days"])]
+        all: bool,
+    },
+    /// Print a shell completion script, e.g. `nanopm completions bash > ~/.nanopm.bash`.
+    Completions {
+        /// The shell to generate completions for.
+        shell: Shell,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct NewArgs {
+    #[command(flatten)]
+    pub setup: SetupArgs,
+    /// Seeds the file structure and general query folders from a template: default,
+    /// music-video, documentary, commercial, podcast or one of your own.
+    #[arg(short, long, value_name = "NAME")]
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SetupArgs {
+    /// Names the project and its directory. With update, renames the old directory too.
+    #[arg(short, long)]
+    pub name: Option<String>,
+    /// Looks for a directory with this name and uses it as the project directory, renaming it
+    /// to the new name.
+    #[arg(long)]
+    pub deadname: Option<String>,
+    /// The amount of shoot days the project should account for.
+    #[arg(short, long, value_parser = parse_positive_integer)]
+    pub days: Option<usize>,
+    /// The amount of cameras the project should account for.
+    #[arg(short, long, value_parser = parse_positive_integer)]
+    pub cameras: Option<usize>,
+    /// The amount of sound sources the project should account for.
+    #[arg(short, long, value_parser = parse_positive_integer)]
+    pub sound_sources: Option<usize>,
+    /// Names the cameras in order, comma-separated (e.g. "A_FX6,B_GOPRO"). Unnamed cameras keep
+    /// A_CAM, B_CAM, ... AA_CAM past 26. An empty string clears the names.
+    #[arg(long, value_name = "NAMES")]
+    pub camera_names: Option<String>,
+    /// Names the sound sources in order, comma-separated (e.g. "BOOM,LAV1").
+    #[arg(long, value_name = "NAMES")]
+    pub sound_source_names: Option<String>,
+    /// Names the day folders, e.g. "{index:02}_DAY{index:02}_{date}". On top of {index},
+    /// {index:02} and {letter} it takes {date}, {location} and {label} from the calendar. An
+    /// empty string restores the default.
+    #[arg(long, value_name = "TEMPLATE")]
+    pub day_template: Option<String>,
+    /// Dates a shoot day, e.g. 2=2026-10-18. Can be repeated, and an empty date clears it. With
+    /// update, day folders named after their date are renamed.
+    #[arg(long, value_name = "DAY=DATE", value_parser = parse_day_date)]
+    pub date: Vec<(usize, Option<NaiveDate>)>,
+    /// Sets where a shoot day takes place, e.g. "2=Pier 9". Can be repeated.
+    #[arg(long, value_name = "DAY=TEXT", value_parser = parse_day_text)]
+    pub location: Vec<(usize, Option<String>)>,
+    /// Labels a shoot day, e.g. 3=PICKUPS. Can be repeated.
+    #[arg(long, value_name = "DAY=TEXT", value_parser = parse_day_text)]
+    pub label: Vec<(usize, Option<String>)>,
+    /// Deletes all empty folders not defined by the file structure after initializing.
+    #[arg(long)]
+    pub clean: bool,
+    /// Prints the folders that would be created, renamed and (with --clean) removed, without
+    /// changing anything on disk.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Sets the --dry-run output format.
+    #[arg(long, value_name = "FORMAT", value_parser = OutputFormat::parse, default_value = "text")]
+    pub format: OutputFormat,
+    /// Lets update shrink days, cameras or sound sources even when the folders it would drop
+    /// still hold media.
+    #[arg(short, long)]
+    pub force: bool,
+    /// Moves folders that update would drop into _ARCHIVED instead of refusing.
+    #[arg(long)]
+    pub archive: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct QueryArgs {
+    /// Queries the folders listed in general_query_params in the config.
+    #[arg(short, long, conflicts_with_all = ["root", "days", "cameras", "sound_sources", "iterable", "folder", "folder_regex", "folder_id"])]
+    pub general: bool,
+    /// Queries the full project directory, as well as returning project config values.
+    #[arg(short, long)]
+    pub root: bool,
+    /// Queries each shoot day.
+    #[arg(short, long)]
+    pub days: bool,
+    /// Queries each camera, combining all days unless --unique is used.
+    #[arg(short, long)]
+    pub cameras: bool,
+    /// Queries each sound source, combining all days unless --unique is used.
+    #[arg(short, long)]
+    pub sound_sources: bool,
+    /// Queries each folder of an iterable declared in the config (e.g. scenes). Can be repeated.
+    #[arg(long, value_name = "ID")]
+    pub iterable: Vec<String>,
+    /// Queries all folders with this name, or ending in this path (e.g. 01_DAY01/01_VIDEO). * and
+    /// ? match within a name, e.g. *_CAM. Can be repeated.
+    #[arg(short, long, value_name = "NAME", conflicts_with_all = ["root", "days", "cameras", "sound_sources", "iterable"])]
+    pub folder: Vec<String>,
+    /// Queries all folders whose path from the project folder down (e.g.
+    /// Demo/02_RUSHES/01_DAY01) matches this regex. Can be repeated.
+    #[arg(long, value_name = "REGEX", conflicts_with_all = ["root", "days", "cameras", "sound_sources", "iterable"])]
+    pub folder_regex: Vec<String>,
+    /// Queries all folders generated for this folder ID from file_structure (e.g. video). Can be
+    /// repeated.
+    #[arg(long, value_name = "ID", conflicts_with_all = ["root", "days", "cameras", "sound_sources", "iterable"])]
+    pub folder_id: Vec<String>,
+    /// Sorts the results by size.
+    #[arg(long, conflicts_with = "sort_default")]
+    pub sort_size: bool,
+    /// Keeps the results in the default order.
+    #[arg(long)]
+    pub sort_default: bool,
+    /// Stops nanopm from combining all days into one entry for cameras and sound sources.
+    #[arg(short, long)]
+    pub unique: bool,
+    /// Hides warnings about folders that don't exist.
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// Records when the query was made.
+    #[arg(short, long)]
+    pub timestamp: bool,
+    /// Includes how long each query took.
+    #[arg(long)]
+    pub runtime: bool,
+    /// Writes the results to a file, optionally with a custom name.
+    #[arg(short, long, value_name = "NAME", num_args = 0..=1, default_missing_value = "")]
+    pub write: Option<String>,
+    /// The output format: text, toml, json or csv.
+    #[arg(long, value_name = "FORMAT", value_parser = OutputFormat::parse, default_value = "text")]
+    pub format: OutputFormat,
+    /// Rescans every folder instead of only those changed since the last query.
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Splits the files of every result by extension.
+    #[arg(long, conflicts_with = "by_kind")]
+    pub by_extension: bool,
+    /// Splits the files of every result into video, audio, image, project, sidecar and other,
+    /// going by the [media] extension table in the config.
+    #[arg(long)]
+    pub by_kind: bool,
+    /// Reads the headers of every video and audio clip (MP4/MOV and WAV/BWF) to total the
+    /// runtime, formats and unreadable clips of each day, camera and sound source.
+    #[arg(long)]
+    pub footage: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Keeps running, re-running the query and redrawing it whenever files in the project
+    /// change. With --write, the same file is rewritten every time.
+    #[arg(long)]
+    pub watch: bool,
+    /// How long to collect changes before re-running, in milliseconds. A steady stream of
+    /// changes (e.g. a card being copied) re-runs the query once per window.
+    #[arg(long, value_name = "MS", default_value = "1000", requires = "watch")]
+    pub debounce: u64,
+    /// Polls for changes every SECS seconds instead of using file system notifications, e.g.
+    /// on network shares. nanopm falls back to polling on its own if notifications fail.
+    #[arg(long, value_name = "SECS", value_parser = parse_positive_integer, requires = "watch")]
+    pub poll: Option<usize>,
+}
+
+impl WatchArgs {
+    fn options(&self) -> Option<WatchOptions> {
+        self.watch.then(|| WatchOptions {
+            debounce: Duration::from_millis(self.debounce),
+            poll_interval: self.poll.map(|secs| Duration::from_secs(secs as u64)),
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(group(ArgGroup::new("target").required(true).args(["camera", "sound_source"])))]
+pub struct IngestArgs {
+    /// The directory to copy from.
+    #[arg(value_name = "SOURCE", required_unless_present = "source_flag")]
+    pub source: Option<String>,
+    /// The directory to copy from, as a flag.
+    #[arg(
+        long = "source",
+        id = "source_flag",
+        value_name = "SOURCE",
+        conflicts_with = "source"
+    )]
+    pub source_flag: Option<String>,
+    /// The shoot day to ingest into. Defaults to the day in the calendar the files were
+    /// recorded on.
+    #[arg(short, long, value_parser = parse_positive_integer)]
+    pub day: Option<usize>,
+    /// The camera to ingest into (1 = A_CAM, 2 = B_CAM, ...).
+    #[arg(short, long, value_parser = parse_positive_integer)]
+    pub camera: Option<usize>,
+    /// The sound source to ingest into (1 = A_REC, 2 = B_REC, ...).
+    #[arg(short, long, value_parser = parse_positive_integer)]
+    pub sound_source: Option<usize>,
+    /// The checksum used to verify copies: xxh64, md5 or sha256.
+    #[arg(long, value_parser = HashAlgorithm::parse, default_value = "xxh64")]
+    pub hash: HashAlgorithm,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// Only verifies the manifest of this shoot day.
+    #[arg(short, long, value_parser = parse_positive_integer)]
+    pub day: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct SortArgs {
+    /// The drop folder to sort. Defaults to the [sort] inbox of the config (00_INBOX).
+    pub inbox: Option<PathBuf>,
+    /// Only prints where every file would go.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Puts the files of the last sort back where they were.
+    #[arg(long, conflicts_with = "inbox")]
+    pub undo: bool,
+    /// The checksum recorded for files sorted into a shoot day: xxh64, md5 or sha256.
+    #[arg(long, value_parser = HashAlgorithm::parse, default_value = "xxh64")]
+    pub hash: HashAlgorithm,
+}
+
+#[derive(Debug, Args)]
+pub struct ArchiveArgs {
+    /// Where to write the archive. Defaults to <name>.tar (or .tar.zst) next to the config.
+    pub output: Option<PathBuf>,
+    /// Compresses the archive with zstd.
+    #[arg(short, long)]
+    pub zstd: bool,
+    /// The zstd compression level, from 1 (fastest) to 22 (smallest).
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..=22), default_value = "3", requires = "zstd")]
+    pub level: i32,
+    /// Leaves out the folders with this ID from file_structure (e.g. prerenders). They are
+    /// recreated empty on restore. Can be repeated.
+    #[arg(short = 'x', long, value_name = "ID")]
+    pub exclude: Vec<String>,
+    /// The checksum recorded in the manifest: xxh64, md5 or sha256.
+    #[arg(long, value_parser = HashAlgorithm::parse, default_value = "xxh64")]
+    pub hash: HashAlgorithm,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TemplatesCommand {
+    /// Lists built-in and user templates.
+    List,
+    /// Prints a template as TOML.
+    Show { name: String },
+    /// Saves the current project's structure as a user template.
+    Export {
+        name: String,
+        /// Describes the template in `templates list`.
+        #[arg(long, default_value = "")]
+        description: String,
+        /// Overwrites an existing template with the same name.
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct WorkspaceArgs {
+    /// The workspace registry (or the directory holding it) to use, instead of the nearest one
+    /// above the current directory.
+    #[arg(long, global = true, env = "NANOPM_WORKSPACE", value_name = "PATH")]
+    pub workspace: Option<PathBuf>,
+    #[command(subcommand)]
+    pub action: WorkspaceCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WorkspaceCommand {
+    /// Creates an empty nanopm_workspace.toml in the current (or --workspace) directory.
+    Init,
+    /// Registers project roots, or the current project if none are given.
+    Add { paths: Vec<PathBuf> },
+    /// Unregisters projects by the path they were registered as, or by their root.
+    #[command(visible_alias = "rm")]
+    Remove {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Lists the registered projects.
+    #[command(visible_alias = "ls")]
+    List,
+    /// Runs the same query in every registered project.
+    #[command(visible_alias = "q")]
+    Query(QueryArgs),
+    /// Totals the size, files, shoot days, cameras and sound sources of every project and the
+    /// whole workspace, biggest project first.
+    Totals {
+        /// Sets the output format.
+        #[arg(long, value_name = "FORMAT", value_parser = OutputFormat::parse, default_value = "text")]
+        format: OutputFormat,
+    },
+}
+
+/// Parses the command line (including the program name) into what `main`
+/// needs to run, loading and migrating the project config where the
+/// operation works on an existing project.
+pub fn parse<I, T>(args: I) -> Result<ParsedReturn, CliError>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let cli = Cli::try_parse_from(rewrite_legacy_flags(args))?;
+    let project = cli.project.as_deref();
+
+    match cli.command {
+        Command::New(args) => {
+            let mut config = Config::new_config();
+            if let Some(name) = &args.template {
+                let template =
+                    template::load_template(name, template::user_templates_dir().as_deref())
+                        .map_err(|e| CliError::Invalid(e.to_string()))?;
+                config.file_structure = template.file_structure;
+                config.general_query_params = template.general_query_params;
+                println!("Set template to: {}", name);
+            }
+            apply_setup_args(&mut config.setup, args.setup, "Set");
+            let root = project.map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+            Ok(ParsedReturn::Setup(OperationType::New, root, config))
+        }
+        Command::Update(args) => {
+            // Persisting a migration is left to `main`, which knows whether
+            // this is a dry run.
+            let root = locate_root(project)?;
+            let mut config = load_project_config(&root, false)?;
+            apply_setup_args(&mut config.setup, args, "Updated");
+            Ok(ParsedReturn::Setup(OperationType::Update, root, config))
+        }
+        Command::Query { args, watch } => {
+            let root = locate_root(project)?;
+            let config = load_project_config(&root, true)?;
+            let mut info = query_info(args, root, config);
+            info.watch = watch.options();
+            Ok(ParsedReturn::Query(info))
+        }
+        Command::Ingest(args) => {
+            let root = locate_root(project)?;
+            let config = load_project_config(&root, true)?;
+            let target = match (args.camera, args.sound_source) {
+                (Some(camera), _) => IngestTarget::Camera(camera),
+                (None, Some(sound_source)) => IngestTarget::SoundSource(sound_source),
+                (None, None) => unreachable!("clap requires --camera or --sound-source"),
+            };
+            Ok(ParsedReturn::Ingest(IngestInfo {
+                root,
+                source: args.source.or(args.source_flag).unwrap_or_default(),
+                day: args.day,
+                target,
+                hash_algorithm: args.hash,
+                config,
+            }))
+        }
+        Command::Verify(args) => {
+            let root = locate_root(project)?;
+            let config = load_project_config(&root, true)?;
+            Ok(ParsedReturn::Verify(VerifyInfo {
+                root,
+                day: args.day,
+                config,
+            }))
+        }
+        Command::Lint => {
+            let root = locate_root(project)?;
+            let config = load_project_config(&root, true)?;
+            Ok(ParsedReturn::Lint(LintInfo { root, config }))
+        }
+        Command::Sort(args) => {
+            let root = locate_root(project)?;
+            let config = load_project_config(&root, true)?;
+            Ok(ParsedReturn::Sort(SortInfo {
+                root,
+                config,
+                dry_run: args.dry_run,
+                action: if args.undo {
+                    SortAction::Undo
+                } else {
+                    SortAction::Sort(args.inbox)
+                },
+                hash_algorithm: args.hash,
+            }))
+        }
+        Command::Templates { action } => {
+            let action = match action.unwrap_or(TemplatesCommand::List) {
+                TemplatesCommand::List => TemplateAction::List,
+                TemplatesCommand::Show { name } => TemplateAction::Show(name),
+                TemplatesCommand::Export {
+                    name,
+                    description,
+                    force,
+                } => TemplateAction::Export {
+                    name,
+                    description,
+                    force,
+                },
+            };
+            // Only exporting reads the project.
+            let root = match action {
+                TemplateAction::Export { .. } => locate_root(project)?,
+                _ => PathBuf::from("."),
+            };
+            Ok(ParsedReturn::Templates(TemplatesInfo { root, action }))
+        }
+        Command::Workspace(args) => {
+            let action = match args.action {
+                WorkspaceCommand::Init => WorkspaceAction::Init,
+                WorkspaceCommand::Add { paths } if paths.is_empty() => {
+                    WorkspaceAction::Add(vec![locate_root(project)?])
+                }
+                WorkspaceCommand::Add { paths } => WorkspaceAction::Add(paths),
+                WorkspaceCommand::Remove { paths } => WorkspaceAction::Remove(paths),
+                WorkspaceCommand::List => WorkspaceAction::List,
+                WorkspaceCommand::Query(args) => {
+                    let (query, settings) = query_and_settings(args);
+                    WorkspaceAction::Query(query, settings)
+                }
+                WorkspaceCommand::Totals { format } => WorkspaceAction::Totals(format),
+            };
+            Ok(ParsedReturn::Workspace(WorkspaceInfo {
+                workspace: args.workspace,
+                action,
+            }))
+        }
+        Command::Archive(args) => {
+            let root = locate_root(project)?;
+            let config = load_project_config(&root, true)?;
+            Ok(ParsedReturn::Archive(ArchiveInfo {
+                root,
+                output: args.output,
+                compression: args.zstd.then_some(args.level),
+                exclude: args.exclude,
+                hash_algorithm: args.hash,
+                config,
+            }))
+        }
+        Command::Restore { archive } => Ok(ParsedReturn::Restore(RestoreInfo {
+            root: project.map_or_else(|| PathBuf::from("."), Path::to_path_buf),
+            archive,
+        })),
+        Command::Offload { id, days, to } => {
+            let root = locate_root(project)?;
+            let config = load_project_config(&root, true)?;
+            Ok(ParsedReturn::Tier(TierInfo {
+                root,
+                config,
+                action: TierAction::Offload {
+                    selection: TierSelection {
+                        id,
+                        days: days.unwrap_or_default(),
+                    },
+                    to,
+                },
+            }))
+        }
+        Command::Recall { id, days, .. } => {
+            let root = locate_root(project)?;
+            let config = load_project_config(&root, true)?;
+            Ok(ParsedReturn::Tier(TierInfo {
+                root,
+                config,
+                action: TierAction::Recall(id.map(|id| TierSelection {
+                    id,
+                    days: days.unwrap_or_default(),
+                })),
+            }))
+        }
+        Command::Completions { shell } => Ok(ParsedReturn::Completions(shell)),
+    }
+}
+
+pub fn print_completions(shell: Shell) {
+    let mut command = Cli::command();
+    clap_complete::generate(shell, &mut command, "nanopm", &mut std::io::stdout());
+}
+
+fn rewrite_legacy_flags<I, T>(args: I) -> Vec<OsString>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    args.into_iter()
+        .map(|arg| {
+            let arg = arg.into();
+            LEGACY_FLAGS
+                .iter()
+                .find(|(legacy, _)| arg == *legacy)
+                .map_or(arg, |(_, long)| OsString::from(long))
+        })
+        .collect()
+}
+
+fn locate_root(project: Option<&Path>) -> Result<PathBuf, CliError> {
+    project::locate_root(project).map_err(|e| CliError::ConfigLoad(e.to_string()))
+}
+
+fn load_project_config(root: &Path, persist_migration: bool) -> Result<Config, CliError> {
+    let (config, report) =
+        migration::load_config(&root.join(project::CONFIG_FILE), persist_migration)
+            .map_err(|e| CliError::ConfigLoad(e.to_string()))?;
+    if let Some(report) = report
+        && report.backup.is_some()
+    {
+        report.print();
+    }
+    Ok(config)
+}
+
+fn apply_setup_args(project: &mut ProjectSetup, args: SetupArgs, print_query: &str) {
+    if let Some(name) = args.name {
+        println!("{} project name to {}", print_query, name);
+        project.name = name;
+    }
+    if let Some(deadname) = args.deadname {
+        println!("{} deadname to: {}", print_query, deadname);
+        project.deadname = Some(deadname);
+    }
+    if let Some(days) = args.days {
+        println!("{} project days: {}", print_query, days);
+        project.days = days;
+    }
+    if let Some(cameras) = args.cameras {
+        println!("{} cameras to: {}", print_query, cameras);
+        project.cameras = cameras;
+    }
+    if let Some(sound_sources) = args.sound_sources {
+        println!("{} sound sources to: {}", print_query, sound_sources);
+        project.sound_sources = sound_sources;
+    }
+    if let Some(names) = args.camera_names {
+        println!("{} camera names to: {}", print_query, names);
+        project.camera_names = parse_name_list(&names);
+    }
+    if let Some(names) = args.sound_source_names {
+        println!("{} sound source names to: {}", print_query, names);
+        project.sound_source_names = parse_name_list(&names);
+    }
+    if let Some(template) = args.day_template {
+        println!("{} day template to: {}", print_query, template);
+        project.day_template = (!template.trim().is_empty()).then_some(template);
+    }
+    for (day, date) in args.date {
+        let shown = date.map_or("none".to_string(), |date| date.to_string());
+        println!("{} the date of day {} to: {}", print_query, day, shown);
+        project.set_shoot_day(day, |entry| entry.date = date);
+    }
+    for (day, location) in args.location {
+        let shown = location.as_deref().unwrap_or("none");
+        println!("{} the location of day {} to: {}", print_query, day, shown);
+        project.set_shoot_day(day, |entry| entry.location = location);
+    }
+    for (day, label) in args.label {
+        let shown = label.as_deref().unwrap_or("none");
+        println!("{} the label of day {} to: {}", print_query, day, shown);
+        project.set_shoot_day(day, |entry| entry.label = label);
+    }
+    if args.clean {
+        println!("Cleaning empty folders that are undefined!");
+        project.clean_project = true;
+    }
+    project.dry_run = args.dry_run;
+    project.plan_format = args.format;
+    project.force = args.force;
+    project.archive_orphans = args.archive;
+}
+
+fn query_info(args: QueryArgs, root: PathBuf, config: Config) -> QueryInfo {
+    let (query, settings) = query_and_settings(args);
+    QueryInfo {
+        root,
+        query,
+        settings,
+        config,
+        watch: None,
+    }
+}
+
+fn query_and_settings(args: QueryArgs) -> (Query, QuerySettings) {
+    let sort_type = if args.sort_size {
+        SortType::BySize
+    } else {
+        SortType::default_sort_type()
+    };
+
+    let mut partial_queries = Vec::new();
+    if args.root {
+        partial_queries.push(QueryType::Root);
+    }
+    if args.days {
+        partial_queries.push(QueryType::Days);
+    }
+    if args.cameras {
+        partial_queries.push(QueryType::Cams);
+    }
+    if args.sound_sources {
+        partial_queries.push(QueryType::Sound);
+    }
+    for id in &args.iterable {
+        let id = id.trim_start_matches('%');
+        partial_queries.push(match id {
+            iterable::DAYS => QueryType::Days,
+            iterable::CAMS => QueryType::Cams,
+            iterable::SOUND_SOURCES => QueryType::Sound,
+            _ => QueryType::Iterable(id.to_string()),
+        });
+    }
+
+    let folders: Vec<FolderPattern> = args
+        .folder
+        .iter()
+        .map(|folder| FolderPattern::parse(folder))
+        .chain(args.folder_regex.into_iter().map(FolderPattern::Regex))
+        .chain(args.folder_id.into_iter().map(FolderPattern::Id))
+        .collect();
+
+    let query = if !folders.is_empty() {
+        Query::Folder(folders, sort_type)
+    } else if !partial_queries.is_empty() {
+        Query::Partial(partial_queries, sort_type)
+    } else {
+        if !args.general && !args.quiet {
+            println!("No query type specified, defaulting to general query.");
+        }
+        Query::General(sort_type)
+    };
+
+    let settings = QuerySettings {
+        write: args.write.is_some(),
+        output_name: args.write.filter(|name| !name.is_empty()),
+        record_timestamp: args.timestamp,
+        unique_entries: args.unique,
+        quiet: args.quiet,
+        include_runtime: args.runtime,
+        format: args.format,
+        no_cache: args.no_cache,
+        breakdown: if args.by_extension {
+            Some(Breakdown::Extension)
+        } else if args.by_kind {
+            Some(Breakdown::Kind)
+        } else {
+            None
+        },
+        footage: args.footage,
+    };
+    (query, settings)
+}
+
+fn parse_positive_integer(arg: &str) -> Result<usize, String> {
+    match arg.parse::<usize>() {
+        Ok(0) => Err("must be greater than 0".to_string()),
+        Ok(n) => Ok(n),
+        Err(_) => Err("must be a positive integer".to_string()),
+    }
+}
+
+/// Splits a calendar argument like "2=2026-10-18" into the day and its value,
+/// which is `None` when empty.
+fn parse_day_text(arg: &str) -> Result<(usize, Option<String>), String> {
+    let (day, value) = arg
+        .split_once('=')
+        .ok_or("must look like DAY=VALUE, e.g. 2=2026-10-18")?;
+    let day = parse_positive_integer(day.trim()).map_err(|e| format!("the day {}", e))?;
+    let value = value.trim();
+    Ok((day, (!value.is_empty()).then(|| value.to_string())))
+}
+
+fn parse_day_date(arg: &str) -> Result<(usize, Option<NaiveDate>), String> {
+    let (day, date) = parse_day_text(arg)?;
+    let date = date
+        .map(|date| {
+            NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                .map_err(|_| format!("\"{}\" is not a date like 2026-10-18", date))
+        })
+        .transpose()?;
+    Ok((day, date))
+}
+
+/// Splits a comma-separated list of unit names. An empty argument clears the
+/// names so units fall back to their letters.
+fn parse_name_list(arg: &str) -> Vec<String> {
+    if arg.trim().is_empty() {
+        return Vec::new();
+    }
+    arg.split(',').map(|name| name.trim().to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_cli(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(rewrite_legacy_flags(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_key_value_and_legacy_flags() {
+        let Command::New(args) =
+            parse_cli(&["nanopm", "new", "--days=3", "-dn", "Old", "-fmt", "json"])
+                .unwrap()
+                .command
+        else {
+            panic!("Expected new");
+        };
+        assert_eq!(args.setup.days, Some(3));
+        assert_eq!(args.setup.deadname.as_deref(), Some("Old"));
+        assert_eq!(args.setup.format, OutputFormat::Json);
+
+        assert!(parse_cli(&["nanopm", "new", "--days=0"]).is_err());
+        assert!(parse_cli(&["nanopm", "update", "--template", "podcast"]).is_err());
+    }
+
+    #[test]
+    fn test_sort_args() {
+        let Command::Sort(args) = parse_cli(&["nanopm", "file-in", "card", "--dry-run"])
+            .unwrap()
+            .command
+        else {
+            panic!("Expected sort");
+        };
+        assert_eq!(args.inbox, Some(PathBuf::from("card")));
+        assert!(args.dry_run && !args.undo);
+        assert!(parse_cli(&["nanopm", "sort", "--undo"]).is_ok());
+        assert!(parse_cli(&["nanopm", "sort", "card", "--undo"]).is_err());
+        assert!(matches!(
+            parse_cli(&["nanopm", "check"]).unwrap().command,
+            Command::Lint
+        ));
+    }
+
+    #[test]
+    fn test_query_args() {
+        let Command::Query { args, .. } = parse_cli(&["nanopm", "q", "-dcu", "--sort-size", "-w"])
+            .unwrap()
+            .command
+        else {
+            panic!("Expected query");
+        };
+        let info = query_info(args, PathBuf::from("."), Config::new_config());
+        assert_eq!(
+            parse_cli(&["nanopm", "q", "-r", "--project=../shoot"])
+                .unwrap()
+                .project,
+            Some(PathBuf::from("../shoot"))
+        );
+        assert!(matches!(
+            info.query,
+            Query::Partial(ref types, SortType::BySize) if types == &[QueryType::Days, QueryType::Cams]
+        ));
+        assert!(info.settings.unique_entries);
+        assert!(info.settings.write);
+        assert!(info.settings.output_name.is_none());
+        assert!(!info.settings.no_cache);
+        assert!(info.settings.breakdown.is_none());
+        assert!(!info.settings.footage);
+        assert!(parse_cli(&["nanopm", "q", "--by-kind", "--by-extension"]).is_err());
+
+        assert!(parse_cli(&["nanopm", "q", "-g", "-r"]).is_err());
+        assert!(parse_cli(&["nanopm", "q", "-f", "01_VIDEO", "-d"]).is_err());
+        assert!(parse_cli(&["nanopm", "q", "--folder-id", "video", "-g"]).is_err());
+    }
+
+    #[test]
+    fn test_folder_patterns() {
+        let Command::Query { args, .. } = parse_cli(&[
+            "nanopm",
+            "q",
+            "-f",
+            "01_VIDEO",
+            "-f",
+            "*_CAM",
+            "--folder-regex",
+            "DAY0[12]",
+            "--folder-id=audio",
+        ])
+        .unwrap()
+        .command
+        else {
+            panic!("Expected query");
+        };
+        let info = query_info(args, PathBuf::from("."), Config::new_config());
+        let Query::Folder(folders, _) = info.query else {
+            panic!("Expected folder query");
+        };
+        assert_eq!(
+            folders,
+            vec![
+                FolderPattern::Name("01_VIDEO".to_string()),
+                FolderPattern::Glob("*_CAM".to_string()),
+                FolderPattern::Regex("DAY0[12]".to_string()),
+                FolderPattern::Id("audio".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_watch_args() {
+        let Command::Query { watch, .. } =
+            parse_cli(&["nanopm", "q", "-d", "--watch", "--debounce=250"])
+                .unwrap()
+                .command
+        else {
+            panic!("Expected query");
+        };
+        assert_eq!(
+            watch.options(),
+            Some(WatchOptions {
+                debounce: Duration::from_millis(250),
+                poll_interval: None,
+            })
+        );
+
+        assert!(parse_cli(&["nanopm", "q", "--poll", "5"]).is_err());
+        assert!(parse_cli(&["nanopm", "ws", "q", "--watch"]).is_err());
+    }
+
+    #[test]
+    fn test_ingest_requires_target() {
+        assert!(parse_cli(&["nanopm", "ingest", "/card", "-d", "1"]).is_err());
+        assert!(
+            parse_cli(&["nanopm", "ingest", "/card", "-d", "1", "-c", "1", "-s", "1"]).is_err()
+        );
+        assert!(parse_cli(&["nanopm", "ingest", "-src", "/card", "-d", "1", "-s", "2"]).is_ok());
+    }
+
+    #[test]
+    fn test_archive_args() {
+        let Command::Archive(args) = parse_cli(&[
+            "nanopm",
+            "archive",
+            "-z",
+            "-x",
+            "prerenders",
+            "--exclude=finals",
+        ])
+        .unwrap()
+        .command
+        else {
+            panic!("Expected archive");
+        };
+        assert!(args.zstd);
+        assert_eq!(args.level, 3);
+        assert_eq!(args.exclude, vec!["prerenders", "finals"]);
+
+        assert!(parse_cli(&["nanopm", "archive", "--level", "9"]).is_err());
+        assert!(parse_cli(&["nanopm", "archive", "-z", "--level", "23"]).is_err());
+    }
+
+    #[test]
+    fn test_tier_args() {
+        let Command::Offload { id, days, to } =
+            parse_cli(&["nanopm", "tier", "days", "-d", "1-3", "--to", "/cold"])
+                .unwrap()
+                .command
+        else {
+            panic!("Expected offload");
+        };
+        assert_eq!(id, "days");
+        assert_eq!(days, Some(vec![1, 2, 3]));
+        assert_eq!(to, Some(PathBuf::from("/cold")));
+
+        assert!(parse_cli(&["nanopm", "recall", "--all"]).is_ok());
+        assert!(parse_cli(&["nanopm", "recall"]).is_err());
+        assert!(parse_cli(&["nanopm", "recall", "days", "--all"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_positive_integer() {
+        assert_eq!(parse_positive_integer("5").unwrap(), 5);
+        assert!(parse_positive_integer("0").is_err());
+        assert!(parse_positive_integer("-1").is_err());
+        assert!(parse_positive_integer("abc").is_err());
+    }
+
+    #[test]
+    fn test_calendar_args() {
+        let Command::Update(args) = parse_cli(&[
+            "nanopm",
+            "update",
+            "--date",
+            "1=2026-10-17",
+            "--date=2=",
+            "--location",
+            "1=Pier 9",
+            "--day-template",
+            "{index:02}_{date}",
+        ])
+        .unwrap()
+        .command
+        else {
+            panic!("Expected update");
+        };
+        assert_eq!(
+            args.date,
+            vec![(1, NaiveDate::from_ymd_opt(2026, 10, 17)), (2, None)]
+        );
+        assert_eq!(args.location, vec![(1, Some("Pier 9".to_string()))]);
+
+        let mut setup = ProjectSetup::new();
+        setup.set_shoot_day(2, |entry| entry.label = Some("PICKUPS".to_string()));
+        setup.set_shoot_day(2, |entry| {
+            entry.date = NaiveDate::from_ymd_opt(2026, 10, 18)
+        });
+        apply_setup_args(&mut setup, args, "Updated");
+        assert_eq!(setup.day_template.as_deref(), Some("{index:02}_{date}"));
+        assert_eq!(
+            setup.day_on(NaiveDate::from_ymd_opt(2026, 10, 17).unwrap()),
+            Some(1)
+        );
+        assert_eq!(setup.shoot_day(2).unwrap().date, None);
+        assert_eq!(
+            setup.shoot_day(2).unwrap().label.as_deref(),
+            Some("PICKUPS")
+        );
+
+        assert!(parse_cli(&["nanopm", "update", "--date", "2026-10-17"]).is_err());
+        assert!(parse_cli(&["nanopm", "update", "--date", "0=2026-10-17"]).is_err());
+        assert!(parse_cli(&["nanopm", "update", "--date", "1=17/10/2026"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_name_list() {
+        assert_eq!(parse_name_list("A_FX6, B_GOPRO"), vec!["A_FX6", "B_GOPRO"]);
+        assert!(parse_name_list("").is_empty());
+    }
+}
@@ -1,17 +1,36 @@
 use core::mem::discriminant as tag;
-use std::{fmt, usize};
+use std::{fmt, path::PathBuf};
 
+use clap_complete::Shell;
 use config::{Config as ConfigLoader, File, FileFormat};
 use serde::{Deserialize, Serialize};
 use toml;
 
-use super::init::{OperationType, QueryParams, new_project_setup};
-use crate::{InitParams, ProjectSetup, init, util::util::get_version};
+use super::{
+    init::{self, OperationType, new_project_setup},
+    iterable::{self, Iterable},
+    lint::LintTable,
+    manifest::HashAlgorithm,
+    media::MediaTable,
+    pattern,
+    sort::SortTable,
+    tier::{TierSelection, Tiering},
+    watch::WatchOptions,
+};
+use crate::util::util::get_version;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
     pub version: String,
     pub setup: init::ProjectSetup,
+    #[serde(default, skip_serializing_if = "Tiering::is_empty")]
+    pub tiering: Tiering,
+    #[serde(default, skip_serializing_if = "MediaTable::is_default")]
+    pub media: MediaTable,
+    #[serde(default, skip_serializing_if = "LintTable::is_default")]
+    pub lint: LintTable,
+    #[serde(default, skip_serializing_if = "SortTable::is_default")]
+    pub sort: SortTable,
     pub file_structure: FileStructure,
     pub general_query_params: Vec<String>,
 }
@@ -19,31 +38,146 @@ pub struct Config {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct FileStructure {
     pub folders_list: Vec<Folder>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub iterables: Vec<Iterable>,
 }
 
 pub enum ParsedReturn {
-    None,
-    Config(Config),
+    Setup(OperationType, PathBuf, Config),
     Query(QueryInfo),
+    Ingest(IngestInfo),
+    Verify(VerifyInfo),
+    Lint(LintInfo),
+    Sort(SortInfo),
+    Templates(TemplatesInfo),
+    Workspace(WorkspaceInfo),
+    Archive(ArchiveInfo),
+    Restore(RestoreInfo),
+    Tier(TierInfo),
+    Completions(Shell),
 }
 
 pub struct QueryInfo {
+    pub root: PathBuf,
     pub query: Query,
     pub settings: QuerySettings,
     pub config: Config,
+    /// Keeps re-running the query as files change.
+    pub watch: Option<WatchOptions>,
 }
 
-impl QueryInfo {
-    pub fn new_query_info() -> Self {
-        QueryInfo {
-            query: Query::None,
-            settings: QuerySettings::default(),
-            config: Config::new_config(),
-        }
-    }
+pub struct IngestInfo {
+    pub root: PathBuf,
+    pub source: String,
+    /// Picked from the calendar by when the files were recorded if unset.
+    pub day: Option<usize>,
+    pub target: IngestTarget,
+    pub hash_algorithm: HashAlgorithm,
+    pub config: Config,
+}
+
+pub struct VerifyInfo {
+    pub root: PathBuf,
+    pub day: Option<usize>,
+    pub config: Config,
+}
+
+pub struct LintInfo {
+    pub root: PathBuf,
+    pub config: Config,
+}
+
+pub struct SortInfo {
+    pub root: PathBuf,
+    pub config: Config,
+    /// Only prints what would move.
+    pub dry_run: bool,
+    pub action: SortAction,
+    /// The checksum recorded in the day manifests for files sorted into a day.
+    pub hash_algorithm: HashAlgorithm,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortAction {
+    /// Files the given inbox, or `[sort] inbox` without one.
+    Sort(Option<PathBuf>),
+    /// Puts the files of the last sort back.
+    Undo,
+}
+
+pub struct ArchiveInfo {
+    pub root: PathBuf,
+    /// Defaults to `{name}.tar` (or `.tar.zst`) next to the config.
+    pub output: Option<PathBuf>,
+    /// zstd level, or `None` for a plain tar.
+    pub compression: Option<i32>,
+    pub exclude: Vec<String>,
+    pub hash_algorithm: HashAlgorithm,
+    pub config: Config,
+}
+
+pub struct RestoreInfo {
+    /// The directory to restore into, like the root of a new project.
+    pub root: PathBuf,
+    pub archive: PathBuf,
+}
+
+pub struct TierInfo {
+    pub root: PathBuf,
+    pub config: Config,
+    pub action: TierAction,
 }
 
 #[derive(Debug, Clone)]
+pub enum TierAction {
+    /// `to` replaces the offline root recorded in the config.
+    Offload {
+        selection: TierSelection,
+        to: Option<PathBuf>,
+    },
+    /// `None` recalls every offline folder.
+    Recall(Option<TierSelection>),
+}
+
+pub struct TemplatesInfo {
+    pub root: PathBuf,
+    pub action: TemplateAction,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TemplateAction {
+    List,
+    Show(String),
+    Export {
+        name: String,
+        description: String,
+        force: bool,
+    },
+}
+
+pub struct WorkspaceInfo {
+    /// `--workspace`/`NANOPM_WORKSPACE`, the registry or its directory.
+    pub workspace: Option<PathBuf>,
+    pub action: WorkspaceAction,
+}
+
+#[derive(Debug, Clone)]
+pub enum WorkspaceAction {
+    Init,
+    Add(Vec<PathBuf>),
+    Remove(Vec<PathBuf>),
+    List,
+    Query(Query, QuerySettings),
+    Totals(OutputFormat),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum IngestTarget {
+    Camera(usize),
+    SoundSource(usize),
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct QuerySettings {
     pub write: bool,
     pub output_name: Option<String>,
@@ -51,19 +185,22 @@ pub struct QuerySettings {
     pub unique_entries: bool,
     pub quiet: bool,
     pub include_runtime: bool,
+    pub format: OutputFormat,
+    /// Rescans the whole project instead of refreshing the scan cache.
+    pub no_cache: bool,
+    /// Splits every result's files by extension or media kind.
+    pub breakdown: Option<Breakdown>,
+    /// Reads the headers of every clip under day, camera and sound source
+    /// results to total their runtime.
+    pub footage: bool,
 }
 
-impl QuerySettings {
-    pub fn default() -> Self {
-        QuerySettings {
-            write: false,
-            output_name: None,
-            record_timestamp: false,
-            unique_entries: false,
-            quiet: false,
-            include_runtime: false,
-        }
-    }
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub enum Breakdown {
+    Extension,
+    /// By the `[media]` table: video, audio, image, project, sidecar and
+    /// other.
+    Kind,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -71,25 +208,52 @@ pub enum Query {
     None,
     General(SortType),
     Partial(Vec<QueryType>, SortType),
-    Folder(Vec<String>, SortType),
+    Folder(Vec<FolderPattern>, SortType),
 }
 
-impl PartialEq<Self> for Query {
-    fn eq(&self, rhs: &Self) -> bool {
-        tag(self) == tag(rhs)
+/// Which directories a folder query (or `general_query_params`) picks.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub enum FolderPattern {
+    /// A folder name, or the end of a path like `01_DAY01/01_VIDEO`, matched
+    /// by whole names.
+    Name(String),
+    /// Like `Name`, where `*` and `?` match within a name, e.g. `*_CAM`.
+    Glob(String),
+    /// Searched for in the path from the project folder down, e.g.
+    /// `Demo/02_RUSHES/01_DAY01/01_VIDEO`.
+    Regex(String),
+    /// Every folder generated for this ID from `file_structure`.
+    Id(String),
+}
+
+impl FolderPattern {
+    /// A name, or a glob if it holds wildcards.
+    pub fn parse(arg: &str) -> Self {
+        if pattern::is_glob(arg) {
+            FolderPattern::Glob(arg.to_string())
+        } else {
+            FolderPattern::Name(arg.to_string())
+        }
     }
 }
 
-impl Query {
-    pub fn get_sort_type(&self) -> &SortType {
+impl fmt::Display for FolderPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Query::General(sort_type) => sort_type,
-            Query::Partial(_, sort_type) => sort_type,
-            Query::Folder(_, sort_type) => sort_type,
-            Query::None => &SortType::ByDefaultOrder,
+            FolderPattern::Name(pattern) | FolderPattern::Glob(pattern) => write!(f, "{}", pattern),
+            FolderPattern::Regex(regex) => write!(f, "/{}/", regex),
+            FolderPattern::Id(id) => write!(f, "ID {}", id),
         }
     }
+}
+
+impl PartialEq<Self> for Query {
+    fn eq(&self, rhs: &Self) -> bool {
+        tag(self) == tag(rhs)
+    }
+}
 
+impl Query {
     pub fn get_default_general_query() -> Vec<String> {
         vec![
             String::from("01_DOCUMENTATION"),
@@ -118,9 +282,38 @@ impl SortType {
     pub fn default_sort_type() -> Self {
         SortType::ByDefaultOrder
     }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Toml,
+    Json,
+    Csv,
+}
 
-    pub fn any() -> Self {
-        SortType::None
+impl OutputFormat {
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        match arg.to_lowercase().as_str() {
+            "text" | "txt" => Ok(OutputFormat::Text),
+            "toml" => Ok(OutputFormat::Toml),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            other => Err(format!(
+                "\"{}\" is not a valid format! Expected one of: text, toml, json, csv",
+                other
+            )),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Toml => "toml",
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+        }
     }
 }
 
@@ -130,36 +323,35 @@ pub enum QueryType {
     Days,
     Cams,
     Sound,
+    Iterable(String),
 }
 
 impl FileStructure {
-    fn get_default_structure() -> Self {
-        let mut folders = Vec::new();
-
-        folders.push(Folder::new("doc", None, "01_DOCUMENTATION"));
-        folders.push(Folder::new("rushes", None, "02_RUSHES"));
-        folders.push(Folder::new("external", None, "03_EXTERNAL"));
-        folders.push(Folder::new("prerenders", None, "04_PRE-RENDERS"));
-        folders.push(Folder::new("finals", None, "05_FINALS"));
-
-        folders.push(Folder::new("prepro", Some("doc"), "01_PRE-PRO"));
-        folders.push(Folder::new("production", Some("doc"), "02_PRODUCTION"));
-
-        folders.push(Folder::new("days", Some("rushes"), "%days"));
-        folders.push(Folder::new("video", Some("days"), "01_VIDEO"));
-        folders.push(Folder::new("audio", Some("days"), "02_AUDIO"));
-        folders.push(Folder::new("vo", Some("days"), "03_VO"));
-        folders.push(Folder::new("cams", Some("video"), "%cams"));
-        folders.push(Folder::new("soundsources", Some("audio"), "%soundsources"));
-
-        folders.push(Folder::new("graphics", Some("external"), "01_GRAPHICS"));
-        folders.push(Folder::new("images", Some("external"), "02_IMAGES"));
-        folders.push(Folder::new("music", Some("external"), "03_MUSIC"));
-        folders.push(Folder::new("sfx", Some("external"), "04_SFX"));
-        folders.push(Folder::new("comps", Some("external"), "05_COMPS"));
+    pub fn get_default_structure() -> Self {
+        let folders = vec![
+            Folder::new("doc", None, "01_DOCUMENTATION"),
+            Folder::new("rushes", None, "02_RUSHES"),
+            Folder::new("external", None, "03_EXTERNAL"),
+            Folder::new("prerenders", None, "04_PRE-RENDERS"),
+            Folder::new("finals", None, "05_FINALS"),
+            Folder::new("prepro", Some("doc"), "01_PRE-PRO"),
+            Folder::new("production", Some("doc"), "02_PRODUCTION"),
+            Folder::new("days", Some("rushes"), "%days"),
+            Folder::new("video", Some("days"), "01_VIDEO"),
+            Folder::new("audio", Some("days"), "02_AUDIO"),
+            Folder::new("vo", Some("days"), "03_VO"),
+            Folder::new("cams", Some("video"), "%cams"),
+            Folder::new("soundsources", Some("audio"), "%soundsources"),
+            Folder::new("graphics", Some("external"), "01_GRAPHICS"),
+            Folder::new("images", Some("external"), "02_IMAGES"),
+            Folder::new("music", Some("external"), "03_MUSIC"),
+            Folder::new("sfx", Some("external"), "04_SFX"),
+            Folder::new("comps", Some("external"), "05_COMPS"),
+        ];
 
         FileStructure {
             folders_list: folders,
+            iterables: Vec::new(),
         }
     }
 }
@@ -200,35 +392,17 @@ impl Config {
         Ok(())
     }
 
-    pub fn read_config(file_path: &str) -> Result<Self, config::ConfigError> {
+    pub fn from_toml_str(text: &str) -> Result<Self, config::ConfigError> {
         let config_loader = ConfigLoader::builder()
-            .add_source(File::new(file_path, FileFormat::Toml))
+            .add_source(File::from_str(text, FileFormat::Toml))
             .build()?;
         config_loader.try_deserialize()
     }
 
     pub fn validate(&self) -> Result<(), ConfigError> {
-        if self.setup.name.trim().is_empty() {
-            return Err(ConfigError::ParseError(
-                "Project name cannot be empty".to_string(),
-            ));
-        }
-        if self.setup.days == 0 {
-            return Err(ConfigError::ParseError(
-                "Number of days must be greater than 0".to_string(),
-            ));
-        }
-        if self.setup.cameras == 0 {
-            return Err(ConfigError::ParseError(
-                "Number of cameras must be greater than 0".to_string(),
-            ));
-        }
-        if self.setup.sound_sources == 0 {
-            return Err(ConfigError::ParseError(
-                "Number of sound sources must be greater than 0".to_string(),
-            ));
-        }
-        Ok(())
+        self.setup.validate().map_err(ConfigError::ParseError)?;
+        iterable::validate_iterables(&self.file_structure.iterables)
+            .map_err(ConfigError::ParseError)
     }
 }
 
@@ -237,300 +411,21 @@ impl Config {
         Config {
             version: get_version(),
             setup: new_project_setup(),
+            tiering: Tiering::default(),
+            media: MediaTable::default(),
+            lint: LintTable::default(),
+            sort: SortTable::default(),
             file_structure: FileStructure::get_default_structure(),
             general_query_params: Query::get_default_general_query(),
         }
     }
 }
 
-pub fn parse_args(args: Vec<String>, load: bool, op_type: &OperationType) -> ParsedReturn {
-    let mut args_to_process = args.len().saturating_sub(2);
-    let mut arg_index: usize = 1;
-    let mut next_init_param = InitParams::None;
-    let mut next_query_param = QueryParams::None;
-
-    let mut project: ProjectSetup;
-    let structure: FileStructure;
-    let general_query_params: Vec<String>;
-
-    let mut query = Query::None;
-    let mut query_settings = QuerySettings::default();
-
-    if load {
-        let project_result = Config::read_config("config.toml");
-        project = match project_result {
-            Ok(config) => {
-                structure = config.file_structure;
-                general_query_params = config.general_query_params;
-                config.setup
-            }
-            Err(error) => {
-                eprintln!(
-                    "Line {}: Problem opening the file: {}
-                    \nIf the file was not found, consider reinitializing the project with nanopm \
-                     new (optionally add the argument -dn to consider an existing directory as \
-                     the project directory).",
-                    line!(),
-                    error
-                );
-                std::process::exit(2);
-            }
-        };
-    } else {
-        project = new_project_setup();
-        structure = FileStructure::get_default_structure();
-        general_query_params = Query::get_default_general_query();
-    }
-
-    while args_to_process > 0 && op_type != &OperationType::Query {
-        let print_query = if load { "Updated" } else { "Set" };
-
-        arg_index += 1;
-        let current_arg = &args[arg_index];
-
-        if next_init_param == InitParams::None {
-            match current_arg.as_str() {
-                "-n" | "--name" => next_init_param = InitParams::ProjName,
-                "-dn" | "--deadname" => next_init_param = InitParams::DeadName,
-                "-d" | "--days" => next_init_param = InitParams::Days,
-                "-c" | "--cameras" => next_init_param = InitParams::Cameras,
-                "-s" | "--sound-sources" => next_init_param = InitParams::SoundSources,
-                "-cl" | "--clean" => {
-                    println!("Cleaning empty folders that are undefined!");
-                    project.clean_project = true;
-                }
-                other => {
-                    eprintln!(
-                        "Error in parsing: \"{}\" is not a valid CLI argument!",
-                        other
-                    );
-                    std::process::exit(1);
-                }
-            }
-        } else {
-            match next_init_param {
-                InitParams::ProjName => {
-                    project.name = String::from(current_arg);
-                    println!("{} project name to {}", print_query, current_arg);
-                }
-                InitParams::DeadName => {
-                    project.deadname = Some(String::from(current_arg));
-                    println!("{} deadname to: {}", print_query, current_arg);
-                }
-                InitParams::Days => {
-                    project.days =
-                        parse_positive_integer(current_arg, "days").unwrap_or_else(|e| {
-                            eprintln!("{}", e);
-                            std::process::exit(1);
-                        });
-                    println!("{} project days: {}", print_query, current_arg);
-                }
-                InitParams::Cameras => {
-                    project.cameras = parse_positive_integer(current_arg, "cameras")
-                        .unwrap_or_else(|e| {
-                            eprintln!("{}", e);
-                            std::process::exit(1);
-                        });
-                    println!("{} cameras to: {}", print_query, current_arg);
-                }
-                InitParams::SoundSources => {
-                    project.sound_sources = parse_positive_integer(current_arg, "sound sources")
-                        .unwrap_or_else(|e| {
-                            eprintln!("{}", e);
-                            std::process::exit(1);
-                        });
-                    println!("{} sound sources to: {}", print_query, current_arg);
-                }
-                InitParams::None => {}
-            }
-            next_init_param = InitParams::None;
-        }
-        args_to_process = args_to_process.saturating_sub(1);
-    }
-
-    if next_init_param != InitParams::None && op_type != &OperationType::Query {
-        eprintln!(
-            "Parameter \"{}\" should be followed by {}!",
-            args[arg_index],
-            init::get_required_type_init(next_init_param, true)
-        );
-        std::process::exit(1);
-    }
-
-    let mut queries_to_run: Vec<QueryType> = Vec::new();
-    let mut folders_to_search: Vec<String> = Vec::new();
-
-    while args_to_process > 0 && op_type == &OperationType::Query {
-        arg_index += 1;
-        let current_arg = &args[arg_index];
-
-        if next_query_param == QueryParams::None {
-            match current_arg.as_str() {
-                "-w" | "--write" => {
-                    query_settings.write = true;
-                    next_query_param = QueryParams::OutputDir;
-                }
-                "-t" | "--timestamp" => query_settings.record_timestamp = true,
-                "-u" | "--unique" => query_settings.unique_entries = true,
-                "-q" | "--quiet" => query_settings.quiet = true,
-                "-rt" | "--runtime" => query_settings.include_runtime = true,
-                "-g" | "--general" => {
-                    if query == Query::None {
-                        query = Query::General(SortType::default_sort_type());
-                    } else {
-                        eprintln!("Cannot have more than one query type!");
-                        std::process::exit(1);
-                    }
-                }
-                "-ss" | "--sort-size" => {
-                    query = match query {
-                        Query::General(_) => Query::General(SortType::BySize),
-                        Query::Partial(queries, _) => Query::Partial(queries, SortType::BySize),
-                        Query::Folder(folders, _) => Query::Folder(folders, SortType::BySize),
-                        Query::None => {
-                            eprintln!("Please specify a query type before specifying a sort type!");
-                            std::process::exit(1);
-                        }
-                    };
-                }
-                "-sd" | "--sort-default" => {
-                    query = match query {
-                        Query::General(_) => Query::General(SortType::ByDefaultOrder),
-                        Query::Partial(queries, _) => {
-                            Query::Partial(queries, SortType::ByDefaultOrder)
-                        }
-                        Query::Folder(folders, _) => {
-                            Query::Folder(folders, SortType::ByDefaultOrder)
-                        }
-                        Query::None => {
-                            eprintln!("Please specify a query type before specifying a sort type!");
-                            std::process::exit(1);
-                        }
-                    };
-                }
-                "-r" | "--root" => {
-                    if query == Query::None || matches!(&query, Query::Partial(_, _)) {
-                        queries_to_run.push(QueryType::Root);
-                        query =
-                            Query::Partial(queries_to_run.clone(), query.get_sort_type().clone());
-                    } else {
-                        eprintln!("Cannot have more than one query type!");
-                        std::process::exit(1);
-                    }
-                }
-                "-d" | "--days" => {
-                    if query == Query::None || matches!(&query, Query::Partial(_, _)) {
-                        queries_to_run.push(QueryType::Days);
-                        query =
-                            Query::Partial(queries_to_run.clone(), query.get_sort_type().clone());
-                    } else {
-                        eprintln!("Cannot have more than one query type!");
-                        std::process::exit(1);
-                    }
-                }
-                "-c" | "--cameras" => {
-                    if query == Query::None || matches!(&query, Query::Partial(_, _)) {
-                        queries_to_run.push(QueryType::Cams);
-                        query =
-                            Query::Partial(queries_to_run.clone(), query.get_sort_type().clone());
-                    } else {
-                        eprintln!("Cannot have more than one query type!");
-                        std::process::exit(1);
-                    }
-                }
-                "-s" | "--sound-sources" => {
-                    if query == Query::None || matches!(&query, Query::Partial(_, _)) {
-                        queries_to_run.push(QueryType::Sound);
-                        query =
-                            Query::Partial(queries_to_run.clone(), query.get_sort_type().clone());
-                    } else {
-                        eprintln!("Cannot have more than one query type!");
-                        std::process::exit(1);
-                    }
-                }
-                "-f" | "--folder" => next_query_param = QueryParams::Folder,
-                other => {
-                    eprintln!(
-                        "Error in parsing: \"{}\" is not a valid CLI argument!",
-                        other
-                    );
-                    std::process::exit(1);
-                }
-            }
-        } else {
-            match next_query_param {
-                QueryParams::Folder => {
-                    if query == Query::None || matches!(&query, Query::Folder(_, _)) {
-                        folders_to_search.push(String::from(current_arg));
-                        query =
-                            Query::Folder(folders_to_search.clone(), query.get_sort_type().clone());
-                    } else {
-                        eprintln!("Cannot have more than one query type!");
-                        std::process::exit(1);
-                    }
-                }
-                QueryParams::OutputDir => {
-                    query_settings.output_name = Some(String::from(current_arg));
-                }
-                QueryParams::None => {}
-            }
-            next_query_param = QueryParams::None;
-        }
-        args_to_process = args_to_process.saturating_sub(1);
-    }
-
-    if next_query_param != QueryParams::None
-        && op_type == &OperationType::Query
-        && next_query_param != QueryParams::OutputDir
-    {
-        eprintln!(
-            "Parameter \"{}\" accepts values of {}!",
-            args[arg_index],
-            init::get_required_type_query(next_query_param, true)
-        );
-        std::process::exit(1);
-    }
-
-    if query == Query::None && op_type == &OperationType::Query {
-        query = Query::General(SortType::ByDefaultOrder);
-        if !query_settings.quiet {
-            println!("No query type specified, defaulting to general query.");
-        }
-    }
-
-    if op_type != &OperationType::Query {
-        ParsedReturn::Config(Config {
-            version: get_version(),
-            setup: project,
-            file_structure: structure,
-            general_query_params,
-        })
-    } else {
-        ParsedReturn::Query(QueryInfo {
-            query,
-            settings: query_settings,
-            config: Config {
-                version: get_version(),
-                setup: project,
-                file_structure: structure,
-                general_query_params,
-            },
-        })
-    }
-}
-
-fn parse_positive_integer(arg: &str, param_name: &str) -> Result<usize, String> {
-    match arg.parse::<usize>() {
-        Ok(0) => Err(format!("{} must be greater than 0", param_name)),
-        Ok(n) => Ok(n),
-        Err(_) => Err(format!("{} must be a positive integer", param_name)),
-    }
-}
-
 #[derive(Debug)]
 pub enum ConfigError {
     IoError(std::io::Error),
     ParseError(String),
+    UnsafeUpdate(String),
 }
 
 impl fmt::Display for ConfigError {
@@ -538,6 +433,7 @@ impl fmt::Display for ConfigError {
         match self {
             ConfigError::IoError(e) => write!(f, "IO error: {}", e),
             ConfigError::ParseError(msg) => write!(f, "Parsing error: {}", msg),
+            ConfigError::UnsafeUpdate(msg) => write!(f, "Unsafe update: {}", msg),
         }
     }
 }
@@ -549,11 +445,12 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_parse_positive_integer() {
-        assert_eq!(parse_positive_integer("5", "test").unwrap(), 5);
-        assert!(parse_positive_integer("0", "test").is_err());
-        assert!(parse_positive_integer("-1", "test").is_err());
-        assert!(parse_positive_integer("abc", "test").is_err());
+    fn test_output_format_parse() {
+        assert_eq!(OutputFormat::parse("json").unwrap(), OutputFormat::Json);
+        assert_eq!(OutputFormat::parse("CSV").unwrap(), OutputFormat::Csv);
+        assert_eq!(OutputFormat::parse("txt").unwrap(), OutputFormat::Text);
+        assert!(OutputFormat::parse("xml").is_err());
+        assert_eq!(OutputFormat::Toml.extension(), "toml");
     }
 
     #[test]
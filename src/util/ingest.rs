@@ -0,0 +1,378 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    cam_folder_name,
+    config::{Config, ConfigError, IngestInfo, IngestTarget},
+    day_folder_name, generate_folder_paths,
+    init::ProjectSetup,
+    manifest::{Manifest, ManifestEntry, VerifyError, copy_and_hash, hash_file},
+    query::to_shorthand,
+    sound_folder_name,
+};
+
+use super::{media::MediaKind, metadata};
+
+#[derive(Debug)]
+pub enum IngestError {
+    IoError(std::io::Error),
+    WalkDirError(walkdir::Error),
+    ConfigError(ConfigError),
+    VerifyError(VerifyError),
+    InvalidIngest(String),
+    WouldOverwrite(Vec<String>),
+    ChecksumMismatch(String),
+}
+
+impl std::fmt::Display for IngestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IngestError::IoError(e) => write!(f, "IO error: {}", e),
+            IngestError::WalkDirError(e) => write!(f, "Filesystem error: {}", e),
+            IngestError::ConfigError(e) => write!(f, "Config error: {}", e),
+            IngestError::VerifyError(e) => write!(f, "Verification error: {}", e),
+            IngestError::InvalidIngest(msg) => write!(f, "Invalid ingest: {}", msg),
+            IngestError::WouldOverwrite(paths) => write!(
+                f,
+                "Refusing to overwrite {} existing file(s):\n    {}",
+                paths.len(),
+                paths.join("\n    ")
+            ),
+            IngestError::ChecksumMismatch(path) => write!(
+                f,
+                "Checksum of \"{}\" does not match its source after copying",
+                path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IngestError {}
+
+impl From<ConfigError> for IngestError {
+    fn from(error: ConfigError) -> Self {
+        IngestError::ConfigError(error)
+    }
+}
+
+impl From<VerifyError> for IngestError {
+    fn from(error: VerifyError) -> Self {
+        IngestError::VerifyError(error)
+    }
+}
+
+impl From<std::io::Error> for IngestError {
+    fn from(error: std::io::Error) -> Self {
+        IngestError::IoError(error)
+    }
+}
+
+impl From<walkdir::Error> for IngestError {
+    fn from(error: walkdir::Error) -> Self {
+        IngestError::WalkDirError(error)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestSummary {
+    pub destination: String,
+    pub file_count: usize,
+    pub total_size_u64: u64,
+}
+
+pub fn ingest(ingest_info: IngestInfo) -> Result<IngestSummary, IngestError> {
+    let source = Path::new(&ingest_info.source);
+    if !source.is_dir() {
+        return Err(IngestError::InvalidIngest(format!(
+            "Source \"{}\" is not a directory",
+            ingest_info.source
+        )));
+    }
+
+    let day = match ingest_info.day {
+        Some(day) => day,
+        None => {
+            let day = day_from_timestamps(source, &ingest_info.config)?;
+            println!(
+                "Ingesting into day {}, going by when the files were recorded",
+                day
+            );
+            day
+        }
+    };
+    let relative_destination = resolve_destination(&ingest_info.config, day, &ingest_info.target)?;
+    let destination = ingest_info.root.join(&relative_destination);
+    let files = collect_files(source, &destination)?;
+
+    let conflicts: Vec<String> = files
+        .iter()
+        .filter(|(_, to)| to.exists())
+        .map(|(_, to)| to.to_string_lossy().replace("\\", "/"))
+        .collect();
+    if !conflicts.is_empty() {
+        return Err(IngestError::WouldOverwrite(conflicts));
+    }
+
+    let day_folder = ingest_info.root.join(day_folder_of(
+        &ingest_info.config.setup,
+        &relative_destination,
+        day,
+    ));
+    let mut manifest = Manifest::load_or_new(&day_folder)?;
+    let hash_algorithm = ingest_info.hash_algorithm;
+
+    let mut total_size_u64 = 0;
+    for (from, to) in &files {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let (size, source_hash) = copy_and_hash(from, to, hash_algorithm)?;
+        total_size_u64 += size;
+        let destination_hash = hash_file(to, hash_algorithm)?;
+
+        if source_hash != destination_hash {
+            // Left in place, the bad copy would block the retry as an overwrite.
+            fs::remove_file(to)?;
+            manifest.write(&day_folder)?;
+            return Err(IngestError::ChecksumMismatch(
+                to.to_string_lossy().replace("\\", "/"),
+            ));
+        }
+        manifest.record(ManifestEntry::from_file(
+            to,
+            &day_folder,
+            destination_hash,
+            hash_algorithm,
+        )?);
+    }
+    manifest.write(&day_folder)?;
+
+    let summary = IngestSummary {
+        destination: destination.to_string_lossy().replace("\\", "/"),
+        file_count: files.len(),
+        total_size_u64,
+    };
+
+    println!(
+        "Ingested and verified {} file(s) ({}) from \"{}\" into \"{}\"",
+        summary.file_count,
+        to_shorthand(summary.total_size_u64),
+        ingest_info.source,
+        summary.destination
+    );
+
+    Ok(summary)
+}
+
+/// Finds the generated folder for the given day and camera/sound source, so
+/// ingest always lands in a path `update` would have created.
+pub fn resolve_destination(
+    config: &Config,
+    day: usize,
+    target: &IngestTarget,
+) -> Result<String, IngestError> {
+    if day > config.setup.days {
+        return Err(IngestError::InvalidIngest(format!(
+            "Day {} is outside of the project's {} day(s)",
+            day, config.setup.days
+        )));
+    }
+
+    let cameras = config.setup.cameras_on_day(day);
+    let sound_sources = config.setup.sound_sources_on_day(day);
+    let unit_folder = match target {
+        IngestTarget::Camera(index) if *index <= cameras => cam_folder_name(&config.setup, *index),
+        IngestTarget::Camera(index) => {
+            return Err(IngestError::InvalidIngest(format!(
+                "Camera {} is outside of day {}'s {} camera(s)",
+                index, day, cameras
+            )));
+        }
+        IngestTarget::SoundSource(index) if *index <= sound_sources => {
+            sound_folder_name(&config.setup, *index)
+        }
+        IngestTarget::SoundSource(index) => {
+            return Err(IngestError::InvalidIngest(format!(
+                "Sound source {} is outside of day {}'s {} sound source(s)",
+                index, day, sound_sources
+            )));
+        }
+    };
+    let day_folder = day_folder_name(&config.setup, day);
+
+    generate_folder_paths(config)?
+        .into_iter()
+        .find(|path| {
+            let mut components = path.rsplit('/');
+            components.next() == Some(unit_folder.as_str())
+                && components.any(|component| component == day_folder)
+        })
+        .ok_or_else(|| {
+            IngestError::InvalidIngest(format!(
+                "The file structure has no \"{}\" folder inside \"{}\"",
+                unit_folder, day_folder
+            ))
+        })
+}
+
+/// Cuts `destination` back to the day folder it sits in, which is where the
+/// day's manifest lives.
+fn day_folder_of(setup: &ProjectSetup, destination: &str, day: usize) -> PathBuf {
+    let day_folder = day_folder_name(setup, day);
+    let mut path = PathBuf::new();
+    for component in destination.split('/') {
+        path.push(component);
+        if component == day_folder {
+            break;
+        }
+    }
+    path
+}
+
+/// The calendar day most of the clips (or, without clips, the files) under
+/// `source` were recorded on.
+fn day_from_timestamps(source: &Path, config: &Config) -> Result<usize, IngestError> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(source) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            files.push(entry.into_path());
+        }
+    }
+    let clips: Vec<&PathBuf> = files
+        .iter()
+        .filter(|file| {
+            matches!(
+                config.media.kind_of(file),
+                MediaKind::Video | MediaKind::Audio
+            )
+        })
+        .collect();
+    let files = if clips.is_empty() {
+        files.iter().collect()
+    } else {
+        clips
+    };
+
+    let mut days: BTreeMap<usize, usize> = BTreeMap::new();
+    for file in files {
+        if let Some(day) = metadata::recorded_date(file).and_then(|date| config.setup.day_on(date))
+        {
+            *days.entry(day).or_default() += 1;
+        }
+    }
+    // Ties go to the earlier day.
+    days.into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(day, _)| day)
+        .ok_or_else(|| {
+            IngestError::InvalidIngest(format!(
+                "None of the files in \"{}\" were recorded on a day in the calendar, pass --day",
+                source.display()
+            ))
+        })
+}
+
+/// Pairs every file under `source` with its destination, keeping the
+/// source's relative layout.
+fn collect_files(
+    source: &Path,
+    destination: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>, IngestError> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(source).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(source)
+            .map_err(|e| IngestError::InvalidIngest(e.to_string()))?;
+        files.push((entry.path().to_path_buf(), destination.join(relative)));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_destination() {
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.setup.days = 3;
+
+        assert_eq!(
+            resolve_destination(&config, 2, &IngestTarget::Camera(1)).unwrap(),
+            "Demo/02_RUSHES/02_DAY02/01_VIDEO/01_A_CAM"
+        );
+        assert_eq!(
+            resolve_destination(&config, 3, &IngestTarget::SoundSource(1)).unwrap(),
+            "Demo/02_RUSHES/03_DAY03/02_AUDIO/01_A_REC"
+        );
+        assert!(resolve_destination(&config, 4, &IngestTarget::Camera(1)).is_err());
+        assert!(resolve_destination(&config, 1, &IngestTarget::Camera(3)).is_err());
+
+        config.setup.day_overrides = vec![crate::util::init::DayOverride {
+            day: 3,
+            cameras: Some(1),
+            sound_sources: None,
+        }];
+        assert!(resolve_destination(&config, 2, &IngestTarget::Camera(2)).is_ok());
+        assert!(resolve_destination(&config, 3, &IngestTarget::Camera(2)).is_err());
+    }
+
+    #[test]
+    fn test_day_folder_of() {
+        assert_eq!(
+            day_folder_of(
+                &ProjectSetup::new(),
+                "Demo/02_RUSHES/02_DAY02/01_VIDEO/01_A_CAM",
+                2
+            ),
+            Path::new("Demo/02_RUSHES/02_DAY02")
+        );
+    }
+
+    #[test]
+    fn test_collect_files_preserves_layout() {
+        let source = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(source.path().join("DCIM/100CANON")).unwrap();
+        fs::write(source.path().join("DCIM/100CANON/clip001.mov"), b"video").unwrap();
+        fs::write(source.path().join("notes.txt"), b"notes").unwrap();
+
+        let files = collect_files(source.path(), Path::new("dest")).unwrap();
+        let destinations: Vec<PathBuf> = files.into_iter().map(|(_, to)| to).collect();
+        assert_eq!(
+            destinations,
+            vec![
+                Path::new("dest/DCIM/100CANON/clip001.mov").to_path_buf(),
+                Path::new("dest/notes.txt").to_path_buf(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_day_from_timestamps() {
+        let source = tempfile::TempDir::new().unwrap();
+        fs::write(source.path().join("clip001.mov"), b"video").unwrap();
+        let mut config = Config::new_config();
+        assert!(day_from_timestamps(source.path(), &config).is_err());
+
+        let today = chrono::Local::now().date_naive();
+        config
+            .setup
+            .set_shoot_day(2, |entry| entry.date = Some(today));
+        assert_eq!(day_from_timestamps(source.path(), &config).unwrap(), 2);
+        config.setup.days = 1;
+        assert!(day_from_timestamps(source.path(), &config).is_err());
+    }
+}
@@ -1,51 +1,12 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
-#[derive(Eq, PartialEq, Debug)]
-pub enum InitParams {
-    None,
-    ProjName,
-    DeadName,
-    Days,
-    Cameras,
-    SoundSources,
-}
-
-#[derive(Eq, PartialEq, Debug)]
-pub enum QueryParams {
-    None,
-    OutputDir,
-    Folder,
-}
+use super::{config::OutputFormat, iterable};
 
 #[derive(Eq, PartialEq, Debug)]
 pub enum OperationType {
-    None,
     New,
     Update,
-    Query,
-}
-
-impl InitParams {
-    pub fn _to_string(&self) -> String {
-        match self {
-            InitParams::None => String::from("None"),
-            InitParams::ProjName => String::from("ProjName"),
-            InitParams::DeadName => String::from("DeadName"),
-            InitParams::Days => String::from("Days"),
-            InitParams::Cameras => String::from("Cameras"),
-            InitParams::SoundSources => String::from("SoundSources"),
-        }
-    }
-}
-
-impl QueryParams {
-    pub fn _to_string(&self) -> String {
-        match self {
-            QueryParams::None => String::from("None"),
-            QueryParams::OutputDir => String::from("Output Directory"),
-            QueryParams::Folder => String::from("Folder"),
-        }
-    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -56,8 +17,28 @@ pub struct ProjectSetup {
     pub days: usize,
     pub cameras: usize,
     pub sound_sources: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub camera_names: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sound_source_names: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub day_overrides: Vec<DayOverride>,
+    /// Names the %days folders, see `iterable::render_day`. Defaults to
+    /// `{index:02}_DAY{index:02}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day_template: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calendar: Vec<ShootDay>,
     #[serde(skip_serializing, default)]
     pub clean_project: bool,
+    #[serde(skip_serializing, default)]
+    pub dry_run: bool,
+    #[serde(skip_serializing, default)]
+    pub force: bool,
+    #[serde(skip_serializing, default)]
+    pub archive_orphans: bool,
+    #[serde(skip_serializing, default)]
+    pub plan_format: OutputFormat,
 }
 
 impl Default for ProjectSetup {
@@ -74,7 +55,16 @@ impl ProjectSetup {
             days: 2,
             cameras: 2,
             sound_sources: 1,
+            camera_names: Vec::new(),
+            sound_source_names: Vec::new(),
+            day_overrides: Vec::new(),
+            day_template: None,
+            calendar: Vec::new(),
             clean_project: false,
+            dry_run: false,
+            force: false,
+            archive_orphans: false,
+            plan_format: OutputFormat::Text,
         }
     }
 
@@ -91,52 +81,207 @@ impl ProjectSetup {
         if self.sound_sources == 0 {
             return Err("Number of sound sources must be greater than 0".to_string());
         }
+        validate_unit_names(&self.camera_names, "Camera")?;
+        validate_unit_names(&self.sound_source_names, "Sound source")?;
+        for (i, day_override) in self.day_overrides.iter().enumerate() {
+            if day_override.day == 0 {
+                return Err("Day overrides must target a day greater than 0".to_string());
+            }
+            if day_override.cameras == Some(0) || day_override.sound_sources == Some(0) {
+                return Err(format!(
+                    "Day {} override must keep at least one camera and sound source",
+                    day_override.day
+                ));
+            }
+            if self.day_overrides[..i]
+                .iter()
+                .any(|other| other.day == day_override.day)
+            {
+                return Err(format!(
+                    "Day {} is overridden more than once",
+                    day_override.day
+                ));
+            }
+        }
+        for (i, shoot_day) in self.calendar.iter().enumerate() {
+            if shoot_day.day == 0 {
+                return Err("Calendar entries must target a day greater than 0".to_string());
+            }
+            if let Some(other) = self.calendar[..i].iter().find(|other| {
+                other.day == shoot_day.day || (other.date.is_some() && other.date == shoot_day.date)
+            }) {
+                return Err(if other.day == shoot_day.day {
+                    format!("Day {} is in the calendar more than once", shoot_day.day)
+                } else {
+                    format!(
+                        "Days {} and {} are both on {}",
+                        other.day,
+                        shoot_day.day,
+                        shoot_day.date.unwrap_or_default()
+                    )
+                });
+            }
+        }
+        iterable::check_day_template(self.day_template())?;
+        let names: Vec<String> = (1..=self.days)
+            .map(|day| iterable::render_day(self.day_template(), day, self.shoot_day(day)))
+            .collect();
+        for (i, name) in names.iter().enumerate() {
+            if name.is_empty() {
+                return Err(format!("The day template leaves day {} unnamed", i + 1));
+            }
+            if names[..i].contains(name) {
+                return Err(format!(
+                    "The day template names more than one day \"{}\"; use {{index}} in it",
+                    name
+                ));
+            }
+        }
         Ok(())
     }
-}
 
-pub fn new_project_setup() -> ProjectSetup {
-    ProjectSetup::new()
-}
+    pub fn day_template(&self) -> &str {
+        self.day_template
+            .as_deref()
+            .unwrap_or(iterable::DAY_TEMPLATE)
+    }
+
+    /// The calendar entry of `day`, if it has one.
+    pub fn shoot_day(&self, day: usize) -> Option<&ShootDay> {
+        self.calendar.iter().find(|shoot_day| shoot_day.day == day)
+    }
+
+    /// The shoot day falling on `date`, going by the calendar.
+    pub fn day_on(&self, date: NaiveDate) -> Option<usize> {
+        self.calendar
+            .iter()
+            .find(|shoot_day| shoot_day.date == Some(date) && shoot_day.day <= self.days)
+            .map(|shoot_day| shoot_day.day)
+    }
 
-pub fn get_required_type_init(operation: InitParams, readable: bool) -> String {
-    if readable {
-        match operation {
-            InitParams::ProjName => String::from("a String"),
-            InitParams::DeadName => String::from("a String"),
-            InitParams::Days => String::from("a positive integer"),
-            InitParams::Cameras => String::from("a positive integer"),
-            InitParams::SoundSources => String::from("a positive integer"),
-            InitParams::None => String::from("None"),
+    /// Sets (or with `None`, clears) a field of `day`'s calendar entry,
+    /// dropping entries left empty.
+    pub fn set_shoot_day(&mut self, day: usize, update: impl FnOnce(&mut ShootDay)) {
+        let index = match self.calendar.iter().position(|entry| entry.day == day) {
+            Some(index) => index,
+            None => {
+                self.calendar.push(ShootDay {
+                    day,
+                    ..Default::default()
+                });
+                self.calendar.sort_by_key(|entry| entry.day);
+                self.calendar
+                    .iter()
+                    .position(|entry| entry.day == day)
+                    .unwrap()
+            }
+        };
+        update(&mut self.calendar[index]);
+        self.calendar.retain(|entry| !entry.is_empty());
+    }
+
+    /// Label of camera `index` (1-based): its configured name, or `A_CAM`,
+    /// `B_CAM`, ... `AA_CAM` when unnamed.
+    pub fn camera_label(&self, index: usize) -> String {
+        match self.camera_names.get(index.wrapping_sub(1)) {
+            Some(name) => name.clone(),
+            None => format!("{}_CAM", crate::num_to_letters(index)),
         }
-    } else {
-        match operation {
-            InitParams::ProjName => String::from("String"),
-            InitParams::DeadName => String::from("String"),
-            InitParams::Days => String::from("usize"),
-            InitParams::Cameras => String::from("usize"),
-            InitParams::SoundSources => String::from("usize"),
-            InitParams::None => String::from("None"),
+    }
+
+    /// Label of sound source `index` (1-based): its configured name, or
+    /// `A_REC`, `B_REC`, ... `AA_REC` when unnamed.
+    pub fn sound_source_label(&self, index: usize) -> String {
+        match self.sound_source_names.get(index.wrapping_sub(1)) {
+            Some(name) => name.clone(),
+            None => format!("{}_REC", crate::num_to_letters(index)),
         }
     }
+
+    fn day_override(&self, day: usize) -> Option<&DayOverride> {
+        self.day_overrides.iter().find(|o| o.day == day)
+    }
+
+    /// Cameras used on `day`, falling back to the project-wide count.
+    pub fn cameras_on_day(&self, day: usize) -> usize {
+        self.day_override(day)
+            .and_then(|o| o.cameras)
+            .unwrap_or(self.cameras)
+    }
+
+    /// Sound sources used on `day`, falling back to the project-wide count.
+    pub fn sound_sources_on_day(&self, day: usize) -> usize {
+        self.day_override(day)
+            .and_then(|o| o.sound_sources)
+            .unwrap_or(self.sound_sources)
+    }
+
+    /// Highest camera count of any day in the project.
+    pub fn max_cameras(&self) -> usize {
+        (1..=self.days)
+            .map(|day| self.cameras_on_day(day))
+            .max()
+            .unwrap_or(self.cameras)
+    }
+
+    /// Highest sound source count of any day in the project.
+    pub fn max_sound_sources(&self) -> usize {
+        (1..=self.days)
+            .map(|day| self.sound_sources_on_day(day))
+            .max()
+            .unwrap_or(self.sound_sources)
+    }
 }
 
-pub fn get_required_type_query(operation: QueryParams, readable: bool) -> String {
-    if readable {
-        match operation {
-            QueryParams::Folder => String::from("a String"),
-            QueryParams::OutputDir => String::from("a String"),
-            QueryParams::None => String::from("None"),
+fn validate_unit_names(names: &[String], unit: &str) -> Result<(), String> {
+    for (i, name) in names.iter().enumerate() {
+        if name.trim().is_empty() {
+            return Err(format!("{} names cannot be empty", unit));
         }
-    } else {
-        match operation {
-            QueryParams::Folder => String::from("String"),
-            QueryParams::OutputDir => String::from("String"),
-            QueryParams::None => String::from("None"),
+        if name.contains(['/', '\\']) {
+            return Err(format!("{} name \"{}\" cannot contain slashes", unit, name));
         }
+        if names[..i].contains(name) {
+            return Err(format!("{} name \"{}\" is used more than once", unit, name));
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the project-wide camera and/or sound source count for one day,
+/// e.g. a pickup day shot on a single camera.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DayOverride {
+    pub day: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cameras: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound_sources: Option<usize>,
+}
+
+/// The date, place and label of a shoot day. Day templates can put them in
+/// the day's folder name, and ingest/sort use the dates to pick a day.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ShootDay {
+    pub day: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl ShootDay {
+    fn is_empty(&self) -> bool {
+        self.date.is_none() && self.location.is_none() && self.label.is_none()
     }
 }
 
+pub fn new_project_setup() -> ProjectSetup {
+    ProjectSetup::new()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,6 +307,82 @@ mod tests {
         assert!(setup.validate().is_err());
     }
 
+    #[test]
+    fn test_day_overrides() {
+        let mut setup = ProjectSetup::new();
+        setup.days = 3;
+        setup.day_overrides = vec![DayOverride {
+            day: 3,
+            cameras: Some(1),
+            sound_sources: None,
+        }];
+        assert!(setup.validate().is_ok());
+        assert_eq!(setup.cameras_on_day(1), 2);
+        assert_eq!(setup.cameras_on_day(3), 1);
+        assert_eq!(setup.sound_sources_on_day(3), 1);
+        assert_eq!(setup.max_cameras(), 2);
+
+        setup.day_overrides[0].cameras = Some(4);
+        assert_eq!(setup.max_cameras(), 4);
+
+        setup.day_overrides.push(setup.day_overrides[0].clone());
+        assert!(setup.validate().is_err());
+
+        setup.day_overrides.pop();
+        setup.day_overrides[0].cameras = Some(0);
+        assert!(setup.validate().is_err());
+    }
+
+    #[test]
+    fn test_calendar() {
+        let mut setup = ProjectSetup::new();
+        setup.days = 3;
+        let date = |day| NaiveDate::from_ymd_opt(2026, 10, day).unwrap();
+        setup.set_shoot_day(2, |entry| entry.date = Some(date(18)));
+        setup.set_shoot_day(1, |entry| entry.date = Some(date(17)));
+        setup.set_shoot_day(1, |entry| entry.label = Some("Pickups".to_string()));
+        assert_eq!(setup.calendar.len(), 2);
+        assert_eq!(setup.calendar[0].day, 1);
+        assert_eq!(setup.day_on(date(18)), Some(2));
+        assert_eq!(setup.day_on(date(19)), None);
+        assert!(setup.validate().is_ok());
+
+        setup.set_shoot_day(2, |entry| entry.date = None);
+        assert!(setup.shoot_day(2).is_none());
+
+        setup.set_shoot_day(3, |entry| entry.date = Some(date(17)));
+        assert!(setup.validate().is_err());
+        setup.set_shoot_day(3, |entry| entry.date = Some(date(19)));
+
+        setup.day_template = Some("{date}".to_string());
+        assert!(setup.validate().is_err());
+        setup.day_template = Some("{index:02}_{date}_{location}".to_string());
+        assert!(setup.validate().is_ok());
+        setup.day_template = Some("{index:02}_{weather}".to_string());
+        assert!(setup.validate().is_err());
+    }
+
+    #[test]
+    fn test_unit_labels() {
+        let mut setup = ProjectSetup::new();
+        assert_eq!(setup.camera_label(1), "A_CAM");
+        assert_eq!(setup.camera_label(28), "AB_CAM");
+        assert_eq!(setup.sound_source_label(2), "B_REC");
+
+        setup.camera_names = vec!["A_FX6".to_string(), "B_GOPRO".to_string()];
+        setup.sound_source_names = vec!["BOOM".to_string()];
+        assert!(setup.validate().is_ok());
+        assert_eq!(setup.camera_label(2), "B_GOPRO");
+        assert_eq!(setup.camera_label(3), "C_CAM");
+        assert_eq!(setup.sound_source_label(1), "BOOM");
+
+        setup.sound_source_names.push("BOOM".to_string());
+        assert!(setup.validate().is_err());
+
+        setup.sound_source_names = vec!["LAV/1".to_string()];
+        assert!(setup.validate().is_err());
+    }
+
     #[test]
     fn test_default_project_setup() {
         let setup = ProjectSetup::default();
@@ -170,19 +391,7 @@ mod tests {
         assert_eq!(setup.cameras, 2);
         assert_eq!(setup.sound_sources, 1);
         assert!(!setup.clean_project);
+        assert!(!setup.dry_run);
         assert!(setup.deadname.is_none());
     }
-
-    #[test]
-    fn test_get_required_type_functions() {
-        assert_eq!(
-            get_required_type_init(InitParams::Days, true),
-            "a positive integer"
-        );
-        assert_eq!(get_required_type_init(InitParams::Days, false), "usize");
-        assert_eq!(
-            get_required_type_query(QueryParams::Folder, true),
-            "a String"
-        );
-    }
 }
@@ -0,0 +1,331 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    init::{ProjectSetup, ShootDay},
+    num_to_letters,
+    util::util::sanitize_filename,
+};
+
+pub const DAYS: &str = "days";
+pub const CAMS: &str = "cams";
+pub const SOUND_SOURCES: &str = "soundsources";
+
+pub const DAY_TEMPLATE: &str = "{index:02}_DAY{index:02}";
+pub const UNIT_TEMPLATE: &str = "{index:02}_{name}";
+
+/// A placeholder folder (`%<id>`) that expands into `count` folders named
+/// after `template`. Templates understand `{index}`, `{index:0N}` (zero
+/// padded to N digits), `{letter}` (A, B, ... AA) and `{name}` (the matching
+/// entry of `names`, or the letter when there is none).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Iterable {
+    pub id: String,
+    pub count: usize,
+    pub template: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+}
+
+impl Iterable {
+    pub fn folder_name(&self, index: usize) -> String {
+        let name = self.names.get(index.wrapping_sub(1)).map(String::as_str);
+        render_template(&self.template, index, name)
+    }
+
+    pub fn folder_names(&self) -> Vec<String> {
+        (1..=self.count).map(|i| self.folder_name(i)).collect()
+    }
+
+    pub fn is_builtin(id: &str) -> bool {
+        matches!(id, DAYS | CAMS | SOUND_SOURCES)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!(
+                "Iterable ID \"{}\" may only contain letters, digits, '_' and '-'",
+                self.id
+            ));
+        }
+        if Self::is_builtin(&self.id) {
+            return Err(format!(
+                "Iterable ID \"{}\" is reserved for the built-in %{} iterable",
+                self.id, self.id
+            ));
+        }
+        if self.count == 0 {
+            return Err(format!(
+                "Iterable \"{}\" must have a count greater than 0",
+                self.id
+            ));
+        }
+        check_template(&self.template)
+            .map_err(|e| format!("Iterable \"{}\" has an invalid template: {}", self.id, e))?;
+
+        let names = self.folder_names();
+        for (i, name) in names.iter().enumerate() {
+            if name.trim().is_empty() || name.contains(['/', '\\']) {
+                return Err(format!(
+                    "Iterable \"{}\" produces the invalid folder name \"{}\"",
+                    self.id, name
+                ));
+            }
+            if names[..i].contains(name) {
+                return Err(format!(
+                    "Iterable \"{}\" produces \"{}\" more than once; use {{index}} in its template",
+                    self.id, name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Built-in iterables come from the project setup. Cameras and sound sources
+/// take the given day's overrides into account; outside of a day they cover
+/// every unit any day uses.
+pub fn builtin(id: &str, setup: &ProjectSetup, day: Option<usize>) -> Option<Iterable> {
+    let (count, names) = match id {
+        // Day names can carry calendar entries, so they are rendered up front.
+        DAYS => {
+            return Some(Iterable {
+                id: DAYS.to_string(),
+                count: setup.days,
+                template: "{name}".to_string(),
+                names: (1..=setup.days)
+                    .map(|day| render_day(setup.day_template(), day, setup.shoot_day(day)))
+                    .collect(),
+            });
+        }
+        CAMS => {
+            let count = day.map_or(setup.max_cameras(), |d| setup.cameras_on_day(d));
+            (count, (1..=count).map(|i| setup.camera_label(i)).collect())
+        }
+        SOUND_SOURCES => {
+            let count = day.map_or(setup.max_sound_sources(), |d| setup.sound_sources_on_day(d));
+            (
+                count,
+                (1..=count).map(|i| setup.sound_source_label(i)).collect(),
+            )
+        }
+        _ => return None,
+    };
+    Some(Iterable {
+        id: id.to_string(),
+        count,
+        template: UNIT_TEMPLATE.to_string(),
+        names,
+    })
+}
+
+/// Looks up `%<id>`, built-in or declared under `[[file_structure.iterables]]`.
+pub fn resolve(id: &str, config: &Config, day: Option<usize>) -> Option<Iterable> {
+    builtin(id, &config.setup, day).or_else(|| {
+        config
+            .file_structure
+            .iterables
+            .iter()
+            .find(|iterable| iterable.id == id)
+            .cloned()
+    })
+}
+
+pub fn validate_iterables(iterables: &[Iterable]) -> Result<(), String> {
+    for (i, iterable) in iterables.iter().enumerate() {
+        iterable.validate()?;
+        if iterables[..i].iter().any(|other| other.id == iterable.id) {
+            return Err(format!(
+                "Iterable \"{}\" is declared more than once",
+                iterable.id
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn render_template(template: &str, index: usize, name: Option<&str>) -> String {
+    render_tokens(template, |token| render_token(token, index, name))
+}
+
+/// Replaces every `{token}` `value` knows, leaving the rest as written. The
+/// values are never scanned for placeholders themselves.
+fn render_tokens(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rendered.push_str(&rest[start..]);
+            return rendered;
+        };
+        let token = &rest[start + 1..start + end];
+        match value(token) {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Renders a day template, which on top of the usual placeholders
+/// understands `{date}` (e.g. 2026-10-17), `{location}` and `{label}` from the
+/// day's calendar entry. Days missing one get it left out, along with the
+/// separators it leaves dangling at either end.
+pub fn render_day(template: &str, index: usize, shoot_day: Option<&ShootDay>) -> String {
+    let field = |value: Option<String>| value.map(|v| sanitize_filename(&v)).unwrap_or_default();
+    render_tokens(template, |token| match token {
+        "date" => Some(field(
+            shoot_day.and_then(|d| d.date).map(|date| date.to_string()),
+        )),
+        "location" => Some(field(shoot_day.and_then(|d| d.location.clone()))),
+        "label" => Some(field(shoot_day.and_then(|d| d.label.clone()))),
+        _ => render_token(token, index, None),
+    })
+    .trim_matches(|c: char| c == '_' || c == '-' || c.is_whitespace())
+    .to_string()
+}
+
+pub fn check_day_template(template: &str) -> Result<(), String> {
+    let placeholders = ["{date}", "{location}", "{label}"]
+        .iter()
+        .fold(template.to_string(), |template, field| {
+            template.replace(field, "x")
+        });
+    check_template(&placeholders).map_err(|e| format!("Invalid day template: {}", e))
+}
+
+fn render_token(token: &str, index: usize, name: Option<&str>) -> Option<String> {
+    match token {
+        "index" => Some(index.to_string()),
+        "letter" => Some(num_to_letters(index)),
+        "name" => Some(name.map_or_else(|| num_to_letters(index), str::to_string)),
+        _ => {
+            let width = token.strip_prefix("index:0")?.parse::<usize>().ok()?;
+            Some(format!("{:0width$}", index, width = width))
+        }
+    }
+}
+
+fn check_template(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("template cannot be empty".to_string());
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed '{{' in \"{}\"", template))?;
+        let token = &rest[start + 1..start + end];
+        if render_token(token, 1, None).is_none() {
+            return Err(format!("unknown placeholder {{{}}}", token));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenes() -> Iterable {
+        Iterable {
+            id: "scenes".to_string(),
+            count: 3,
+            template: "{index:02}_SC{index:03}".to_string(),
+            names: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        assert_eq!(render_template(DAY_TEMPLATE, 3, None), "03_DAY03");
+        let shoot_day = ShootDay {
+            day: 3,
+            date: chrono::NaiveDate::from_ymd_opt(2026, 10, 17),
+            location: Some("Pier 9/Dock".to_string()),
+            label: None,
+        };
+        let template = "{index:02}_DAY{index:02}_{date}_{location}_{label}";
+        assert_eq!(
+            render_day(template, 3, Some(&shoot_day)),
+            "03_DAY03_2026-10-17_Pier 9_Dock"
+        );
+        assert_eq!(render_day(template, 4, None), "04_DAY04");
+        assert!(check_day_template(template).is_ok());
+        assert!(check_day_template("{weather}").is_err());
+        assert_eq!(
+            render_template("{index:02}_{letter}_CAM", 28, None),
+            "28_AB_CAM"
+        );
+        assert_eq!(render_template(UNIT_TEMPLATE, 2, Some("BOOM")), "02_BOOM");
+        assert_eq!(render_template("EP{index}", 7, None), "EP7");
+        assert_eq!(
+            scenes().folder_names(),
+            vec!["01_SC001", "02_SC002", "03_SC003"]
+        );
+    }
+
+    #[test]
+    fn test_render_template_unclosed_brace() {
+        assert_eq!(render_template("EP{index}_{draft", 7, None), "EP7_{draft");
+        assert_eq!(render_template("{", 1, None), "{");
+    }
+
+    #[test]
+    fn test_render_day_with_braces_in_fields() {
+        let shoot_day = ShootDay {
+            day: 1,
+            date: None,
+            location: Some("Room {index}".to_string()),
+            label: Some("Studio {A".to_string()),
+        };
+        assert_eq!(
+            render_day(
+                "{index:02}_DAY{index:02}_{location}_{label}",
+                1,
+                Some(&shoot_day)
+            ),
+            "01_DAY01_Room {index}_Studio {A"
+        );
+    }
+
+    #[test]
+    fn test_validate_iterables() {
+        assert!(validate_iterables(&[scenes()]).is_ok());
+        assert!(validate_iterables(&[scenes(), scenes()]).is_err());
+
+        let mut bad = scenes();
+        bad.template = "{index:02}_{scene}".to_string();
+        assert!(validate_iterables(&[bad]).is_err());
+
+        let mut bad = scenes();
+        bad.template = "SCENE".to_string();
+        assert!(validate_iterables(&[bad]).is_err());
+
+        let mut bad = scenes();
+        bad.id = DAYS.to_string();
+        assert!(validate_iterables(&[bad]).is_err());
+    }
+
+    #[test]
+    fn test_builtin_respects_setup() {
+        let mut setup = ProjectSetup::new();
+        setup.camera_names = vec!["A_FX6".to_string()];
+        let cams = builtin(CAMS, &setup, Some(1)).unwrap();
+        assert_eq!(cams.folder_names(), vec!["01_A_FX6", "02_B_CAM"]);
+        assert_eq!(
+            builtin(DAYS, &setup, None).unwrap().folder_names(),
+            vec!["01_DAY01", "02_DAY02"]
+        );
+        assert!(builtin("scenes", &setup, None).is_none());
+    }
+}
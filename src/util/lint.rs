@@ -0,0 +1,287 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use super::{
+    config::{Config, ConfigError, LintInfo},
+    media::MediaKind,
+    plan,
+};
+use crate::{folder_paths, generate_folder_paths};
+
+#[derive(Debug)]
+pub enum LintError {
+    IoError(std::io::Error),
+    ConfigError(ConfigError),
+    Failed(LintReport),
+}
+
+impl std::fmt::Display for LintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LintError::IoError(e) => write!(f, "IO error: {}", e),
+            LintError::ConfigError(e) => write!(f, "Config error: {}", e),
+            LintError::Failed(report) => write!(
+                f,
+                "{} misplaced file(s), {} loose file(s) and {} unexpected folder(s)",
+                report.misplaced.len(),
+                report.loose.len(),
+                report.unexpected_folders.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LintError {}
+
+impl From<std::io::Error> for LintError {
+    fn from(error: std::io::Error) -> Self {
+        LintError::IoError(error)
+    }
+}
+
+impl From<ConfigError> for LintError {
+    fn from(error: ConfigError) -> Self {
+        LintError::ConfigError(error)
+    }
+}
+
+impl From<walkdir::Error> for LintError {
+    fn from(error: walkdir::Error) -> Self {
+        LintError::IoError(error.into())
+    }
+}
+
+/// The `[lint]` table of the config: which kinds of files (by the `[media]`
+/// table) may go where. Rules for folder IDs the file structure doesn't have
+/// are skipped, so the defaults work for every template.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct LintTable {
+    /// Folders besides the file structure's that may sit directly in the
+    /// project folder. Hidden folders, the `[sort]` inbox and the folder
+    /// `update --archive` moves orphans to always may.
+    pub extra_folders: Vec<String>,
+    pub rules: Vec<LintRule>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LintRule {
+    /// The folder ID in the file structure, e.g. "cams".
+    pub id: String,
+    /// Kinds of files not allowed anywhere in the folder.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<MediaKind>,
+    /// Kinds of files not allowed directly in the folder, only further down.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny_loose: Vec<MediaKind>,
+}
+
+impl Default for LintTable {
+    fn default() -> Self {
+        let media = vec![MediaKind::Video, MediaKind::Audio, MediaKind::Image];
+        let rule = |id: &str, deny: Vec<MediaKind>, deny_loose: Vec<MediaKind>| LintRule {
+            id: id.to_string(),
+            deny,
+            deny_loose,
+        };
+        LintTable {
+            extra_folders: Vec::new(),
+            rules: vec![
+                rule("cams", vec![MediaKind::Audio], Vec::new()),
+                rule("soundsources", vec![MediaKind::Video], Vec::new()),
+                rule("rushes", Vec::new(), media.clone()),
+                rule("days", Vec::new(), media),
+            ],
+        }
+    }
+}
+
+impl LintTable {
+    pub fn is_default(&self) -> bool {
+        *self == LintTable::default()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LintReport {
+    pub checked: usize,
+    /// Files of a kind their folder denies, e.g. a .wav in a camera folder.
+    pub misplaced: Vec<String>,
+    /// Media sitting directly in a folder that should only hold subfolders.
+    pub loose: Vec<String>,
+    /// Folders in the project folder that aren't in the file structure.
+    pub unexpected_folders: Vec<String>,
+}
+
+impl LintReport {
+    pub fn is_clean(&self) -> bool {
+        self.misplaced.is_empty() && self.loose.is_empty() && self.unexpected_folders.is_empty()
+    }
+
+    pub fn print(&self) {
+        println!("Checked {} file(s) against the lint rules.", self.checked);
+        for (label, paths) in [
+            ("Misplaced", &self.misplaced),
+            ("Loose", &self.loose),
+            ("Unexpected folder", &self.unexpected_folders),
+        ] {
+            for path in paths {
+                println!("    {}: {}", label, path);
+            }
+        }
+    }
+}
+
+/// Checks the project under `lint_info.root` against the `[lint]` table of its
+/// config. Fails with the report if anything is out of place.
+pub fn lint(lint_info: LintInfo) -> Result<LintReport, LintError> {
+    let report = check(&lint_info.root, &lint_info.config)?;
+    report.print();
+
+    if report.is_clean() {
+        Ok(report)
+    } else {
+        Err(LintError::Failed(report))
+    }
+}
+
+pub fn check(root: &Path, config: &Config) -> Result<LintReport, LintError> {
+    let mut report = LintReport::default();
+    let kind_of = |path: &Path| config.media.kind_of(path);
+    let display = |path: &Path| {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        relative.to_string_lossy().replace("\\", "/")
+    };
+
+    let known_ids: Vec<&str> = config
+        .file_structure
+        .folders_list
+        .iter()
+        .map(|folder| folder.id.as_str())
+        .collect();
+    for rule in &config.lint.rules {
+        if !known_ids.contains(&rule.id.as_str()) {
+            continue;
+        }
+        for folder in folder_paths(config, &rule.id)? {
+            let folder = root.join(folder);
+            if !folder.is_dir() {
+                continue;
+            }
+            for file in WalkDir::new(&folder).min_depth(1).sort_by_file_name() {
+                let file = file?;
+                if !file.file_type().is_file() {
+                    continue;
+                }
+                let kind = kind_of(file.path());
+                if rule.deny.contains(&kind) {
+                    report.misplaced.push(format!(
+                        "{} ({} file in \"{}\")",
+                        display(file.path()),
+                        kind,
+                        rule.id
+                    ));
+                } else if file.depth() == 1 && rule.deny_loose.contains(&kind) {
+                    report.loose.push(format!(
+                        "{} ({} file directly in \"{}\")",
+                        display(file.path()),
+                        kind,
+                        rule.id
+                    ));
+                }
+            }
+        }
+    }
+
+    let project_folder = root.join(&config.setup.name);
+    let top_level: Vec<String> = generate_folder_paths(config)?
+        .iter()
+        .filter_map(|path| {
+            let (parent, name) = path.rsplit_once('/')?;
+            (parent == config.setup.name).then(|| name.to_string())
+        })
+        .collect();
+    if project_folder.is_dir() {
+        let mut entries = fs::read_dir(&project_folder)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type()?.is_dir()
+                && !name.starts_with('.')
+                && !top_level.contains(&name)
+                && name != config.sort.inbox
+                && name != plan::ARCHIVE_FOLDER_NAME
+                && !config.lint.extra_folders.contains(&name)
+            {
+                report.unexpected_folders.push(display(&entry.path()));
+            }
+        }
+        report.checked = WalkDir::new(&project_folder)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .count();
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        let day = dir.path().join("Demo/02_RUSHES/01_DAY01");
+        let cam = day.join("01_VIDEO/01_A_CAM");
+        let rec = day.join("02_AUDIO/01_A_REC");
+        fs::create_dir_all(&cam).unwrap();
+        fs::create_dir_all(&rec).unwrap();
+        fs::create_dir_all(dir.path().join("Demo/.cache")).unwrap();
+        fs::create_dir_all(dir.path().join("Demo").join(plan::ARCHIVE_FOLDER_NAME)).unwrap();
+        fs::write(cam.join("A001.MOV"), b"").unwrap();
+        fs::write(cam.join("A001.XML"), b"").unwrap();
+        fs::write(rec.join("ZOOM0001.WAV"), b"").unwrap();
+        fs::write(day.join("manifest.toml"), b"").unwrap();
+        assert!(check(dir.path(), &config).unwrap().is_clean());
+
+        fs::write(cam.join("ZOOM0002.WAV"), b"").unwrap();
+        fs::write(rec.join("A002.MP4"), b"").unwrap();
+        fs::write(day.join("A003.MOV"), b"").unwrap();
+        fs::write(day.join("../still.jpg"), b"").unwrap();
+        fs::create_dir_all(dir.path().join("Demo/06_MISC")).unwrap();
+        let report = check(dir.path(), &config).unwrap();
+        assert_eq!(report.checked, 8);
+        assert_eq!(
+            report.misplaced,
+            vec![
+                "Demo/02_RUSHES/01_DAY01/01_VIDEO/01_A_CAM/ZOOM0002.WAV (audio file in \"cams\")",
+                "Demo/02_RUSHES/01_DAY01/02_AUDIO/01_A_REC/A002.MP4 (video file in \"soundsources\")",
+            ]
+        );
+        assert_eq!(
+            report.loose,
+            vec![
+                "Demo/02_RUSHES/still.jpg (image file directly in \"rushes\")",
+                "Demo/02_RUSHES/01_DAY01/A003.MOV (video file directly in \"days\")",
+            ]
+        );
+        assert_eq!(report.unexpected_folders, vec!["Demo/06_MISC"]);
+
+        config.lint.extra_folders.push("06_MISC".to_string());
+        config.lint.rules.retain(|rule| rule.id != "cams");
+        config.lint.rules.push(LintRule {
+            id: "not_a_folder".to_string(),
+            deny: vec![MediaKind::Other],
+            deny_loose: Vec::new(),
+        });
+        let report = check(dir.path(), &config).unwrap();
+        assert_eq!(report.misplaced.len(), 1);
+        assert!(report.unexpected_folders.is_empty());
+    }
+}
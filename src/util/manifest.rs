@@ -0,0 +1,492 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::Path,
+    time::SystemTime,
+};
+
+use chrono::{DateTime, offset::Utc};
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+use xxhash_rust::xxh64::Xxh64;
+
+use crate::{
+    config::{Config, ConfigError, VerifyInfo},
+    day_folder_name, generate_folder_paths,
+    util::util::get_version,
+};
+
+/// Written into every day folder that has received an ingest. Modelled on the
+/// MHL (Media Hash List) `hash` entries, but stored as TOML like the config.
+pub const MANIFEST_FILE_NAME: &str = "nanopm_manifest.toml";
+
+#[derive(Debug)]
+pub enum VerifyError {
+    IoError(std::io::Error),
+    ConfigError(ConfigError),
+    ManifestError(String),
+    Failed(VerifyReport),
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::IoError(e) => write!(f, "IO error: {}", e),
+            VerifyError::ConfigError(e) => write!(f, "Config error: {}", e),
+            VerifyError::ManifestError(msg) => write!(f, "Manifest error: {}", msg),
+            VerifyError::Failed(report) => write!(
+                f,
+                "{} missing, {} changed and {} extra file(s)",
+                report.missing.len(),
+                report.changed.len(),
+                report.extra.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl From<ConfigError> for VerifyError {
+    fn from(error: ConfigError) -> Self {
+        VerifyError::ConfigError(error)
+    }
+}
+
+impl From<std::io::Error> for VerifyError {
+    fn from(error: std::io::Error) -> Self {
+        VerifyError::IoError(error)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub enum HashAlgorithm {
+    XxHash64,
+    Md5,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        match arg.to_lowercase().as_str() {
+            "xxh64" | "xxhash64" | "xxhash" => Ok(HashAlgorithm::XxHash64),
+            "md5" => Ok(HashAlgorithm::Md5),
+            "sha256" | "sha-256" => Ok(HashAlgorithm::Sha256),
+            other => Err(format!(
+                "\"{}\" is not a valid hash algorithm! Expected one of: xxh64, md5, sha256",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Manifest {
+    pub version: String,
+    pub creator: String,
+    pub created: String,
+    pub updated: String,
+    #[serde(default)]
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ManifestEntry {
+    /// Relative to the day folder that holds the manifest, always with `/`.
+    pub path: String,
+    pub size: u64,
+    pub last_modified: String,
+    pub hash_algorithm: HashAlgorithm,
+    pub hash: String,
+    pub hash_date: String,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        let now = timestamp(SystemTime::now());
+        Manifest {
+            version: get_version(),
+            creator: format!("nanopm {}", env!("CARGO_PKG_VERSION")),
+            created: now.clone(),
+            updated: now,
+            files: Vec::new(),
+        }
+    }
+
+    /// Reads the manifest in `day_folder`, or starts a new one if there is
+    /// none yet.
+    pub fn load_or_new(day_folder: &Path) -> Result<Self, VerifyError> {
+        let path = day_folder.join(MANIFEST_FILE_NAME);
+        if path.exists() {
+            Manifest::read(&path)
+        } else {
+            Ok(Manifest::new())
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, VerifyError> {
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(|e| {
+            VerifyError::ManifestError(format!("Could not read {}: {}", path.display(), e))
+        })
+    }
+
+    pub fn write(&mut self, day_folder: &Path) -> Result<(), VerifyError> {
+        self.updated = timestamp(SystemTime::now());
+        let text = toml::to_string(self)
+            .map_err(|e| VerifyError::ManifestError(format!("Could not serialize: {}", e)))?;
+        fs::write(day_folder.join(MANIFEST_FILE_NAME), text)?;
+        Ok(())
+    }
+
+    /// Adds an entry, replacing any previous entry for the same path.
+    pub fn record(&mut self, entry: ManifestEntry) {
+        self.files.retain(|existing| existing.path != entry.path);
+        self.files.push(entry);
+    }
+}
+
+impl ManifestEntry {
+    pub fn from_file(
+        file: &Path,
+        day_folder: &Path,
+        hash: String,
+        hash_algorithm: HashAlgorithm,
+    ) -> Result<Self, VerifyError> {
+        let metadata = fs::metadata(file)?;
+        let relative = file.strip_prefix(day_folder).map_err(|_| {
+            VerifyError::ManifestError(format!(
+                "{} is not inside {}",
+                file.display(),
+                day_folder.display()
+            ))
+        })?;
+        Ok(ManifestEntry {
+            path: relative.to_string_lossy().replace("\\", "/"),
+            size: metadata.len(),
+            last_modified: timestamp(metadata.modified()?),
+            hash_algorithm,
+            hash,
+            hash_date: timestamp(SystemTime::now()),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct VerifyReport {
+    pub verified: usize,
+    pub missing: Vec<String>,
+    pub changed: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.changed.is_empty() && self.extra.is_empty()
+    }
+
+    pub fn print(&self) {
+        println!(
+            "Verified {} file(s) against their manifests.",
+            self.verified
+        );
+        for (label, paths) in [
+            ("Missing", &self.missing),
+            ("Changed", &self.changed),
+            ("Extra", &self.extra),
+        ] {
+            for path in paths {
+                println!("    {}: {}", label, path);
+            }
+        }
+    }
+}
+
+/// A running hash in any of the supported algorithms.
+enum Hasher {
+    XxHash64(Xxh64),
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::XxHash64 => Hasher::XxHash64(Xxh64::new(0)),
+            HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::XxHash64(hasher) => hasher.update(bytes),
+            Hasher::Md5(hasher) => hasher.update(bytes),
+            Hasher::Sha256(hasher) => hasher.update(bytes),
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Hasher::XxHash64(hasher) => format!("{:016x}", hasher.digest()),
+            Hasher::Md5(hasher) => to_hex(&hasher.finalize()),
+            Hasher::Sha256(hasher) => to_hex(&hasher.finalize()),
+        }
+    }
+}
+
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finish())
+}
+
+/// Copies `from` to a new file at `to`, hashing the source as it is read so
+/// it is only read once, and carries its modification time over. Returns the
+/// bytes copied and the source's hash. A copy that fails half way is removed.
+pub fn copy_and_hash(
+    from: &Path,
+    to: &Path,
+    algorithm: HashAlgorithm,
+) -> io::Result<(u64, String)> {
+    let mut source = File::open(from)?;
+    let mut destination = File::create_new(to)?;
+    let result = copy_into(&mut source, &mut destination, algorithm);
+    if result.is_err() {
+        let _ = fs::remove_file(to);
+    }
+    result
+}
+
+fn copy_into(
+    source: &mut File,
+    destination: &mut File,
+    algorithm: HashAlgorithm,
+) -> io::Result<(u64, String)> {
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut copied = 0;
+    loop {
+        let read = source.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        destination.write_all(&buffer[..read])?;
+        copied += read as u64;
+    }
+    destination.flush()?;
+    destination.set_modified(source.metadata()?.modified()?)?;
+    Ok((copied, hasher.finish()))
+}
+
+pub fn verify(verify_info: VerifyInfo) -> Result<VerifyReport, VerifyError> {
+    let config = &verify_info.config;
+    if let Some(day) = verify_info.day
+        && day > config.setup.days
+    {
+        return Err(VerifyError::ManifestError(format!(
+            "Day {} is outside of the project's {} day(s)",
+            day, config.setup.days
+        )));
+    }
+
+    let mut report = VerifyReport::default();
+    let mut manifests_found = 0;
+
+    for day_folder in day_folders(config, verify_info.day)? {
+        let day_folder = verify_info.root.join(day_folder);
+        let manifest_path = day_folder.join(MANIFEST_FILE_NAME);
+        if !manifest_path.exists() {
+            continue;
+        }
+        manifests_found += 1;
+        verify_day(&day_folder, &Manifest::read(&manifest_path)?, &mut report)?;
+    }
+
+    if manifests_found == 0 {
+        return Err(VerifyError::ManifestError(
+            "No ingest manifests were found to verify against".to_string(),
+        ));
+    }
+
+    report.print();
+
+    if report.is_clean() {
+        Ok(report)
+    } else {
+        Err(VerifyError::Failed(report))
+    }
+}
+
+fn verify_day(
+    day_folder: &Path,
+    manifest: &Manifest,
+    report: &mut VerifyReport,
+) -> Result<(), VerifyError> {
+    let display = |relative: &str| format!("{}/{}", day_folder.display(), relative);
+
+    for entry in &manifest.files {
+        let file = day_folder.join(&entry.path);
+        if !file.is_file() {
+            report.missing.push(display(&entry.path));
+            continue;
+        }
+        let size = fs::metadata(&file)?.len();
+        if size != entry.size || hash_file(&file, entry.hash_algorithm)? != entry.hash {
+            report.changed.push(display(&entry.path));
+            continue;
+        }
+        report.verified += 1;
+    }
+
+    for file in WalkDir::new(day_folder).sort_by_file_name() {
+        let file = file.map_err(|e| VerifyError::IoError(e.into()))?;
+        if !file.file_type().is_file() {
+            continue;
+        }
+        let relative = file
+            .path()
+            .strip_prefix(day_folder)
+            .map(|path| path.to_string_lossy().replace("\\", "/"))
+            .unwrap_or_default();
+        if relative != MANIFEST_FILE_NAME
+            && !manifest.files.iter().any(|entry| entry.path == relative)
+        {
+            report.extra.push(display(&relative));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns every generated folder for the given day, or for all days.
+pub fn day_folders(config: &Config, day: Option<usize>) -> Result<Vec<String>, ConfigError> {
+    let names: Vec<String> = match day {
+        Some(day) => vec![day_folder_name(&config.setup, day)],
+        None => (1..=config.setup.days)
+            .map(|day| day_folder_name(&config.setup, day))
+            .collect(),
+    };
+    Ok(generate_folder_paths(config)?
+        .into_iter()
+        .filter(|path| {
+            path.rsplit('/')
+                .next()
+                .is_some_and(|last| names.iter().any(|name| name == last))
+        })
+        .collect())
+}
+
+fn timestamp(time: SystemTime) -> String {
+    let datetime: DateTime<Utc> = time.into();
+    datetime.to_rfc3339()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("clip.mov");
+        fs::write(&file, b"abc").unwrap();
+
+        assert_eq!(
+            hash_file(&file, HashAlgorithm::Md5).unwrap(),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            hash_file(&file, HashAlgorithm::Sha256).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash_file(&file, HashAlgorithm::XxHash64).unwrap(),
+            "44bc2cf5ad770999"
+        );
+    }
+
+    #[test]
+    fn test_copy_and_hash() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let from = dir.path().join("A001.mov");
+        let to = dir.path().join("copy.mov");
+        fs::write(&from, b"nanopm").unwrap();
+        let shot = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_792_229_400);
+        File::options()
+            .write(true)
+            .open(&from)
+            .unwrap()
+            .set_modified(shot)
+            .unwrap();
+
+        let (size, hash) = copy_and_hash(&from, &to, HashAlgorithm::XxHash64).unwrap();
+        assert_eq!(size, 6);
+        assert_eq!(hash, hash_file(&from, HashAlgorithm::XxHash64).unwrap());
+        assert_eq!(fs::read(&to).unwrap(), b"nanopm");
+        assert_eq!(fs::metadata(&to).unwrap().modified().unwrap(), shot);
+        // Never overwrites, and leaves the existing file alone.
+        assert!(copy_and_hash(&from, &to, HashAlgorithm::Md5).is_err());
+        assert!(to.exists());
+    }
+
+    #[test]
+    fn test_verify_day_reports_missing_changed_and_extra() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let day_folder = dir.path();
+        fs::create_dir_all(day_folder.join("01_VIDEO/01_A_CAM")).unwrap();
+        let kept = day_folder.join("01_VIDEO/01_A_CAM/A001.mov");
+        let edited = day_folder.join("01_VIDEO/01_A_CAM/A002.mov");
+        let deleted = day_folder.join("01_VIDEO/01_A_CAM/A003.mov");
+
+        let mut manifest = Manifest::new();
+        for file in [&kept, &edited, &deleted] {
+            fs::write(file, b"original").unwrap();
+            let hash = hash_file(file, HashAlgorithm::XxHash64).unwrap();
+            manifest.record(
+                ManifestEntry::from_file(file, day_folder, hash, HashAlgorithm::XxHash64).unwrap(),
+            );
+        }
+        manifest.write(day_folder).unwrap();
+
+        fs::write(&edited, b"tampered").unwrap();
+        fs::remove_file(&deleted).unwrap();
+        fs::write(day_folder.join("01_VIDEO/stray.wav"), b"stray").unwrap();
+
+        let mut report = VerifyReport::default();
+        let manifest = Manifest::read(&day_folder.join(MANIFEST_FILE_NAME)).unwrap();
+        verify_day(day_folder, &manifest, &mut report).unwrap();
+
+        assert_eq!(report.verified, 1);
+        assert_eq!(report.changed.len(), 1);
+        assert!(report.changed[0].ends_with("A002.mov"));
+        assert_eq!(report.missing.len(), 1);
+        assert!(report.missing[0].ends_with("A003.mov"));
+        assert_eq!(report.extra.len(), 1);
+        assert!(report.extra[0].ends_with("01_VIDEO/stray.wav"));
+        assert!(!report.is_clean());
+    }
+}
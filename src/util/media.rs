@@ -0,0 +1,124 @@
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// What a file is to an editor, going by its extension.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    Video,
+    Audio,
+    Image,
+    Project,
+    Sidecar,
+    Other,
+}
+
+impl fmt::Display for MediaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MediaKind::Video => "video",
+            MediaKind::Audio => "audio",
+            MediaKind::Image => "image",
+            MediaKind::Project => "project",
+            MediaKind::Sidecar => "sidecar",
+            MediaKind::Other => "other",
+        })
+    }
+}
+
+/// The `[media]` table of the config: the extensions (without the dot, any
+/// case) of each kind of file. Anything not listed is `other`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct MediaTable {
+    pub video: Vec<String>,
+    pub audio: Vec<String>,
+    pub image: Vec<String>,
+    pub project: Vec<String>,
+    /// Files cameras and recorders write next to the media, e.g. XML
+    /// metadata or thumbnails.
+    pub sidecar: Vec<String>,
+}
+
+impl Default for MediaTable {
+    fn default() -> Self {
+        let list = |extensions: &[&str]| extensions.iter().map(|e| e.to_string()).collect();
+        MediaTable {
+            video: list(&[
+                "mov", "mp4", "m4v", "mxf", "braw", "r3d", "ari", "crm", "mts", "m2ts", "avi",
+                "mkv", "insv", "lrv",
+            ]),
+            audio: list(&["wav", "bwf", "aif", "aiff", "mp3", "aac", "m4a", "flac"]),
+            image: list(&[
+                "jpg", "jpeg", "png", "tif", "tiff", "dng", "cr2", "cr3", "nef", "arw", "heic",
+                "psd", "exr", "dpx",
+            ]),
+            project: list(&[
+                "prproj", "aep", "drp", "fcpxml", "avb", "avp", "aaf", "edl", "otio",
+            ]),
+            sidecar: list(&["xml", "xmp", "thm", "srt", "lrf", "bim", "cpf", "ale"]),
+        }
+    }
+}
+
+impl MediaTable {
+    pub fn is_default(&self) -> bool {
+        *self == MediaTable::default()
+    }
+
+    /// The kind of files with `extension`, e.g. "MOV".
+    pub fn kind(&self, extension: &str) -> MediaKind {
+        let listed = |extensions: &[String]| {
+            extensions
+                .iter()
+                .any(|listed| listed.eq_ignore_ascii_case(extension))
+        };
+        if extension.is_empty() {
+            MediaKind::Other
+        } else if listed(&self.video) {
+            MediaKind::Video
+        } else if listed(&self.audio) {
+            MediaKind::Audio
+        } else if listed(&self.image) {
+            MediaKind::Image
+        } else if listed(&self.project) {
+            MediaKind::Project
+        } else if listed(&self.sidecar) {
+            MediaKind::Sidecar
+        } else {
+            MediaKind::Other
+        }
+    }
+
+    pub fn kind_of(&self, path: &Path) -> MediaKind {
+        self.kind(&extension(path))
+    }
+}
+
+/// The lowercase extension of `path`, "" if it has none.
+pub fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_kinds() {
+        let mut table = MediaTable::default();
+        assert_eq!(table.kind_of(Path::new("A001C003.MOV")), MediaKind::Video);
+        assert_eq!(table.kind_of(Path::new("ZOOM0001.WAV")), MediaKind::Audio);
+        assert_eq!(table.kind_of(Path::new("C0001M01.XML")), MediaKind::Sidecar);
+        assert_eq!(table.kind_of(Path::new("edit.prproj")), MediaKind::Project);
+        assert_eq!(table.kind_of(Path::new("notes.txt")), MediaKind::Other);
+        assert_eq!(table.kind_of(Path::new(".DS_Store")), MediaKind::Other);
+
+        table.video.push("NEV".to_string());
+        assert_eq!(table.kind("nev"), MediaKind::Video);
+        assert_eq!(MediaKind::Sidecar.to_string(), "sidecar");
+    }
+}
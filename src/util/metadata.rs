@@ -0,0 +1,649 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+
+/// `moov` boxes past this size are taken as corrupt rather than read into
+/// memory. Real ones are a few MB even for hour-long clips.
+const MAX_MOOV_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum MetadataError {
+    IoError(io::Error),
+    Malformed(String),
+}
+
+impl std::fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetadataError::IoError(e) => write!(f, "IO error: {}", e),
+            MetadataError::Malformed(msg) => write!(f, "Malformed file: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {}
+
+impl From<io::Error> for MetadataError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            // Headers that claim more than the file holds.
+            io::ErrorKind::UnexpectedEof => {
+                MetadataError::Malformed("the file ends mid-header".to_string())
+            }
+            _ => MetadataError::IoError(error),
+        }
+    }
+}
+
+/// Technical metadata of a clip, as far as its container says.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MediaInfo {
+    pub duration: Option<Duration>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// When the clip was recorded, in local time: the `mvhd` creation time of
+    /// an MP4/MOV, or the BWF `bext` origination date and time.
+    pub created: Option<NaiveDateTime>,
+    /// The BWF `bext` time reference: samples since midnight at the start of
+    /// the recording.
+    pub time_reference: Option<u64>,
+}
+
+impl MediaInfo {
+    /// A short description to group clips by, e.g. "3840x2160 25fps" or
+    /// "48000Hz 2ch".
+    pub fn format(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            parts.push(format!("{}x{}", width, height));
+        }
+        if let Some(frame_rate) = self.frame_rate {
+            parts.push(format!("{}fps", (frame_rate * 1000.0).round() / 1000.0));
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            parts.push(format!("{}Hz", sample_rate));
+        }
+        if let Some(channels) = self.channels {
+            parts.push(format!("{}ch", channels));
+        }
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+/// Reads the metadata of the MP4/MOV or WAV/BWF file at `path`, going by its
+/// first bytes rather than its extension. `None` for any other container.
+pub fn read(path: &Path) -> Result<Option<MediaInfo>, MetadataError> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 12];
+    let read = read_up_to(&mut file, &mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    if read == 12 && matches!(&magic[..4], b"RIFF" | b"RF64") && &magic[8..] == b"WAVE" {
+        return read_wav(&mut file).map(Some);
+    }
+    if read >= 8
+        && matches!(
+            &magic[4..8],
+            b"ftyp" | b"moov" | b"mdat" | b"wide" | b"free" | b"skip"
+        )
+    {
+        return read_mp4(&mut file).map(Some);
+    }
+    Ok(None)
+}
+
+/// The day `path` was recorded on: its embedded date, or its modification
+/// time (which cameras and copies keep) without one.
+pub fn recorded_date(path: &Path) -> Option<NaiveDate> {
+    match read(path).ok().flatten().and_then(|info| info.created) {
+        Some(created) => Some(created.date()),
+        None => {
+            let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+            Some(DateTime::<Local>::from(modified).date_naive())
+        }
+    }
+}
+
+fn read_up_to(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn read_mp4(file: &mut (impl Read + Seek)) -> Result<MediaInfo, MetadataError> {
+    let length = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+
+    // The moov box can sit before or after the media data, so walk the top
+    // level until it turns up.
+    let mut position = 0;
+    while position + 8 <= length {
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+        let mut header_size = 8;
+        let mut size = u32::from_be_bytes(header[..4].try_into().unwrap()) as u64;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large)?;
+            size = u64::from_be_bytes(large);
+            header_size = 16;
+        } else if size == 0 {
+            size = length - position;
+        }
+        if size < header_size {
+            return Err(MetadataError::Malformed(format!(
+                "a box at byte {} is {} bytes long",
+                position, size
+            )));
+        }
+
+        if &header[4..] == b"moov" {
+            if size > MAX_MOOV_SIZE {
+                return Err(MetadataError::Malformed(format!(
+                    "the moov box is {} bytes long",
+                    size
+                )));
+            }
+            let mut moov = vec![0u8; (size - header_size) as usize];
+            file.read_exact(&mut moov)?;
+            return parse_moov(&moov);
+        }
+        position += size;
+        file.seek(SeekFrom::Start(position))?;
+    }
+    Err(MetadataError::Malformed("no moov box".to_string()))
+}
+
+/// The boxes directly inside `data`, as (type, payload).
+fn boxes(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.len() < 8 {
+            return None;
+        }
+        let size = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let (header_size, size) = match size {
+            0 => (8, rest.len()),
+            1 if rest.len() >= 16 => (
+                16,
+                u64::from_be_bytes(rest[8..16].try_into().unwrap()) as usize,
+            ),
+            _ => (8, size),
+        };
+        if size < header_size || size > rest.len() {
+            return None;
+        }
+        let found = (&rest[4..8], &rest[header_size..size]);
+        rest = &rest[size..];
+        Some(found)
+    })
+}
+
+fn child<'a>(data: &'a [u8], kind: &[u8]) -> Option<&'a [u8]> {
+    boxes(data)
+        .find(|(found, _)| *found == kind)
+        .map(|(_, payload)| payload)
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// The timescale and duration of an `mvhd` or `mdhd` box, which share their
+/// layout up to there. A duration of all ones means it is unknown.
+fn timescale_and_duration(header: &[u8]) -> Option<(u32, Option<u64>)> {
+    match header.first()? {
+        0 => Some((
+            be_u32(header, 12)?,
+            Some(be_u32(header, 16)?)
+                .filter(|&duration| duration != u32::MAX)
+                .map(u64::from),
+        )),
+        1 => Some((
+            be_u32(header, 20)?,
+            Some(be_u64(header, 24)?).filter(|&duration| duration != u64::MAX),
+        )),
+        _ => None,
+    }
+}
+
+/// `amount / per_second` seconds, `None` if that doesn't fit a `Duration`.
+fn seconds(amount: u64, per_second: u64) -> Option<Duration> {
+    Duration::try_from_secs_f64(amount as f64 / per_second as f64).ok()
+}
+
+/// The creation time of an `mvhd` box: seconds since 1904 in UTC, 0 when
+/// the camera didn't set it.
+fn creation_time(mvhd: &[u8]) -> Option<NaiveDateTime> {
+    const SECONDS_1904_TO_1970: i64 = 2_082_844_800;
+    let seconds = match mvhd.first()? {
+        0 => be_u32(mvhd, 4)? as u64,
+        _ => be_u64(mvhd, 4)?,
+    };
+    if seconds == 0 {
+        return None;
+    }
+    let utc = DateTime::from_timestamp(seconds as i64 - SECONDS_1904_TO_1970, 0)?;
+    Some(utc.with_timezone(&Local).naive_local())
+}
+
+fn parse_moov(moov: &[u8]) -> Result<MediaInfo, MetadataError> {
+    let mvhd =
+        child(moov, b"mvhd").ok_or_else(|| MetadataError::Malformed("no mvhd box".to_string()))?;
+    let (timescale, duration) = timescale_and_duration(mvhd)
+        .filter(|(timescale, _)| *timescale > 0)
+        .ok_or_else(|| MetadataError::Malformed("unreadable mvhd box".to_string()))?;
+
+    let mut info = MediaInfo {
+        duration: duration.and_then(|duration| seconds(duration, timescale.into())),
+        created: creation_time(mvhd),
+        ..Default::default()
+    };
+    for (_, trak) in boxes(moov).filter(|(kind, _)| *kind == b"trak") {
+        let Some(mdia) = child(trak, b"mdia") else {
+            continue;
+        };
+        let handler = child(mdia, b"hdlr").and_then(|hdlr| hdlr.get(8..12));
+        let stbl = child(mdia, b"minf").and_then(|minf| child(minf, b"stbl"));
+        match handler {
+            Some(b"vide") if info.width.is_none() => {
+                // Width and height close the tkhd box as 16.16 fixed point.
+                if let Some(tkhd) = child(trak, b"tkhd").filter(|tkhd| tkhd.len() >= 8) {
+                    info.width = be_u32(tkhd, tkhd.len() - 8).map(|width| width >> 16);
+                    info.height = be_u32(tkhd, tkhd.len() - 4).map(|height| height >> 16);
+                }
+                let media_time = child(mdia, b"mdhd").and_then(timescale_and_duration);
+                let frames = stbl
+                    .and_then(|stbl| child(stbl, b"stts"))
+                    .and_then(frame_count);
+                if let (Some((timescale, Some(duration))), Some(frames)) = (media_time, frames)
+                    && duration > 0
+                {
+                    info.frame_rate = Some(frames as f64 * timescale as f64 / duration as f64);
+                }
+            }
+            Some(b"soun") if info.sample_rate.is_none() => {
+                // The first sample entry: an 8 byte box header, 8 bytes of
+                // reserved and data reference index, 8 of version and
+                // vendor, then channels, sample size, compression, packet
+                // size and the 16.16 sample rate.
+                if let Some(entry) = stbl
+                    .and_then(|stbl| child(stbl, b"stsd"))
+                    .and_then(|stsd| stsd.get(8..))
+                {
+                    info.channels = be_u16(entry, 24);
+                    info.sample_rate = be_u32(entry, 32).map(|rate| rate >> 16);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+/// How many samples (frames, for video) an `stts` box times.
+fn frame_count(stts: &[u8]) -> Option<u64> {
+    let entries = be_u32(stts, 4)? as usize;
+    (0..entries)
+        .map(|i| be_u32(stts, 8 + i * 8).map(u64::from))
+        .sum()
+}
+
+fn read_wav(file: &mut (impl Read + Seek)) -> Result<MediaInfo, MetadataError> {
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header)?;
+
+    let mut info = MediaInfo::default();
+    let mut byte_rate = None;
+    let mut data_size = None;
+    // RF64 keeps the sizes too big for 32 bits in its ds64 chunk.
+    let mut ds64_data_size = None;
+
+    loop {
+        let mut chunk = [0u8; 8];
+        if read_up_to(file, &mut chunk)? < 8 {
+            break;
+        }
+        let size = u32::from_le_bytes(chunk[4..].try_into().unwrap()) as u64;
+        match &chunk[..4] {
+            b"fmt " => {
+                let fmt = read_chunk(file, size, 16)?;
+                info.channels = Some(u16::from_le_bytes(fmt[2..4].try_into().unwrap()));
+                info.sample_rate = Some(u32::from_le_bytes(fmt[4..8].try_into().unwrap()));
+                byte_rate = Some(u32::from_le_bytes(fmt[8..12].try_into().unwrap()));
+            }
+            b"ds64" => {
+                let ds64 = read_chunk(file, size, 16)?;
+                ds64_data_size = Some(u64::from_le_bytes(ds64[8..16].try_into().unwrap()));
+            }
+            b"bext" => {
+                // Description (256), originator (32) and its reference (32)
+                // come first, then the origination date (10) and time (8)
+                // and the 64 bit time reference.
+                let bext = read_chunk(file, size, 346)?;
+                let text = |range: std::ops::Range<usize>| {
+                    String::from_utf8_lossy(&bext[range])
+                        .trim_end_matches('\0')
+                        .trim()
+                        .to_string()
+                };
+                info.created = origination(&text(320..330), &text(330..338));
+                info.time_reference = Some(u64::from_le_bytes(bext[338..346].try_into().unwrap()));
+            }
+            b"data" => {
+                let size = match (size, ds64_data_size) {
+                    (0xFFFF_FFFF, Some(ds64_size)) => ds64_size,
+                    _ => size,
+                };
+                data_size = Some(size);
+                // Only the header is wanted, and bext may follow the audio.
+                // No file is long enough to seek past a larger chunk.
+                let Ok(skip) = i64::try_from(size.saturating_add(size % 2)) else {
+                    break;
+                };
+                file.seek(SeekFrom::Current(skip))?;
+            }
+            _ => {
+                file.seek(SeekFrom::Current((size + size % 2) as i64))?;
+            }
+        }
+    }
+
+    match (byte_rate, data_size) {
+        (Some(byte_rate), Some(data_size)) if byte_rate > 0 => {
+            info.duration = seconds(data_size, byte_rate.into());
+            Ok(info)
+        }
+        (None, _) => Err(MetadataError::Malformed("no fmt chunk".to_string())),
+        _ => Err(MetadataError::Malformed("no data chunk".to_string())),
+    }
+}
+
+/// The `bext` origination date ("yyyy-mm-dd") and time ("hh:mm:ss"). The
+/// spec allows any separator, so only the digits are read.
+fn origination(date: &str, time: &str) -> Option<NaiveDateTime> {
+    let digits = |text: &str| {
+        text.chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>()
+    };
+    let (date, time) = (digits(date), digits(time));
+    if date.len() != 8 {
+        return None;
+    }
+    let date = NaiveDate::from_ymd_opt(
+        date[..4].parse().ok()?,
+        date[4..6].parse().ok()?,
+        date[6..].parse().ok()?,
+    )?;
+    match time.len() {
+        6 => date.and_hms_opt(
+            time[..2].parse().ok()?,
+            time[2..4].parse().ok()?,
+            time[4..].parse().ok()?,
+        ),
+        _ => date.and_hms_opt(0, 0, 0),
+    }
+}
+
+/// Reads a whole chunk of `size` bytes (plus its pad byte), which has to be at
+/// least `minimum` bytes long.
+fn read_chunk(
+    file: &mut (impl Read + Seek),
+    size: u64,
+    minimum: u64,
+) -> Result<Vec<u8>, MetadataError> {
+    if size < minimum || size > MAX_MOOV_SIZE {
+        return Err(MetadataError::Malformed(format!(
+            "a {} byte chunk where at least {} were expected",
+            size, minimum
+        )));
+    }
+    let mut chunk = vec![0u8; size as usize];
+    file.read_exact(&mut chunk)?;
+    if size % 2 == 1 {
+        file.seek(SeekFrom::Current(1))?;
+    }
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn mp4_box(kind: &[u8], payload: &[u8]) -> Vec<u8> {
+        let mut bytes = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// A version 0 mvhd/mdhd: version and flags, two dates, then the
+    /// timescale and duration.
+    fn media_header(kind: &[u8], timescale: u32, duration: u32) -> Vec<u8> {
+        let mut payload = vec![0u8; 12];
+        payload.extend_from_slice(&timescale.to_be_bytes());
+        payload.extend_from_slice(&duration.to_be_bytes());
+        payload.extend_from_slice(&[0u8; 80]);
+        mp4_box(kind, &payload)
+    }
+
+    fn handler(kind: &[u8]) -> Vec<u8> {
+        let mut payload = vec![0u8; 8];
+        payload.extend_from_slice(kind);
+        payload.extend_from_slice(&[0u8; 13]);
+        mp4_box(b"hdlr", &payload)
+    }
+
+    fn test_mov() -> Vec<u8> {
+        let mut tkhd = vec![0u8; 76];
+        tkhd.extend_from_slice(&(1920u32 << 16).to_be_bytes());
+        tkhd.extend_from_slice(&(1080u32 << 16).to_be_bytes());
+        let mut stts = vec![0u8; 4];
+        stts.extend_from_slice(&1u32.to_be_bytes());
+        stts.extend_from_slice(&250u32.to_be_bytes());
+        stts.extend_from_slice(&1000u32.to_be_bytes());
+        let video = [
+            mp4_box(b"tkhd", &tkhd),
+            mp4_box(
+                b"mdia",
+                &[
+                    media_header(b"mdhd", 25000, 250000),
+                    handler(b"vide"),
+                    mp4_box(b"minf", &mp4_box(b"stbl", &mp4_box(b"stts", &stts))),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+
+        let mut entry = vec![0u8; 16];
+        entry.extend_from_slice(&2u16.to_be_bytes());
+        entry.extend_from_slice(&[0u8; 6]);
+        entry.extend_from_slice(&(48000u32 << 16).to_be_bytes());
+        let mut stsd = vec![0u8; 8];
+        stsd.extend_from_slice(&mp4_box(b"sowt", &entry));
+        let audio = mp4_box(
+            b"mdia",
+            &[
+                handler(b"soun"),
+                mp4_box(b"minf", &mp4_box(b"stbl", &mp4_box(b"stsd", &stsd))),
+            ]
+            .concat(),
+        );
+
+        let moov = [
+            media_header(b"mvhd", 600, 6000),
+            mp4_box(b"trak", &video),
+            mp4_box(b"trak", &audio),
+        ]
+        .concat();
+        // The moov box after the media data, like most cameras write it.
+        [
+            mp4_box(b"ftyp", b"qt  \0\0\0\0qt  "),
+            mp4_box(b"mdat", &[0u8; 64]),
+            mp4_box(b"moov", &moov),
+        ]
+        .concat()
+    }
+
+    fn test_wav(with_bext: bool) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&2u16.to_le_bytes());
+        fmt.extend_from_slice(&48000u32.to_le_bytes());
+        fmt.extend_from_slice(&192000u32.to_le_bytes());
+        fmt.extend_from_slice(&4u16.to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+
+        let chunk = |id: &[u8], payload: &[u8]| {
+            let mut bytes = id.to_vec();
+            bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(payload);
+            bytes
+        };
+        let mut body = b"WAVE".to_vec();
+        if with_bext {
+            let mut bext = vec![0u8; 320];
+            bext.extend_from_slice(b"2026-10-1709:30:00");
+            bext.extend_from_slice(&(48000u64 * 3600 * 9).to_le_bytes());
+            bext.extend_from_slice(&[0u8; 256]);
+            body.extend(chunk(b"bext", &bext));
+        }
+        body.extend(chunk(b"fmt ", &fmt));
+        // Half a second of silence.
+        body.extend(chunk(b"data", &[0u8; 96000]));
+
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        bytes.extend(body);
+        bytes
+    }
+
+    #[test]
+    fn test_read_mov() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("A001C003.MOV");
+        fs::write(&path, test_mov()).unwrap();
+
+        let info = read(&path).unwrap().unwrap();
+        assert_eq!(info.duration, Some(Duration::from_secs(10)));
+        assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+        assert_eq!(info.frame_rate, Some(25.0));
+        assert_eq!((info.sample_rate, info.channels), (Some(48000), Some(2)));
+        assert_eq!(info.format().unwrap(), "1920x1080 25fps 48000Hz 2ch");
+        assert!(info.created.is_none());
+
+        let mut mvhd = vec![0u8; 4];
+        mvhd.extend_from_slice(&(2_082_844_800u32 + 1_792_229_400).to_be_bytes());
+        let utc = DateTime::from_timestamp(1_792_229_400, 0).unwrap();
+        assert_eq!(
+            creation_time(&mvhd),
+            Some(utc.with_timezone(&Local).naive_local())
+        );
+
+        // The spec lets a duration of all ones mean unknown.
+        let mut mvhd = vec![1u8, 0, 0, 0];
+        mvhd.extend_from_slice(&[0u8; 16]);
+        mvhd.extend_from_slice(&1u32.to_be_bytes());
+        mvhd.extend_from_slice(&u64::MAX.to_be_bytes());
+        mvhd.extend_from_slice(&[0u8; 80]);
+        let info = parse_moov(&mp4_box(b"mvhd", &mvhd)).unwrap();
+        assert!(info.duration.is_none());
+        mvhd[31] = 0xFE;
+        let info = parse_moov(&mp4_box(b"mvhd", &mvhd)).unwrap();
+        assert!(info.duration.is_none());
+
+        let mut truncated = test_mov();
+        truncated.truncate(truncated.len() - 20);
+        fs::write(&path, truncated).unwrap();
+        assert!(matches!(read(&path), Err(MetadataError::Malformed(_))));
+    }
+
+    #[test]
+    fn test_read_wav() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("ZOOM0001.WAV");
+        fs::write(&path, test_wav(false)).unwrap();
+        let info = read(&path).unwrap().unwrap();
+        assert_eq!(info.duration, Some(Duration::from_millis(500)));
+        assert_eq!(info.format().unwrap(), "48000Hz 2ch");
+        assert!(info.created.is_none());
+
+        fs::write(&path, test_wav(true)).unwrap();
+        let info = read(&path).unwrap().unwrap();
+        assert_eq!(info.created.unwrap().to_string(), "2026-10-17 09:30:00");
+        assert_eq!(info.time_reference, Some(48000 * 3600 * 9));
+
+        fs::write(&path, b"not a clip").unwrap();
+        assert!(read(&path).unwrap().is_none());
+    }
+
+    /// An RF64 file whose `data` chunk size only its `ds64` chunk holds,
+    /// followed by a `bext` chunk.
+    fn test_rf64(byte_rate: u32, data_size: u64) -> Vec<u8> {
+        let chunk = |id: &[u8], size: u32, payload: &[u8]| {
+            let mut bytes = id.to_vec();
+            bytes.extend_from_slice(&size.to_le_bytes());
+            bytes.extend_from_slice(payload);
+            bytes
+        };
+        let mut ds64 = u64::MAX.to_le_bytes().to_vec();
+        ds64.extend_from_slice(&data_size.to_le_bytes());
+        ds64.extend_from_slice(&[0u8; 12]);
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&48000u32.to_le_bytes());
+        fmt.extend_from_slice(&byte_rate.to_le_bytes());
+        fmt.extend_from_slice(&[1u8, 0, 8, 0]);
+        let mut bext = vec![0u8; 320];
+        bext.extend_from_slice(b"2026-10-1709:30:00");
+        bext.extend_from_slice(&[0u8; 264]);
+
+        let mut bytes = b"RF64\xFF\xFF\xFF\xFFWAVE".to_vec();
+        bytes.extend(chunk(b"ds64", 28, &ds64));
+        bytes.extend(chunk(b"fmt ", 16, &fmt));
+        bytes.extend(chunk(
+            b"data",
+            u32::MAX,
+            &vec![0u8; data_size.min(96000) as usize],
+        ));
+        bytes.extend(chunk(b"bext", 602, &bext));
+        bytes
+    }
+
+    #[test]
+    fn test_read_rf64() {
+        let info = read_wav(&mut io::Cursor::new(test_rf64(96000, 96000))).unwrap();
+        assert_eq!(info.duration, Some(Duration::from_secs(1)));
+        assert_eq!(info.created.unwrap().to_string(), "2026-10-17 09:30:00");
+
+        // Too long for a Duration, which must not panic.
+        let info = read_wav(&mut io::Cursor::new(test_rf64(1, u64::MAX))).unwrap();
+        assert!(info.duration.is_none());
+    }
+}
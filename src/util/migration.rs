@@ -0,0 +1,310 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use toml::{Table, Value};
+
+use crate::config::Config;
+
+/// The config layout this nanopm reads and writes, which `get_version` also
+/// stamps on manifests, archives and query output. Bumped together with a new
+/// step in `MIGRATIONS` whenever the layout changes.
+pub const CONFIG_VERSION: u32 = 3;
+
+/// The first layout nanopm wrote, with a `version` key from the start.
+const OLDEST_VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum MigrationError {
+    IoError(std::io::Error),
+    ParseError(String),
+    UnknownVersion(String),
+    TooNew(String),
+    StepFailed(u32, String),
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::IoError(e) => write!(f, "IO error: {}", e),
+            MigrationError::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            MigrationError::UnknownVersion(version) => {
+                write!(f, "Unrecognized config version \"{}\"", version)
+            }
+            MigrationError::TooNew(version) => write!(
+                f,
+                "The config ({}) was written by a newer nanopm than this one (v{}), please \
+                 update nanopm instead of editing the config",
+                version, CONFIG_VERSION
+            ),
+            MigrationError::StepFailed(from, msg) => write!(
+                f,
+                "Migrating from v{} to v{} failed: {}",
+                from,
+                from + 1,
+                msg
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<std::io::Error> for MigrationError {
+    fn from(error: std::io::Error) -> Self {
+        MigrationError::IoError(error)
+    }
+}
+
+/// What a migration changed, for printing after the fact.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+    pub changes: Vec<String>,
+    pub backup: Option<PathBuf>,
+}
+
+impl MigrationReport {
+    pub fn print(&self) {
+        match &self.backup {
+            Some(backup) => println!(
+                "Migrated config from v{} to v{} (backup saved to \"{}\"):",
+                self.from,
+                self.to,
+                backup.to_string_lossy().replace("\\", "/")
+            ),
+            None => println!(
+                "Config would be migrated from v{} to v{}:",
+                self.from, self.to
+            ),
+        }
+        for change in &self.changes {
+            println!("    {}", change);
+        }
+    }
+}
+
+/// One step of the pipeline, upgrading a config from `from` to `from + 1`.
+/// Steps edit the raw TOML so they still work when the old layout no longer
+/// deserializes into `Config`.
+struct Migration {
+    from: u32,
+    apply: fn(&mut Table) -> Result<Vec<String>, String>,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 2,
+    apply: v2_to_v3,
+}];
+
+/// Reads the config at `path`, upgrading it to the current version if it is
+/// older. With `persist`, an upgraded config is written back after the
+/// original is backed up next to it; otherwise the upgrade stays in memory.
+pub fn load_config(
+    path: &Path,
+    persist: bool,
+) -> Result<(Config, Option<MigrationReport>), MigrationError> {
+    let text = fs::read_to_string(path)?;
+    let (config, report) = migrate_str(&text)?;
+
+    let Some(mut report) = report else {
+        return Ok((config, None));
+    };
+    if persist {
+        let backup = backup_path(path, report.from);
+        fs::copy(path, &backup)?;
+        Config::write_config(&config, &path.to_string_lossy())
+            .map_err(|e| MigrationError::ParseError(e.to_string()))?;
+        report.backup = Some(backup);
+    }
+    Ok((config, Some(report)))
+}
+
+pub fn migrate_str(text: &str) -> Result<(Config, Option<MigrationReport>), MigrationError> {
+    let mut table: Table = text
+        .parse()
+        .map_err(|e: toml::de::Error| MigrationError::ParseError(e.to_string()))?;
+
+    let from = match table.get("version") {
+        None => return Err(MigrationError::UnknownVersion("none".to_string())),
+        Some(Value::String(version)) => parse_version(version)
+            .filter(|&version| version >= OLDEST_VERSION)
+            .ok_or_else(|| MigrationError::UnknownVersion(version.clone()))?,
+        Some(other) => return Err(MigrationError::UnknownVersion(other.to_string())),
+    };
+    let to = CONFIG_VERSION;
+    if from > to {
+        return Err(MigrationError::TooNew(format!("v{}", from)));
+    }
+
+    let mut changes = Vec::new();
+    for version in from..to {
+        let step = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or_else(|| MigrationError::StepFailed(version, "no migration step".to_string()))?;
+        changes
+            .extend((step.apply)(&mut table).map_err(|e| MigrationError::StepFailed(version, e))?);
+        table.insert(
+            "version".to_string(),
+            Value::String(format!("v{}", version + 1)),
+        );
+    }
+
+    let config = if from < to {
+        let migrated =
+            toml::to_string(&table).map_err(|e| MigrationError::ParseError(e.to_string()))?;
+        Config::from_toml_str(&migrated)
+    } else {
+        Config::from_toml_str(text)
+    }
+    .map_err(|e| MigrationError::ParseError(e.to_string()))?;
+
+    let report = (from < to).then_some(MigrationReport {
+        from,
+        to,
+        changes,
+        backup: None,
+    });
+    Ok((config, report))
+}
+
+fn parse_version(version: &str) -> Option<u32> {
+    version.trim().trim_start_matches(['v', 'V']).parse().ok()
+}
+
+/// `config.v1.bak.toml`, or `config.v1.bak2.toml` and so on if an earlier
+/// backup is in the way.
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "config".to_string());
+    let mut attempt = 1;
+    loop {
+        let suffix = if attempt == 1 {
+            String::new()
+        } else {
+            attempt.to_string()
+        };
+        let candidate = path.with_file_name(format!("{}.v{}.bak{}.toml", stem, version, suffix));
+        if !candidate.exists() {
+            return candidate;
+        }
+        attempt += 1;
+    }
+}
+
+/// v3 added the `[tiering]`, `[media]`, `[lint]` and `[sort]` tables, the
+/// iterables of the file structure and the unit names, day overrides, day
+/// template and calendar of `[setup]`. All of them are optional and left out
+/// they behave like v2 did, so only the version changes.
+fn v2_to_v3(table: &mut Table) -> Result<Vec<String>, String> {
+    if !table.get("setup").is_some_and(Value::is_table) {
+        return Err("the config has no [setup] section".to_string());
+    }
+    Ok(vec![
+        "Set version to v3, which adds the optional [tiering], [media], [lint] and [sort] \
+         tables and shoot-day dates to [setup]"
+            .to_string(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Query;
+
+    /// A config as nanopm wrote it before the layout was first changed.
+    const V2_CONFIG: &str = "version = \"v2\"
+general_query_params = [\"01_VIDEO\", \"02_AUDIO\"]
+
+[setup]
+name = \"Old\"
+days = 1
+cameras = 1
+sound_sources = 1
+
+[[file_structure.folders_list]]
+id = \"rushes\"
+name = \"02_RUSHES\"
+
+[[file_structure.folders_list]]
+id = \"days\"
+parent_id = \"rushes\"
+name = \"%days\"
+";
+
+    #[test]
+    fn test_migrate_v2_config() {
+        let (config, report) = migrate_str(V2_CONFIG).unwrap();
+        let report = report.unwrap();
+        assert_eq!((report.from, report.to), (2, 3));
+        assert_eq!(report.changes.len(), 1);
+        assert_eq!(config.version, "v3");
+        assert_eq!(config.setup.name, "Old");
+        assert_eq!(config.file_structure.folders_list.len(), 2);
+        assert_eq!(config.general_query_params, vec!["01_VIDEO", "02_AUDIO"]);
+        assert!(config.setup.calendar.is_empty());
+        assert!(config.tiering.is_empty());
+
+        let unversioned = V2_CONFIG.replace("version = \"v2\"\n", "");
+        assert!(matches!(
+            migrate_str(&unversioned),
+            Err(MigrationError::UnknownVersion(_))
+        ));
+        let older = V2_CONFIG.replace("version = \"v2\"", "version = \"v1\"");
+        assert!(matches!(
+            migrate_str(&older),
+            Err(MigrationError::UnknownVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_current_and_newer_versions() {
+        let current = toml::to_string(&Config::new_config()).unwrap();
+        assert!(migrate_str(&current).unwrap().1.is_none());
+        // Default tables are left out, so a new config reads like a v2 one.
+        assert!(!current.contains("[media]"));
+        assert!(!current.contains("[lint]"));
+        assert!(!current.contains("[sort]"));
+        assert_eq!(
+            migrate_str(&current).unwrap().0.general_query_params,
+            Query::get_default_general_query()
+        );
+
+        let newer = current.replace("version = \"v3\"", "version = \"v9\"");
+        assert!(matches!(
+            migrate_str(&newer),
+            Err(MigrationError::TooNew(_))
+        ));
+
+        let unknown = current.replace("version = \"v3\"", "version = \"beta\"");
+        assert!(matches!(
+            migrate_str(&unknown),
+            Err(MigrationError::UnknownVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_config_backs_up_before_writing() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, V2_CONFIG).unwrap();
+
+        let (_, report) = load_config(&path, false).unwrap();
+        assert!(report.unwrap().backup.is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), V2_CONFIG);
+
+        let (_, report) = load_config(&path, true).unwrap();
+        let backup = report.unwrap().backup.unwrap();
+        assert_eq!(backup, dir.path().join("config.v2.bak.toml"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), V2_CONFIG);
+
+        let (config, report) = load_config(&path, true).unwrap();
+        assert!(report.is_none());
+        assert_eq!(config.setup.name, "Old");
+    }
+}
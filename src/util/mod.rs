@@ -1,4 +1,23 @@
+pub mod archive;
+pub mod cli;
 pub mod config;
+pub mod ingest;
 pub mod init;
+pub mod iterable;
+pub mod lint;
+pub mod manifest;
+pub mod media;
+pub mod metadata;
+pub mod migration;
+pub mod pattern;
+pub mod plan;
+pub mod project;
 pub mod query;
+pub mod scan;
+pub mod sort;
+pub mod template;
+pub mod tier;
+#[allow(clippy::module_inception)]
 pub mod util;
+pub mod watch;
+pub mod workspace;
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+
+use crate::{
+    config::{Config, FolderPattern},
+    folder_paths,
+    query::QueryError,
+};
+
+/// A [`FolderPattern`] ready to test directories with. Paths are relative to
+/// the directory holding the config, so they start with the project folder
+/// (`Demo/02_RUSHES/01_DAY01`) like those from `generate_folder_paths`.
+#[derive(Debug, Clone)]
+pub enum FolderMatcher {
+    /// The last components of the path, one matcher per component.
+    Components(Vec<Component>),
+    Regex(Regex),
+    Paths(HashSet<String>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Component {
+    Name(String),
+    Glob(Regex),
+}
+
+impl FolderMatcher {
+    pub fn new(pattern: &FolderPattern, config: &Config) -> Result<Self, QueryError> {
+        Ok(match pattern {
+            FolderPattern::Name(name) => FolderMatcher::name(name),
+            FolderPattern::Glob(glob) => FolderMatcher::Components(
+                components(glob)
+                    .map(|component| {
+                        if is_glob(component) {
+                            glob_to_regex(component).map(Component::Glob)
+                        } else {
+                            Ok(Component::Name(component.to_string()))
+                        }
+                    })
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| {
+                        QueryError::InvalidQuery(format!("\"{}\" is not a valid glob: {}", glob, e))
+                    })?,
+            ),
+            FolderPattern::Regex(regex) => {
+                FolderMatcher::Regex(Regex::new(regex).map_err(|e| {
+                    QueryError::InvalidQuery(format!("\"{}\" is not a valid regex: {}", regex, e))
+                })?)
+            }
+            FolderPattern::Id(id) => {
+                FolderMatcher::Paths(folder_paths(config, id)?.into_iter().collect())
+            }
+        })
+    }
+
+    /// Matches folders called exactly `name`, or ending in the path `name`.
+    pub fn name(name: &str) -> Self {
+        FolderMatcher::Components(
+            components(name)
+                .map(|component| Component::Name(component.to_string()))
+                .collect(),
+        )
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            FolderMatcher::Components(pattern) => {
+                let path: Vec<&str> = components(path).collect();
+                !pattern.is_empty()
+                    && path.len() >= pattern.len()
+                    && path[path.len() - pattern.len()..].iter().zip(pattern).all(
+                        |(name, component)| match component {
+                            Component::Name(expected) => name == expected,
+                            Component::Glob(regex) => regex.is_match(name),
+                        },
+                    )
+            }
+            FolderMatcher::Regex(regex) => regex.is_match(path),
+            FolderMatcher::Paths(paths) => paths.contains(path),
+        }
+    }
+}
+
+fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|component| !component.is_empty())
+}
+
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Compiles a glob where `*` matches any run of characters and `?` any one
+/// character, none of them `/`, into a regex matching the whole string.
+pub fn glob_to_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_matches_whole_components() {
+        let video = FolderMatcher::name("01_VIDEO");
+        assert!(video.matches("Demo/02_RUSHES/01_DAY01/01_VIDEO"));
+        assert!(!video.matches("Demo/02_RUSHES/01_DAY01/X01_VIDEO"));
+        assert!(!video.matches("Demo/02_RUSHES/01_DAY01/01_VIDEO/01_A_CAM"));
+
+        let day_video = FolderMatcher::name("01_DAY01/01_VIDEO");
+        assert!(day_video.matches("Demo/02_RUSHES/01_DAY01/01_VIDEO"));
+        assert!(!day_video.matches("Demo/02_RUSHES/02_DAY02/01_VIDEO"));
+        assert!(FolderMatcher::name("Demo").matches("Demo"));
+        assert!(!FolderMatcher::name("").matches("Demo"));
+    }
+
+    #[test]
+    fn test_glob_and_regex_patterns() {
+        let config = Config::new_config();
+        let cams = FolderMatcher::new(&FolderPattern::parse("*_CAM"), &config).unwrap();
+        assert!(cams.matches("Demo/02_RUSHES/01_DAY01/01_VIDEO/02_B_CAM"));
+        assert!(!cams.matches("Demo/02_RUSHES/01_DAY01/02_AUDIO/02_B_REC"));
+        let day_cams = FolderMatcher::new(&FolderPattern::parse("0?_DAY*/*/*_CAM"), &config);
+        assert!(
+            day_cams
+                .unwrap()
+                .matches("Demo/02_RUSHES/01_DAY01/01_VIDEO/01_A_CAM")
+        );
+
+        let regex = FolderPattern::Regex("DAY0[12]/01_VIDEO$".to_string());
+        let regex = FolderMatcher::new(&regex, &config).unwrap();
+        assert!(regex.matches("Demo/02_RUSHES/02_DAY02/01_VIDEO"));
+        assert!(!regex.matches("Demo/02_RUSHES/03_DAY03/01_VIDEO"));
+        let invalid = FolderPattern::Regex("DAY(".to_string());
+        assert!(FolderMatcher::new(&invalid, &config).is_err());
+    }
+
+    #[test]
+    fn test_id_pattern_matches_generated_paths() {
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.setup.days = 2;
+
+        let video = FolderPattern::Id("video".to_string());
+        let video = FolderMatcher::new(&video, &config).unwrap();
+        assert!(video.matches("Demo/02_RUSHES/02_DAY02/01_VIDEO"));
+        assert!(!video.matches("Demo/02_RUSHES/03_DAY03/01_VIDEO"));
+        assert!(!video.matches("Demo/03_EXTERNAL/01_VIDEO"));
+
+        let unknown = FolderPattern::Id("unknown".to_string());
+        assert!(FolderMatcher::new(&unknown, &config).is_err());
+    }
+}
@@ -0,0 +1,594 @@
+use std::{collections::HashSet, path::Path};
+
+use fs_extra::dir::get_dir_content;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::{
+    config::{Config, ConfigError, OutputFormat},
+    day_folder_name, folder_entries, generate_folder_paths,
+    init::OperationType,
+    iterable,
+    query::to_shorthand,
+};
+
+/// Orphaned folders are moved here (keeping their relative path) by
+/// `update --archive`.
+pub const ARCHIVE_FOLDER_NAME: &str = "_ARCHIVED";
+
+/// Everything `new`/`update` would change on disk, computed without touching
+/// anything so it can be reviewed with `--dry-run` first.
+#[derive(Debug, Serialize, Default, Clone)]
+pub struct SetupPlan {
+    #[serde(skip_serializing)]
+    project_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rename: Option<Rename>,
+    /// Day folders whose name changes with their calendar entry, e.g. when
+    /// a day's date moves. Both paths are under the new project name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub day_renames: Vec<Rename>,
+    pub create: Vec<String>,
+    pub remove: Vec<String>,
+    pub orphans: Vec<OrphanedFolder>,
+    pub archive_orphans: bool,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+}
+
+impl Rename {
+    /// `path` once this rename is done, if it is the renamed folder or sits
+    /// inside it.
+    fn apply(&self, path: &str) -> Option<String> {
+        move_prefix(path, &self.from, &self.to)
+    }
+
+    /// Where `path` sits before this rename.
+    fn revert(&self, path: &str) -> Option<String> {
+        move_prefix(path, &self.to, &self.from)
+    }
+}
+
+fn move_prefix(path: &str, from: &str, to: &str) -> Option<String> {
+    let rest = path.strip_prefix(from)?;
+    (rest.is_empty() || rest.starts_with('/')).then(|| format!("{}{}", to, rest))
+}
+
+/// `path` once all of `renames` are done.
+fn after_renames(path: &str, renames: &[Rename]) -> String {
+    renames
+        .iter()
+        .find_map(|rename| rename.apply(path))
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// A folder the old config generated but the new one no longer does, which
+/// still holds files. Shrinking days/cameras/sound sources leaves these behind.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct OrphanedFolder {
+    /// Relative to the project root.
+    pub path: String,
+    pub file_count: usize,
+    pub total_size: String,
+    pub total_size_bytes: u64,
+}
+
+impl OrphanedFolder {
+    pub fn describe(&self, project_name: &str) -> String {
+        format!(
+            "{}/{} ({} file(s), {})",
+            project_name, self.path, self.file_count, self.total_size
+        )
+    }
+
+    pub fn archive_path(&self, project_name: &str) -> String {
+        format!("{}/{}/{}", project_name, ARCHIVE_FOLDER_NAME, self.path)
+    }
+}
+
+impl SetupPlan {
+    pub fn is_empty(&self) -> bool {
+        self.rename.is_none()
+            && self.day_renames.is_empty()
+            && self.create.is_empty()
+            && self.remove.is_empty()
+            && self.orphans.is_empty()
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String, ConfigError> {
+        match format {
+            OutputFormat::Text => Ok(self.to_listing()),
+            OutputFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| ConfigError::ParseError(format!("Failed to serialize plan: {}", e))),
+            OutputFormat::Toml => toml::to_string(self)
+                .map_err(|e| ConfigError::ParseError(format!("Failed to serialize plan: {}", e))),
+            OutputFormat::Csv => self
+                .to_csv()
+                .map_err(|e| ConfigError::ParseError(format!("Failed to serialize plan: {}", e))),
+        }
+    }
+
+    fn to_csv(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut rows: Vec<(&str, String, String)> = Vec::new();
+        for rename in self.rename.iter().chain(&self.day_renames) {
+            rows.push(("rename", rename.from.clone(), rename.to.clone()));
+        }
+        for path in &self.create {
+            rows.push(("create", path.clone(), String::new()));
+        }
+        for path in &self.remove {
+            rows.push(("remove", path.clone(), String::new()));
+        }
+        for orphan in &self.orphans {
+            let path = format!("{}/{}", self.project_name, orphan.path);
+            if self.archive_orphans {
+                rows.push(("archive", path, orphan.archive_path(&self.project_name)));
+            } else {
+                rows.push(("orphan", path, String::new()));
+            }
+        }
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(["action", "path", "to"])?;
+        for (action, path, to) in rows {
+            writer.write_record([action, &path, &to])?;
+        }
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+
+    fn to_listing(&self) -> String {
+        let mut text = String::from("[Setup Plan] Dry run, nothing on disk was changed.\n\n");
+        if self.is_empty() {
+            text.push_str("The project is already up to date.\n");
+            return text;
+        }
+        if let Some(rename) = &self.rename {
+            text.push_str(&format!("~ {} -> {}\n", rename.from, rename.to));
+        }
+        for rename in &self.day_renames {
+            text.push_str(&format!("~ {} -> {}\n", rename.from, rename.to));
+        }
+        for path in &self.create {
+            text.push_str(&format!("+ {}\n", path));
+        }
+        for path in &self.remove {
+            text.push_str(&format!("- {}\n", path));
+        }
+        for orphan in &self.orphans {
+            if self.archive_orphans {
+                text.push_str(&format!(
+                    "> {} -> {}\n",
+                    orphan.describe(&self.project_name),
+                    orphan.archive_path(&self.project_name)
+                ));
+            } else {
+                text.push_str(&format!("! {}\n", orphan.describe(&self.project_name)));
+            }
+        }
+        text.push_str(&format!(
+            "\n{} rename(s), {} folder(s) to create, {} empty folder(s) to remove\n",
+            usize::from(self.rename.is_some()) + self.day_renames.len(),
+            self.create.len(),
+            self.remove.len()
+        ));
+        if !self.orphans.is_empty() && !self.archive_orphans {
+            text.push_str(&format!(
+                "{} folder(s) marked ! still hold media and would no longer be part of the \
+                 project. Update refuses to run without --force or --archive.\n",
+                self.orphans.len()
+            ));
+        }
+        text
+    }
+}
+
+/// Mirrors `setup`: the root rename, day folder renames, every generated
+/// folder that does not exist yet and, with `--clean`, every empty folder the
+/// cleanup would delete.
+/// All paths are relative to `base`.
+pub fn plan_setup(
+    base: &Path,
+    old_config: Option<&Config>,
+    config: &Config,
+    op_type: &OperationType,
+) -> Result<SetupPlan, ConfigError> {
+    config.validate()?;
+
+    let setup = &config.setup;
+    let exists = |name: &str| base.join(name).exists();
+
+    let rename_from = match (&setup.deadname, old_config) {
+        (Some(deadname), _) if exists(deadname) => Some(deadname.clone()),
+        (None, Some(old_config))
+            if op_type == &OperationType::Update
+                && exists(&old_config.setup.name)
+                && old_config.setup.name != setup.name =>
+        {
+            Some(old_config.setup.name.clone())
+        }
+        _ => None,
+    };
+
+    // The folder that will become the project root, as it is named right now.
+    let current_root = rename_from.clone().unwrap_or_else(|| setup.name.clone());
+    let on_disk = |path: &str| match path.strip_prefix(&setup.name) {
+        Some(rest) => base.join(format!("{}{}", current_root, rest)),
+        None => base.join(path),
+    };
+
+    let day_renames: Vec<Rename> = match old_config {
+        Some(old_config) if op_type == &OperationType::Update => {
+            find_day_renames(old_config, config)?
+                .into_iter()
+                .filter(|rename| on_disk(&rename.from).is_dir())
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    // Where a generated folder sits right now, before the day renames.
+    let before_renames = |path: &str| match day_renames.iter().find_map(|r| r.revert(path)) {
+        Some(path) => on_disk(&path),
+        None => on_disk(path),
+    };
+
+    let paths = generate_folder_paths(config)?;
+    let mut plan = SetupPlan {
+        project_name: setup.name.clone(),
+        rename: rename_from.map(|from| Rename {
+            from,
+            to: setup.name.clone(),
+        }),
+        create: paths
+            .iter()
+            .filter(|path| !before_renames(path).exists())
+            .cloned()
+            .collect(),
+        day_renames,
+        remove: Vec::new(),
+        orphans: Vec::new(),
+        archive_orphans: setup.archive_orphans,
+    };
+
+    if setup.clean_project {
+        plan.remove = plan_cleanup(
+            &base.join(&current_root),
+            &setup.name,
+            &paths,
+            &plan.day_renames,
+        );
+    }
+
+    if let Some(old_config) = old_config
+        && op_type == &OperationType::Update
+    {
+        plan.orphans = find_orphans(
+            &base.join(&current_root),
+            old_config,
+            config,
+            &plan.day_renames,
+        )?;
+    }
+
+    Ok(plan)
+}
+
+/// Pairs every %days folder of `config` with its name under `old_config`,
+/// keeping the ones that differ. The old paths are rebased onto the new
+/// project name, since the root is renamed first.
+pub fn find_day_renames(old_config: &Config, config: &Config) -> Result<Vec<Rename>, ConfigError> {
+    let days_name = format!("%{}", iterable::DAYS);
+    let mut renames = Vec::new();
+    for folder in &config.file_structure.folders_list {
+        if folder.name != days_name {
+            continue;
+        }
+        for (path, day) in folder_entries(config, &folder.id)? {
+            let (Some(day), Some((parent, name))) = (day, path.rsplit_once('/')) else {
+                continue;
+            };
+            if day > old_config.setup.days {
+                continue;
+            }
+            let old_name = day_folder_name(&old_config.setup, day);
+            if old_name != name {
+                renames.push(Rename {
+                    from: format!("{}/{}", parent, old_name),
+                    to: path,
+                });
+            }
+        }
+    }
+    Ok(renames)
+}
+
+/// Finds the top-most folders generated by `old_config` but not by `config`
+/// that still contain files. Folders `day_renames` moves are not orphaned.
+pub fn find_orphans(
+    root_on_disk: &Path,
+    old_config: &Config,
+    config: &Config,
+    day_renames: &[Rename],
+) -> Result<Vec<OrphanedFolder>, ConfigError> {
+    let relative_paths = |config: &Config| -> Result<Vec<String>, ConfigError> {
+        let prefix = format!("{}/", config.setup.name);
+        Ok(generate_folder_paths(config)?
+            .into_iter()
+            .filter_map(|path| path.strip_prefix(&prefix).map(str::to_string))
+            .collect())
+    };
+
+    let prefix = format!("{}/", config.setup.name);
+    let renamed = |path: &str| {
+        let renamed = after_renames(&format!("{}{}", prefix, path), day_renames);
+        renamed
+            .strip_prefix(&prefix)
+            .map_or(renamed.clone(), str::to_string)
+    };
+
+    let new_paths: HashSet<String> = relative_paths(config)?.into_iter().collect();
+    let mut candidates: Vec<String> = relative_paths(old_config)?
+        .into_iter()
+        .filter(|path| !new_paths.contains(&renamed(path)))
+        .collect();
+    candidates.sort();
+
+    let mut orphans: Vec<OrphanedFolder> = Vec::new();
+    for path in candidates {
+        if orphans
+            .iter()
+            .any(|orphan| path.starts_with(&format!("{}/", orphan.path)))
+        {
+            continue;
+        }
+        let folder = root_on_disk.join(&path);
+        if !folder.is_dir() {
+            continue;
+        }
+        let content = get_dir_content(&folder)
+            .map_err(|e| ConfigError::ParseError(format!("Could not scan {}: {}", path, e)))?;
+        if content.files.is_empty() {
+            continue;
+        }
+        orphans.push(OrphanedFolder {
+            path,
+            file_count: content.files.len(),
+            total_size: to_shorthand(content.dir_size),
+            total_size_bytes: content.dir_size,
+        });
+    }
+
+    Ok(orphans)
+}
+
+/// A folder is cleaned up when it is not generated, holds no files anywhere
+/// below it and has no generated folder below it either. Folders are judged
+/// (and listed) by where they end up after `day_renames`, as `update` renames
+/// before it cleans.
+fn plan_cleanup(
+    root_on_disk: &Path,
+    project_name: &str,
+    valid_paths: &[String],
+    day_renames: &[Rename],
+) -> Vec<String> {
+    let valid: HashSet<&str> = valid_paths.iter().map(String::as_str).collect();
+    let mut keep: HashSet<String> = HashSet::new();
+
+    for path in valid_paths {
+        mark_ancestors(path, &mut keep);
+    }
+
+    let mut directories = Vec::new();
+    for entry in WalkDir::new(root_on_disk)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let relative = match entry.path().strip_prefix(root_on_disk) {
+            Ok(relative) => relative.to_string_lossy().replace("\\", "/"),
+            Err(_) => continue,
+        };
+        let path = if relative.is_empty() {
+            project_name.to_string()
+        } else {
+            after_renames(&format!("{}/{}", project_name, relative), day_renames)
+        };
+
+        if entry.file_type().is_dir() {
+            directories.push(path);
+        } else {
+            mark_ancestors(&path, &mut keep);
+        }
+    }
+
+    let mut remove: Vec<String> = directories
+        .into_iter()
+        .filter(|path| !valid.contains(path.as_str()) && !keep.contains(path))
+        .collect();
+    remove.sort();
+    remove
+}
+
+fn mark_ancestors(path: &str, keep: &mut HashSet<String>) {
+    let mut current = path;
+    while let Some(index) = current.rfind('/') {
+        current = &current[..index];
+        keep.insert(current.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn test_config(name: &str) -> Config {
+        let mut config = Config::new_config();
+        config.setup.name = name.to_string();
+        config.setup.days = 1;
+        config.setup.cameras = 1;
+        config
+    }
+
+    #[test]
+    fn test_plan_new_project_creates_everything() {
+        let base = tempfile::TempDir::new().unwrap();
+        let config = test_config("Demo");
+
+        let plan = plan_setup(base.path(), None, &config, &OperationType::New).unwrap();
+
+        assert!(plan.rename.is_none());
+        assert_eq!(plan.create, generate_folder_paths(&config).unwrap());
+        assert!(plan.remove.is_empty());
+        assert!(fs::read_dir(base.path()).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_plan_update_with_rename_and_clean() {
+        let base = tempfile::TempDir::new().unwrap();
+        let old_config = test_config("Old");
+        for path in generate_folder_paths(&old_config).unwrap() {
+            fs::create_dir_all(base.path().join(path)).unwrap();
+        }
+        fs::create_dir_all(base.path().join("Old/stray/empty")).unwrap();
+        fs::create_dir_all(base.path().join("Old/notes")).unwrap();
+        fs::write(base.path().join("Old/notes/todo.txt"), b"keep me").unwrap();
+
+        let mut config = test_config("New");
+        config.setup.days = 2;
+        config.setup.clean_project = true;
+
+        let plan = plan_setup(
+            base.path(),
+            Some(&old_config),
+            &config,
+            &OperationType::Update,
+        )
+        .unwrap();
+
+        assert_eq!(
+            plan.rename,
+            Some(Rename {
+                from: "Old".to_string(),
+                to: "New".to_string()
+            })
+        );
+        assert!(plan.create.contains(&"New/02_RUSHES/02_DAY02".to_string()));
+        assert!(!plan.create.contains(&"New/02_RUSHES/01_DAY01".to_string()));
+        assert_eq!(plan.remove, vec!["New/stray", "New/stray/empty"]);
+        assert!(base.path().join("Old/stray/empty").exists());
+    }
+
+    #[test]
+    fn test_plan_cleanup_follows_day_renames() {
+        let base = tempfile::TempDir::new().unwrap();
+        let mut old_config = test_config("Demo");
+        old_config.setup.day_template = Some("{index:02}_DAY{index:02}_{date}".to_string());
+        old_config.setup.set_shoot_day(1, |entry| {
+            entry.date = chrono::NaiveDate::from_ymd_opt(2026, 10, 17)
+        });
+        for path in generate_folder_paths(&old_config).unwrap() {
+            fs::create_dir_all(base.path().join(path)).unwrap();
+        }
+        let day = base.path().join("Demo/02_RUSHES/01_DAY01_2026-10-17");
+        fs::create_dir_all(day.join("stray")).unwrap();
+
+        let mut config = old_config.clone();
+        config.setup.set_shoot_day(1, |entry| {
+            entry.date = chrono::NaiveDate::from_ymd_opt(2026, 10, 18)
+        });
+        config.setup.clean_project = true;
+        let plan = plan_setup(
+            base.path(),
+            Some(&old_config),
+            &config,
+            &OperationType::Update,
+        )
+        .unwrap();
+
+        assert_eq!(plan.day_renames.len(), 1);
+        assert!(plan.create.is_empty());
+        assert_eq!(
+            plan.remove,
+            vec!["Demo/02_RUSHES/01_DAY01_2026-10-18/stray"]
+        );
+    }
+
+    #[test]
+    fn test_render_plan() {
+        let plan = SetupPlan {
+            project_name: "Demo".to_string(),
+            rename: None,
+            day_renames: vec![Rename {
+                from: "Demo/02_RUSHES/01_DAY01".to_string(),
+                to: "Demo/02_RUSHES/01_DAY01_2026-10-17".to_string(),
+            }],
+            create: vec!["Demo/01_DOCUMENTATION".to_string()],
+            remove: vec!["Demo/stray".to_string()],
+            orphans: vec![OrphanedFolder {
+                path: "02_RUSHES/03_DAY03".to_string(),
+                file_count: 2,
+                total_size: "4KiB (4KB)".to_string(),
+                total_size_bytes: 4096,
+            }],
+            archive_orphans: false,
+        };
+        let listing = plan.render(OutputFormat::Text).unwrap();
+        assert!(listing.contains("+ Demo/01_DOCUMENTATION\n"));
+        assert!(listing.contains("- Demo/stray\n"));
+        assert!(
+            listing.contains("~ Demo/02_RUSHES/01_DAY01 -> Demo/02_RUSHES/01_DAY01_2026-10-17\n")
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&plan.render(OutputFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["create"][0], "Demo/01_DOCUMENTATION");
+
+        let csv = plan.render(OutputFormat::Csv).unwrap();
+        assert!(csv.contains("remove,Demo/stray,\n"));
+        assert!(csv.contains("orphan,Demo/02_RUSHES/03_DAY03,\n"));
+
+        let quoted = SetupPlan {
+            create: vec!["Demo/Scene 1, \"Pier\"".to_string()],
+            ..SetupPlan::default()
+        };
+        let csv = quoted.render(OutputFormat::Csv).unwrap();
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let row = reader.records().next().unwrap().unwrap();
+        assert_eq!(&row[1], "Demo/Scene 1, \"Pier\"");
+        assert!(listing.contains("! Demo/02_RUSHES/03_DAY03 (2 file(s), 4KiB (4KB))\n"));
+    }
+
+    #[test]
+    fn test_find_orphans_after_shrinking() {
+        let base = tempfile::TempDir::new().unwrap();
+        let mut old_config = test_config("Demo");
+        old_config.setup.days = 3;
+        old_config.setup.cameras = 3;
+        for path in generate_folder_paths(&old_config).unwrap() {
+            fs::create_dir_all(base.path().join(path)).unwrap();
+        }
+        let root = base.path().join("Demo");
+        fs::write(
+            root.join("02_RUSHES/01_DAY01/01_VIDEO/03_C_CAM/C001.mov"),
+            b"footage",
+        )
+        .unwrap();
+        fs::write(root.join("02_RUSHES/03_DAY03/03_VO/vo.wav"), b"voice").unwrap();
+
+        let mut config = test_config("Demo");
+        config.setup.days = 2;
+
+        let orphans = find_orphans(&root, &old_config, &config, &[]).unwrap();
+        let paths: Vec<&str> = orphans.iter().map(|o| o.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["02_RUSHES/01_DAY01/01_VIDEO/03_C_CAM", "02_RUSHES/03_DAY03"]
+        );
+        assert_eq!(orphans[1].file_count, 1);
+        assert!(orphans[1].total_size_bytes >= 5);
+    }
+}
@@ -0,0 +1,685 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    config::{Config, ConfigError, Query, QuerySettings},
+    generate_folder_paths,
+    init::{OperationType, ProjectSetup},
+    migration::{self, MigrationError, MigrationReport},
+    plan::{self, OrphanedFolder, Rename, SetupPlan},
+    query::{self, QueryError, QueryResult},
+};
+
+pub const CONFIG_FILE: &str = "config.toml";
+/// Overrides project discovery, like `--project`.
+pub const PROJECT_ENV: &str = "NANOPM_PROJECT";
+/// Where `create` moves a config it cannot read, so the project can start fresh.
+pub const DISCARDED_CONFIG_FILE: &str = "config_old.toml";
+
+#[derive(Debug)]
+pub enum ProjectError {
+    IoError(std::io::Error),
+    ConfigError(ConfigError),
+    MigrationError(MigrationError),
+    QueryError(QueryError),
+    NotAProject(PathBuf),
+    NoProjectFound(PathBuf),
+}
+
+impl std::fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectError::IoError(e) => write!(f, "IO error: {}", e),
+            ProjectError::ConfigError(e) => write!(f, "{}", e),
+            ProjectError::MigrationError(e) => write!(f, "{}", e),
+            ProjectError::QueryError(e) => write!(f, "{}", e),
+            ProjectError::NotAProject(root) => write!(
+                f,
+                "No {} found in \"{}\", run nanopm new first",
+                CONFIG_FILE,
+                root.to_string_lossy().replace("\\", "/")
+            ),
+            ProjectError::NoProjectFound(start) => write!(
+                f,
+                "No nanopm project found in \"{}\" or any of its parent directories, run \
+                 nanopm new first or point --project (or {}) at one",
+                start.to_string_lossy().replace("\\", "/"),
+                PROJECT_ENV
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+impl From<std::io::Error> for ProjectError {
+    fn from(error: std::io::Error) -> Self {
+        ProjectError::IoError(error)
+    }
+}
+
+impl From<ConfigError> for ProjectError {
+    fn from(error: ConfigError) -> Self {
+        ProjectError::ConfigError(error)
+    }
+}
+
+impl From<MigrationError> for ProjectError {
+    fn from(error: MigrationError) -> Self {
+        ProjectError::MigrationError(error)
+    }
+}
+
+impl From<QueryError> for ProjectError {
+    fn from(error: QueryError) -> Self {
+        ProjectError::QueryError(error)
+    }
+}
+
+/// What `create`/`update` did on top of creating the project's folders.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SetupReport {
+    /// Set when an existing config from an older nanopm was upgraded.
+    pub migration: Option<MigrationReport>,
+    /// Why an existing config was unreadable and moved to `config_old.toml`.
+    pub discarded_config: Option<String>,
+    /// Day folders renamed after their calendar entry, as (from, to).
+    pub renamed: Vec<(String, String)>,
+    /// Orphaned folders moved by `--archive`, as (from, to).
+    pub archived: Vec<(String, String)>,
+    /// Empty folders deleted by `--clean`.
+    pub removed: Vec<String>,
+}
+
+impl SetupReport {
+    pub fn print(&self) {
+        if let Some(migration) = &self.migration {
+            migration.print();
+        }
+        if let Some(error) = &self.discarded_config {
+            eprintln!("Problem opening the file: {}", error);
+            eprintln!("Moved the unreadable config to {}", DISCARDED_CONFIG_FILE);
+        }
+        for (from, to) in &self.renamed {
+            println!("Renamed {} to {}", from, to);
+        }
+        for (from, to) in &self.archived {
+            println!("Archived {} to {}", from, to);
+        }
+        for path in &self.removed {
+            println!("Removed empty directory: {}", path);
+        }
+    }
+}
+
+/// A nanopm project: the directory holding `config.toml` (the root) and the
+/// project folder the config describes inside it.
+#[derive(Debug, Clone)]
+pub struct Project {
+    root: PathBuf,
+    config: Config,
+    migration: Option<MigrationReport>,
+}
+
+impl Project {
+    /// Opens the project at `root`. A config from an older nanopm is upgraded
+    /// on disk, after backing up the original.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, ProjectError> {
+        Self::load(root.as_ref(), true)
+    }
+
+    /// Opens the nearest project at or above `start`, see `discover_root`.
+    pub fn discover(start: impl AsRef<Path>) -> Result<Self, ProjectError> {
+        let start = start.as_ref();
+        let root = discover_root(start)
+            .ok_or_else(|| ProjectError::NoProjectFound(start.to_path_buf()))?;
+        Self::open(start.join(root))
+    }
+
+    /// Like `open`, but never writes to disk: older configs are only upgraded
+    /// in memory.
+    pub fn open_read_only(root: impl AsRef<Path>) -> Result<Self, ProjectError> {
+        Self::load(root.as_ref(), false)
+    }
+
+    fn load(root: &Path, persist: bool) -> Result<Self, ProjectError> {
+        let path = root.join(CONFIG_FILE);
+        if !path.exists() {
+            return Err(ProjectError::NotAProject(root.to_path_buf()));
+        }
+        let (config, migration) = migration::load_config(&path, persist)?;
+        Ok(Project {
+            root: root.to_path_buf(),
+            config,
+            migration,
+        })
+    }
+
+    /// Initializes a project at `root` from `config`, taking over whatever an
+    /// existing config there already set up.
+    pub fn create(
+        root: impl AsRef<Path>,
+        config: Config,
+    ) -> Result<(Self, SetupReport), ProjectError> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+        let mut report = SetupReport::default();
+        let old_config = read_old_config(&root, true, &mut report)?;
+        setup(
+            &root,
+            old_config.as_ref(),
+            &config,
+            &OperationType::New,
+            &mut report,
+        )?;
+        let project = Project {
+            root,
+            config,
+            migration: None,
+        };
+        Ok((project, report))
+    }
+
+    /// What `create` would change on disk, without changing anything.
+    pub fn plan_create(root: impl AsRef<Path>, config: &Config) -> Result<SetupPlan, ProjectError> {
+        let root = root.as_ref();
+        let old_config = read_old_config(root, false, &mut SetupReport::default())?;
+        Ok(plan::plan_setup(
+            root,
+            old_config.as_ref(),
+            config,
+            &OperationType::New,
+        )?)
+    }
+
+    /// Applies `config` to the project, renaming, creating and (if asked)
+    /// archiving or cleaning folders, then saves it.
+    pub fn update(&mut self, config: Config) -> Result<SetupReport, ProjectError> {
+        let mut report = SetupReport::default();
+        setup(
+            &self.root,
+            Some(&self.config),
+            &config,
+            &OperationType::Update,
+            &mut report,
+        )?;
+        self.config = config;
+        self.migration = None;
+        Ok(report)
+    }
+
+    /// What `update` would change on disk, without changing anything.
+    pub fn plan(&self, config: &Config) -> Result<SetupPlan, ProjectError> {
+        Ok(plan::plan_setup(
+            &self.root,
+            Some(&self.config),
+            config,
+            &OperationType::Update,
+        )?)
+    }
+
+    /// Runs `query` and returns its results instead of printing them. Missing
+    /// folders are still reported on stdout unless `settings.quiet` is set.
+    pub fn query(
+        &self,
+        query: &Query,
+        settings: &QuerySettings,
+    ) -> Result<Vec<QueryResult>, ProjectError> {
+        Ok(query::run_query(
+            &self.root,
+            query,
+            &self.config,
+            settings,
+            Instant::now(),
+        )?)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Set when `open` upgraded a config from an older nanopm.
+    pub fn migration_report(&self) -> Option<&MigrationReport> {
+        self.migration.as_ref()
+    }
+}
+
+/// Walks up from `start`, git-style, to the nearest directory holding a
+/// nanopm config. The root is returned relative to `start` (`.`, `..`, ...)
+/// so paths under it stay as short as they were before discovery. `start` is
+/// canonicalized first: the kernel resolves those `..` steps against physical
+/// parents, so walking the path as typed would go wrong through a symlink.
+pub fn discover_root(start: &Path) -> Option<PathBuf> {
+    let start = fs::canonicalize(start).ok()?;
+    start
+        .ancestors()
+        .position(|dir| is_project_config(&dir.join(CONFIG_FILE)))
+        .map(|depth| match depth {
+            0 => PathBuf::from("."),
+            _ => (0..depth).map(|_| "..").collect(),
+        })
+}
+
+/// The root to work in: `explicit` (from `--project`/`NANOPM_PROJECT`, either
+/// the project directory or its config file) if given, otherwise the nearest
+/// project above the current directory.
+pub fn locate_root(explicit: Option<&Path>) -> Result<PathBuf, ProjectError> {
+    match explicit {
+        Some(path) => {
+            let root = if path.is_file() {
+                match path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                    _ => PathBuf::from("."),
+                }
+            } else {
+                path.to_path_buf()
+            };
+            if !root.join(CONFIG_FILE).is_file() {
+                return Err(ProjectError::NotAProject(root));
+            }
+            Ok(root)
+        }
+        None => {
+            let current_dir = std::env::current_dir()?;
+            discover_root(&current_dir).ok_or(ProjectError::NoProjectFound(current_dir))
+        }
+    }
+}
+
+/// `config.toml` is a common name, so only configs with a `[setup]` table
+/// count as nanopm projects during discovery.
+fn is_project_config(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| text.parse::<toml::Table>().ok())
+        .is_some_and(|table| table.get("setup").is_some_and(toml::Value::is_table))
+}
+
+/// Reads the existing config at `root`, if any, migrating it from older nanopm
+/// versions (with a backup) when `persist` is set. A config that cannot be
+/// read or migrated is moved to `config_old.toml` so `new` can start fresh,
+/// unless `persist` is false (dry runs must not touch the disk). A config
+/// written by a newer nanopm is never discarded.
+fn read_old_config(
+    root: &Path,
+    persist: bool,
+    report: &mut SetupReport,
+) -> Result<Option<Config>, ProjectError> {
+    let path = root.join(CONFIG_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    match migration::load_config(&path, persist) {
+        Ok((config, migration)) => {
+            report.migration = migration;
+            Ok(Some(config))
+        }
+        Err(error @ MigrationError::TooNew(_)) => Err(error.into()),
+        Err(error) => {
+            if persist {
+                fs::rename(&path, root.join(DISCARDED_CONFIG_FILE))?;
+                report.discarded_config = Some(error.to_string());
+            }
+            Ok(Some(Config::new_config()))
+        }
+    }
+}
+
+fn setup(
+    root: &Path,
+    old_config: Option<&Config>,
+    config: &Config,
+    op_type: &OperationType,
+    report: &mut SetupReport,
+) -> Result<(), ConfigError> {
+    let old_config_exists = old_config.is_some();
+    let mut old_config = old_config.cloned().unwrap_or_else(Config::new_config);
+
+    config.validate()?;
+
+    let old_setup = &old_config.setup;
+    let setup = &config.setup;
+
+    let (day_renames, orphans) = if old_config_exists {
+        let plan = plan::plan_setup(root, Some(&old_config), config, op_type)?;
+        (plan.day_renames, plan.orphans)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    if !orphans.is_empty() && !setup.force && !setup.archive_orphans {
+        let held = orphans
+            .iter()
+            .map(|orphan| orphan.describe(&setup.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(ConfigError::UnsafeUpdate(format!(
+            "{} folder(s) that still hold media would no longer be part of the project ({}). \
+             Re-run with --force to leave them where they are, or --archive to move them into {}.",
+            orphans.len(),
+            held,
+            plan::ARCHIVE_FOLDER_NAME
+        )));
+    }
+
+    match &setup.deadname {
+        Some(deadname) => initialize_main_folder_deadname(root, deadname, setup)?,
+        None => initialize_main_folder(root, old_setup, setup, op_type, old_config_exists)?,
+    };
+
+    let config_path = root.join(CONFIG_FILE).to_string_lossy().to_string();
+    old_config.setup.name = setup.name.clone();
+    Config::write_config(&old_config, &config_path)?;
+
+    rename_days(root, &day_renames, report)?;
+
+    if setup.archive_orphans {
+        archive_orphans(root, &setup.name, &orphans, report)?;
+    }
+
+    let paths = generate_folder_paths(config)?;
+
+    for path in &paths {
+        let path = root.join(path);
+        if !path.exists() {
+            fs::create_dir_all(path).map_err(ConfigError::IoError)?;
+        }
+    }
+
+    if setup.clean_project {
+        clean_empty_directories(root, &setup.name, &paths, report)?;
+    }
+
+    Config::write_config(config, &config_path)?;
+    Ok(())
+}
+
+fn rename_days(
+    root: &Path,
+    day_renames: &[Rename],
+    report: &mut SetupReport,
+) -> Result<(), ConfigError> {
+    for rename in day_renames {
+        let target = root.join(&rename.to);
+        if target.exists() {
+            return Err(ConfigError::UnsafeUpdate(format!(
+                "Cannot rename {} because {} already exists",
+                rename.from, rename.to
+            )));
+        }
+        fs::rename(root.join(&rename.from), &target).map_err(ConfigError::IoError)?;
+        report
+            .renamed
+            .push((rename.from.clone(), rename.to.clone()));
+    }
+    Ok(())
+}
+
+fn archive_orphans(
+    root: &Path,
+    project_name: &str,
+    orphans: &[OrphanedFolder],
+    report: &mut SetupReport,
+) -> Result<(), ConfigError> {
+    for orphan in orphans {
+        let from = format!("{}/{}", project_name, orphan.path);
+        let to = orphan.archive_path(project_name);
+        let target = root.join(&to);
+        if target.exists() {
+            return Err(ConfigError::UnsafeUpdate(format!(
+                "Cannot archive {} because {} already exists",
+                from, to
+            )));
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(ConfigError::IoError)?;
+        }
+        fs::rename(root.join(&from), &target).map_err(ConfigError::IoError)?;
+        report.archived.push((orphan.describe(project_name), to));
+    }
+    Ok(())
+}
+
+fn clean_empty_directories(
+    root: &Path,
+    project_name: &str,
+    valid_paths: &[String],
+    report: &mut SetupReport,
+) -> Result<(), ConfigError> {
+    let valid_paths: Vec<PathBuf> = valid_paths.iter().map(|path| root.join(path)).collect();
+    let mut cleaned_this_pass = true;
+    const MAX_ITERATIONS: i32 = 100;
+    let mut iterations = 0;
+
+    while cleaned_this_pass && iterations < MAX_ITERATIONS {
+        cleaned_this_pass = false;
+        iterations += 1;
+
+        for entry in WalkDir::new(root.join(project_name))
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            if entry.file_type().is_dir()
+                && WalkDir::new(entry.path()).into_iter().nth(1).is_none()
+                && !valid_paths.iter().any(|path| path == entry.path())
+            {
+                fs::remove_dir(entry.path()).map_err(ConfigError::IoError)?;
+                report
+                    .removed
+                    .push(entry.path().to_string_lossy().replace("\\", "/"));
+                cleaned_this_pass = true;
+            }
+        }
+    }
+
+    if iterations >= MAX_ITERATIONS {
+        return Err(ConfigError::ParseError(
+            "Exceeded maximum cleanup iterations".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn initialize_main_folder(
+    root: &Path,
+    old_setup: &ProjectSetup,
+    setup: &ProjectSetup,
+    op_type: &OperationType,
+    old_config_exists: bool,
+) -> Result<(), ConfigError> {
+    let old_folder = root.join(&old_setup.name);
+    let folder = root.join(&setup.name);
+    if old_config_exists
+        && op_type == &OperationType::Update
+        && old_folder.exists()
+        && old_setup.name != setup.name
+    {
+        fs::rename(old_folder, folder).map_err(ConfigError::IoError)?;
+    } else if !folder.exists() {
+        fs::create_dir(folder).map_err(ConfigError::IoError)?;
+    }
+    Ok(())
+}
+
+fn initialize_main_folder_deadname(
+    root: &Path,
+    deadname: &str,
+    setup: &ProjectSetup,
+) -> Result<(), ConfigError> {
+    let dead_folder = root.join(deadname);
+    let folder = root.join(&setup.name);
+    if dead_folder.exists() {
+        fs::rename(dead_folder, folder).map_err(ConfigError::IoError)?;
+    } else if !folder.exists() {
+        fs::create_dir(folder).map_err(ConfigError::IoError)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SortType;
+
+    #[test]
+    fn test_create_update_and_query() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(matches!(
+            Project::open(dir.path()),
+            Err(ProjectError::NotAProject(_))
+        ));
+
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        let (mut project, report) = Project::create(dir.path(), config.clone()).unwrap();
+        assert_eq!(report, SetupReport::default());
+        assert!(dir.path().join("Demo/02_RUSHES/02_DAY02").is_dir());
+
+        config.setup.name = "Renamed".to_string();
+        config.setup.days = 3;
+        assert!(project.plan(&config).unwrap().rename.is_some());
+        project.update(config).unwrap();
+        assert!(dir.path().join("Renamed/02_RUSHES/03_DAY03").is_dir());
+        assert!(!dir.path().join("Demo").exists());
+
+        let reopened = Project::open(dir.path()).unwrap();
+        assert_eq!(reopened.config().setup.days, 3);
+        let settings = QuerySettings {
+            quiet: true,
+            ..Default::default()
+        };
+        let results = reopened
+            .query(&Query::General(SortType::ByDefaultOrder), &settings)
+            .unwrap();
+        assert!(matches!(results[0], QueryResult::RootResult(_)));
+    }
+
+    #[test]
+    fn test_update_refuses_to_orphan_media() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        let (mut project, _) = Project::create(dir.path(), config.clone()).unwrap();
+        fs::write(dir.path().join("Demo/02_RUSHES/02_DAY02/clip.mov"), "x").unwrap();
+
+        config.setup.days = 1;
+        assert!(matches!(
+            project.update(config.clone()),
+            Err(ProjectError::ConfigError(ConfigError::UnsafeUpdate(_)))
+        ));
+
+        config.setup.archive_orphans = true;
+        let report = project.update(config).unwrap();
+        assert_eq!(report.archived.len(), 1);
+        assert!(
+            dir.path()
+                .join("Demo/_ARCHIVED/02_RUSHES/02_DAY02/clip.mov")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_update_renames_dated_days() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.setup.day_template = Some("{index:02}_DAY{index:02}_{date}".to_string());
+        let date = |day| chrono::NaiveDate::from_ymd_opt(2026, 10, day);
+        config.setup.set_shoot_day(1, |entry| entry.date = date(17));
+        let (mut project, _) = Project::create(dir.path(), config.clone()).unwrap();
+        let day = dir.path().join("Demo/02_RUSHES/01_DAY01_2026-10-17");
+        fs::write(day.join("01_VIDEO/01_A_CAM/A001.mov"), "x").unwrap();
+        assert!(dir.path().join("Demo/02_RUSHES/02_DAY02").is_dir());
+
+        config.setup.name = "Renamed".to_string();
+        config.setup.set_shoot_day(1, |entry| entry.date = date(18));
+        config.setup.set_shoot_day(2, |entry| entry.date = date(19));
+        let plan = project.plan(&config).unwrap();
+        assert_eq!(plan.day_renames.len(), 2);
+        assert!(plan.orphans.is_empty());
+        assert!(
+            !plan
+                .create
+                .iter()
+                .any(|path| path.contains("DAY01") || path.contains("DAY02"))
+        );
+
+        let report = project.update(config).unwrap();
+        assert_eq!(
+            report.renamed[0],
+            (
+                "Renamed/02_RUSHES/01_DAY01_2026-10-17".to_string(),
+                "Renamed/02_RUSHES/01_DAY01_2026-10-18".to_string()
+            )
+        );
+        let day = dir.path().join("Renamed/02_RUSHES/01_DAY01_2026-10-18");
+        assert!(day.join("01_VIDEO/01_A_CAM/A001.mov").exists());
+        assert!(
+            dir.path()
+                .join("Renamed/02_RUSHES/02_DAY02_2026-10-19")
+                .is_dir()
+        );
+        assert!(!dir.path().join("Renamed/02_RUSHES/02_DAY02").exists());
+    }
+
+    #[test]
+    fn test_discover_root_walks_up() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        Project::create(dir.path(), config).unwrap();
+
+        let day = dir.path().join("Demo/02_RUSHES/01_DAY01");
+        assert_eq!(discover_root(&day), Some(PathBuf::from("../../..")));
+        assert_eq!(discover_root(dir.path()), Some(PathBuf::from(".")));
+        assert_eq!(Project::discover(&day).unwrap().config().setup.name, "Demo");
+
+        // Someone else's config.toml does not stop the walk.
+        fs::write(dir.path().join("Demo/config.toml"), "title = \"site\"\n").unwrap();
+        assert_eq!(discover_root(&day), Some(PathBuf::from("../../..")));
+
+        assert_eq!(
+            locate_root(Some(&dir.path().join(CONFIG_FILE))).unwrap(),
+            dir.path()
+        );
+        assert!(matches!(
+            locate_root(Some(&day)),
+            Err(ProjectError::NotAProject(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_root_through_symlink() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        Project::create(dir.path(), config).unwrap();
+
+        let elsewhere = tempfile::TempDir::new().unwrap();
+        let link = elsewhere.path().join("day1");
+        std::os::unix::fs::symlink(dir.path().join("Demo/02_RUSHES/01_DAY01"), &link).unwrap();
+
+        let root = discover_root(&link.join("01_VIDEO")).unwrap();
+        assert_eq!(root, PathBuf::from("../../../.."));
+        assert!(
+            link.join("01_VIDEO")
+                .join(&root)
+                .join(CONFIG_FILE)
+                .is_file()
+        );
+        assert_eq!(
+            Project::discover(&link).unwrap().config().setup.name,
+            "Demo"
+        );
+    }
+}
@@ -2,26 +2,46 @@ extern crate chrono;
 extern crate fs_extra;
 
 use std::{
+    collections::BTreeMap,
     io,
+    path::Path,
     time::{Instant, SystemTime},
 };
 
-use chrono::{DateTime, offset::Utc};
-use fs_extra::dir::get_dir_content;
+use chrono::{DateTime, NaiveDate, offset::Utc};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     Query,
-    config::{Config, ConfigError, QueryInfo, QuerySettings, QueryType, SortType},
-    num_to_char,
+    config::{
+        Breakdown, Config, ConfigError, FolderPattern, OutputFormat, QueryInfo, QuerySettings,
+        QueryType, SortType,
+    },
+    init::ProjectSetup,
+    iterable, num_to_letters,
+    util::{
+        media::MediaKind,
+        metadata,
+        pattern::FolderMatcher,
+        scan::{self, DirTotals, Extensions, ScanTree},
+        util::{format_runtime, get_version},
+        watch,
+    },
 };
 
+/// Bumped whenever a field is added to, removed from or renamed in
+/// `QueryRecord` or `QueryDocument`, or the CSV columns change order.
+pub const QUERY_SCHEMA_VERSION: u32 = 7;
+
 #[derive(Debug)]
 pub enum QueryError {
     IoError(std::io::Error),
     FsExtraError(fs_extra::error::Error),
     ConfigError(ConfigError),
     InvalidQuery(String),
+    SerializeError(String),
+    WatchError(notify::Error),
 }
 
 impl std::fmt::Display for QueryError {
@@ -31,6 +51,8 @@ impl std::fmt::Display for QueryError {
             QueryError::FsExtraError(e) => write!(f, "Filesystem error: {}", e),
             QueryError::ConfigError(e) => write!(f, "Config error: {}", e),
             QueryError::InvalidQuery(msg) => write!(f, "Invalid query: {}", msg),
+            QueryError::SerializeError(msg) => write!(f, "Serialization error: {}", msg),
+            QueryError::WatchError(e) => write!(f, "Watch error: {}", e),
         }
     }
 }
@@ -43,6 +65,12 @@ impl From<ConfigError> for QueryError {
     }
 }
 
+impl From<notify::Error> for QueryError {
+    fn from(error: notify::Error) -> Self {
+        QueryError::WatchError(error)
+    }
+}
+
 impl From<std::io::Error> for QueryError {
     fn from(error: std::io::Error) -> Self {
         QueryError::IoError(error)
@@ -55,6 +83,7 @@ impl From<fs_extra::error::Error> for QueryError {
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub enum QueryResult {
     GeneralResult(GeneralResult),
@@ -62,11 +91,31 @@ pub enum QueryResult {
     DayResult(DayResult),
     CamResult(CamResult),
     SoundResult(SoundResult),
+    IterableResult(IterableResult),
     FolderResult(FolderResult),
 }
 
+/// The columns every result struct carries, as a `QueryRecord` whose
+/// variant-specific columns are left empty.
+macro_rules! common_record {
+    ($result:expr, $kind:expr, $name:expr) => {
+        QueryRecord {
+            kind: $kind,
+            name: $name,
+            file_count: $result.file_count,
+            total_size: $result.total_size.clone(),
+            total_size_bytes: $result.total_size_u64,
+            runtime_ms: $result.runtime_ms,
+            offline_file_count: $result.offline.as_ref().map(|o| o.file_count),
+            offline_size_bytes: $result.offline.as_ref().map(|o| o.total_size_u64),
+            breakdown: BreakdownRecord::from_entries(&$result.breakdown),
+            ..QueryRecord::default()
+        }
+    };
+}
+
 impl QueryResult {
-    fn get_result_string(self) -> String {
+    pub fn get_result_string(self) -> String {
         match self {
             QueryResult::GeneralResult(r) => {
                 format!(
@@ -98,6 +147,12 @@ impl QueryResult {
                     toml::to_string(&r).expect("Could not serialize sound sources query result!")
                 )
             }
+            QueryResult::IterableResult(r) => {
+                format!(
+                    "[Iterable Query]\n{}",
+                    toml::to_string(&r).expect("Could not serialize iterable query result!")
+                )
+            }
             QueryResult::FolderResult(r) => {
                 format!(
                     "[Folder Query]\n{}",
@@ -107,13 +162,6 @@ impl QueryResult {
         }
     }
 
-    fn get_general_result(&self) -> &GeneralResult {
-        match self {
-            QueryResult::GeneralResult(r) => r,
-            _ => panic!("Not a General Result!"),
-        }
-    }
-
     fn get_size_for_sorting(&self) -> u64 {
         match self {
             QueryResult::GeneralResult(r) => r.total_size_u64,
@@ -121,190 +169,750 @@ impl QueryResult {
             QueryResult::DayResult(r) => r.total_size_u64,
             QueryResult::CamResult(r) => r.total_size_u64,
             QueryResult::SoundResult(r) => r.total_size_u64,
+            QueryResult::IterableResult(r) => r.total_size_u64,
             QueryResult::FolderResult(r) => r.total_size_u64,
         }
     }
+
+    fn breakdown_mut(&mut self) -> &mut Vec<BreakdownEntry> {
+        match self {
+            QueryResult::GeneralResult(r) => &mut r.breakdown,
+            QueryResult::RootResult(r) => &mut r.breakdown,
+            QueryResult::DayResult(r) => &mut r.breakdown,
+            QueryResult::CamResult(r) => &mut r.breakdown,
+            QueryResult::SoundResult(r) => &mut r.breakdown,
+            QueryResult::IterableResult(r) => &mut r.breakdown,
+            QueryResult::FolderResult(r) => &mut r.breakdown,
+        }
+    }
+
+    fn offline_mut(&mut self) -> &mut Option<OfflineSize> {
+        match self {
+            QueryResult::GeneralResult(r) => &mut r.offline,
+            QueryResult::RootResult(r) => &mut r.offline,
+            QueryResult::DayResult(r) => &mut r.offline,
+            QueryResult::CamResult(r) => &mut r.offline,
+            QueryResult::SoundResult(r) => &mut r.offline,
+            QueryResult::IterableResult(r) => &mut r.offline,
+            QueryResult::FolderResult(r) => &mut r.offline,
+        }
+    }
+
+    /// Only day, camera and sound source results carry footage.
+    fn footage_mut(&mut self) -> Option<&mut Option<Footage>> {
+        match self {
+            QueryResult::DayResult(r) => Some(&mut r.footage),
+            QueryResult::CamResult(r) => Some(&mut r.footage),
+            QueryResult::SoundResult(r) => Some(&mut r.footage),
+            _ => None,
+        }
+    }
+
+    pub fn to_record(&self) -> QueryRecord {
+        match self {
+            QueryResult::GeneralResult(r) => QueryRecord {
+                path: Some(r.path.clone()),
+                ..common_record!(r, "general", r.folder_name.clone())
+            },
+            QueryResult::RootResult(r) => QueryRecord {
+                shoot_days: Some(r.shoot_days),
+                camera_count: Some(r.camera_count),
+                sound_source_count: Some(r.sound_source_count),
+                ..common_record!(r, "root", r.project_name.clone())
+            },
+            QueryResult::DayResult(r) => QueryRecord {
+                path: r.path.clone(),
+                date: r.date.map(|date| date.to_string()),
+                ..common_record!(r, "day", r.day.clone())
+            }
+            .with_footage(r.footage.as_ref()),
+            QueryResult::CamResult(r) => QueryRecord {
+                path: r.path.clone(),
+                ..common_record!(r, "camera", r.camera.clone())
+            }
+            .with_footage(r.footage.as_ref()),
+            QueryResult::SoundResult(r) => QueryRecord {
+                path: r.path.clone(),
+                ..common_record!(r, "sound_source", r.sound_source.clone())
+            }
+            .with_footage(r.footage.as_ref()),
+            QueryResult::IterableResult(r) => QueryRecord {
+                path: r.path.clone(),
+                ..common_record!(r, "iterable", r.entry.clone())
+            },
+            QueryResult::FolderResult(r) => QueryRecord {
+                path: Some(r.path.clone()),
+                ..common_record!(
+                    r,
+                    "folder",
+                    r.path.rsplit('/').next().unwrap_or(&r.path).to_string()
+                )
+            },
+        }
+    }
+}
+
+/// One row of machine-readable query output. Every `QueryResult` variant maps
+/// onto this shape so JSON, TOML and CSV consumers see the same columns.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct QueryRecord {
+    pub kind: &'static str,
+    pub name: String,
+    pub path: Option<String>,
+    pub file_count: usize,
+    pub total_size: String,
+    pub total_size_bytes: u64,
+    pub shoot_days: Option<usize>,
+    pub camera_count: Option<usize>,
+    pub sound_source_count: Option<usize>,
+    pub runtime_ms: Option<u64>,
+    pub offline_file_count: Option<usize>,
+    pub offline_size_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub breakdown: Vec<BreakdownRecord>,
+    pub clip_count: Option<usize>,
+    pub video_runtime_ms: Option<u64>,
+    pub audio_runtime_ms: Option<u64>,
+    pub unreadable_clip_count: Option<usize>,
+    /// The calendar date of a day, e.g. "2026-10-17".
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BreakdownRecord {
+    pub class: String,
+    pub file_count: usize,
+    pub total_size: String,
+    pub total_size_bytes: u64,
+}
+
+impl BreakdownRecord {
+    fn from_entries(entries: &[BreakdownEntry]) -> Vec<Self> {
+        entries
+            .iter()
+            .map(|entry| BreakdownRecord {
+                class: entry.class.clone(),
+                file_count: entry.file_count,
+                total_size: entry.total_size.clone(),
+                total_size_bytes: entry.total_size_u64,
+            })
+            .collect()
+    }
+}
+
+/// The column CSV appends to a record to name the breakdown class a line
+/// describes, empty on the record's own line.
+#[derive(Debug, Serialize)]
+pub struct CsvBreakdown<'a> {
+    breakdown: Option<&'a str>,
+}
+
+/// A CSV line: a `QueryRecord` without its breakdown entries, or one of those
+/// entries as a record repeating the kind, name and path.
+pub type CsvRow<'a> = (QueryRecord, CsvBreakdown<'a>);
+
+impl QueryRecord {
+    pub const CSV_HEADERS: [&'static str; 18] = [
+        "kind",
+        "name",
+        "path",
+        "file_count",
+        "total_size",
+        "total_size_bytes",
+        "shoot_days",
+        "camera_count",
+        "sound_source_count",
+        "runtime_ms",
+        "offline_file_count",
+        "offline_size_bytes",
+        "clip_count",
+        "video_runtime_ms",
+        "audio_runtime_ms",
+        "unreadable_clip_count",
+        "date",
+        "breakdown",
+    ];
+
+    fn with_footage(self, footage: Option<&Footage>) -> Self {
+        QueryRecord {
+            clip_count: footage.map(|f| f.clip_count),
+            video_runtime_ms: footage.map(|f| f.video_runtime_ms),
+            audio_runtime_ms: footage.map(|f| f.audio_runtime_ms),
+            unreadable_clip_count: footage.map(|f| f.unreadable.len()),
+            ..self
+        }
+    }
+
+    pub fn csv_rows(&self) -> Vec<CsvRow<'_>> {
+        let record = QueryRecord {
+            breakdown: Vec::new(),
+            ..self.clone()
+        };
+        let entries = self.breakdown.iter().map(|entry| {
+            let line = QueryRecord {
+                kind: self.kind,
+                name: self.name.clone(),
+                path: self.path.clone(),
+                file_count: entry.file_count,
+                total_size: entry.total_size.clone(),
+                total_size_bytes: entry.total_size_bytes,
+                ..QueryRecord::default()
+            };
+            (
+                line,
+                CsvBreakdown {
+                    breakdown: Some(&entry.class),
+                },
+            )
+        });
+        std::iter::once((record, CsvBreakdown { breakdown: None }))
+            .chain(entries)
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryDocument {
+    schema_version: u32,
+    nanopm_version: String,
+    query: &'static str,
+    unique_entries: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generated_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_runtime_ms: Option<u64>,
+    results: Vec<QueryRecord>,
+}
+
+impl QueryDocument {
+    fn new(
+        query_results: &[QueryResult],
+        settings: &QuerySettings,
+        query_type: &Query,
+        start_time: Instant,
+    ) -> Self {
+        QueryDocument {
+            schema_version: QUERY_SCHEMA_VERSION,
+            nanopm_version: get_version(),
+            query: match query_type {
+                Query::General(_) => "general",
+                Query::Partial(_, _) => "partial",
+                Query::Folder(_, _) => "folder",
+                Query::None => "none",
+            },
+            unique_entries: settings.unique_entries,
+            generated_at: if settings.record_timestamp {
+                let datetime: DateTime<Utc> = SystemTime::now().into();
+                Some(datetime.to_rfc3339())
+            } else {
+                None
+            },
+            total_runtime_ms: if settings.include_runtime {
+                Some(start_time.elapsed().as_millis() as u64)
+            } else {
+                None
+            },
+            results: query_results.iter().map(QueryResult::to_record).collect(),
+        }
+    }
+
+    fn render(&self, format: OutputFormat) -> Result<String, QueryError> {
+        match format {
+            OutputFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| QueryError::SerializeError(e.to_string())),
+            OutputFormat::Toml => {
+                toml::to_string(self).map_err(|e| QueryError::SerializeError(e.to_string()))
+            }
+            OutputFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                writer
+                    .write_record(QueryRecord::CSV_HEADERS)
+                    .map_err(|e| QueryError::SerializeError(e.to_string()))?;
+                for row in self.results.iter().flat_map(QueryRecord::csv_rows) {
+                    writer
+                        .serialize(row)
+                        .map_err(|e| QueryError::SerializeError(e.to_string()))?;
+                }
+                let bytes = writer
+                    .into_inner()
+                    .map_err(|e| QueryError::SerializeError(e.to_string()))?;
+                String::from_utf8(bytes).map_err(|e| QueryError::SerializeError(e.to_string()))
+            }
+            OutputFormat::Text => Err(QueryError::SerializeError(
+                "Text output is not a structured document".to_string(),
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Eq, Ord, PartialEq, PartialOrd, Clone)]
 pub struct GeneralResult {
-    path: String,
-    folder_name: String,
-    file_count: usize,
-    total_size: String,
+    pub path: String,
+    pub folder_name: String,
+    pub file_count: usize,
+    pub total_size: String,
     #[serde(skip_serializing)]
-    total_size_u64: u64,
+    pub total_size_u64: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
-    runtime_ms: Option<u64>,
+    pub runtime_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline: Option<OfflineSize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakdown: Vec<BreakdownEntry>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct RootResult {
-    project_name: String,
-    file_count: usize,
-    total_size: String,
-    shoot_days: usize,
-    camera_count: usize,
-    sound_source_count: usize,
+    pub project_name: String,
+    pub file_count: usize,
+    pub total_size: String,
+    #[serde(skip_serializing)]
+    pub total_size_u64: u64,
+    pub shoot_days: usize,
+    pub camera_count: usize,
+    pub sound_source_count: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
-    runtime_ms: Option<u64>,
+    pub runtime_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline: Option<OfflineSize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakdown: Vec<BreakdownEntry>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DayResult {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
-    path: Option<String>,
-    day: String,
-    file_count: usize,
-    total_size: String,
+    pub path: Option<String>,
+    pub day: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub file_count: usize,
+    pub total_size: String,
     #[serde(skip_serializing)]
-    total_size_u64: u64,
+    pub total_size_u64: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
-    runtime_ms: Option<u64>,
+    pub runtime_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline: Option<OfflineSize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakdown: Vec<BreakdownEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footage: Option<Footage>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CamResult {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
-    path: Option<String>,
-    camera: String,
-    file_count: usize,
-    total_size: String,
+    pub path: Option<String>,
+    pub camera: String,
+    pub file_count: usize,
+    pub total_size: String,
     #[serde(skip_serializing)]
-    total_size_u64: u64,
+    pub total_size_u64: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
-    runtime_ms: Option<u64>,
+    pub runtime_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline: Option<OfflineSize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakdown: Vec<BreakdownEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footage: Option<Footage>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SoundResult {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
-    path: Option<String>,
-    sound_source: String,
-    file_count: usize,
-    total_size: String,
+    pub path: Option<String>,
+    pub sound_source: String,
+    pub file_count: usize,
+    pub total_size: String,
     #[serde(skip_serializing)]
-    total_size_u64: u64,
+    pub total_size_u64: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
-    runtime_ms: Option<u64>,
+    pub runtime_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline: Option<OfflineSize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakdown: Vec<BreakdownEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footage: Option<Footage>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct IterableResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub path: Option<String>,
+    pub entry: String,
+    pub file_count: usize,
+    pub total_size: String,
+    #[serde(skip_serializing)]
+    pub total_size_u64: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline: Option<OfflineSize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakdown: Vec<BreakdownEntry>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct FolderResult {
-    path: String,
-    file_count: usize,
-    total_size: String,
+    pub path: String,
+    pub file_count: usize,
+    pub total_size: String,
     #[serde(skip_serializing)]
-    total_size_u64: u64,
+    pub total_size_u64: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
-    runtime_ms: Option<u64>,
+    pub runtime_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline: Option<OfflineSize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakdown: Vec<BreakdownEntry>,
 }
 
-pub fn query(query_info: QueryInfo) -> Result<(), QueryError> {
-    let start_time = Instant::now();
+/// Files of one extension or media kind in a queried folder, see
+/// `QuerySettings::breakdown`.
+#[derive(Debug, Deserialize, Serialize, Eq, Ord, PartialEq, PartialOrd, Clone)]
+pub struct BreakdownEntry {
+    pub class: String,
+    pub file_count: usize,
+    pub total_size: String,
+    #[serde(skip_serializing)]
+    pub total_size_u64: u64,
+}
 
-    match query_info.query {
-        Query::General(sort_type) => {
-            query_general(
-                sort_type,
-                query_info.config,
-                query_info.settings,
-                start_time,
-            )?;
+impl BreakdownEntry {
+    fn new(class: String, totals: DirTotals) -> Self {
+        BreakdownEntry {
+            class,
+            file_count: totals.file_count,
+            total_size: to_shorthand(totals.size),
+            total_size_u64: totals.size,
         }
+    }
+
+    /// The files in `folders` (as listed by the scan) and below, split the
+    /// way `settings.breakdown` asks. Extensions come biggest first, kinds in
+    /// the order of `MediaKind`.
+    fn of_folders<'a>(
+        tree: &ScanTree,
+        folders: impl IntoIterator<Item = &'a str>,
+        config: &Config,
+        settings: &QuerySettings,
+    ) -> Vec<Self> {
+        let Some(breakdown) = settings.breakdown else {
+            return Vec::new();
+        };
+        let mut extensions = Extensions::new();
+        for folder in folders {
+            for (extension, totals) in tree.extensions(folder).into_iter().flatten() {
+                extensions
+                    .entry(extension.clone())
+                    .or_default()
+                    .add(*totals);
+            }
+        }
+
+        match breakdown {
+            Breakdown::Extension => {
+                let mut entries: Vec<Self> = extensions
+                    .into_iter()
+                    .map(|(extension, totals)| {
+                        let class = if extension.is_empty() {
+                            "(none)".to_string()
+                        } else {
+                            format!(".{}", extension)
+                        };
+                        Self::new(class, totals)
+                    })
+                    .collect();
+                entries.sort_by_key(|entry| std::cmp::Reverse(entry.total_size_u64));
+                entries
+            }
+            Breakdown::Kind => {
+                let mut kinds: BTreeMap<MediaKind, DirTotals> = BTreeMap::new();
+                for (extension, totals) in extensions {
+                    kinds
+                        .entry(config.media.kind(&extension))
+                        .or_default()
+                        .add(totals);
+                }
+                kinds
+                    .into_iter()
+                    .map(|(kind, totals)| Self::new(kind.to_string(), totals))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Files offloaded from a queried folder (see `nanopm offload`). They still
+/// count as part of the project, just not on this volume.
+#[derive(Debug, Deserialize, Serialize, Eq, Ord, PartialEq, PartialOrd, Clone)]
+pub struct OfflineSize {
+    pub file_count: usize,
+    pub total_size: String,
+    #[serde(skip_serializing)]
+    pub total_size_u64: u64,
+}
+
+impl OfflineSize {
+    fn from_totals((file_count, size): (usize, u64)) -> Option<Self> {
+        (file_count > 0 || size > 0).then(|| OfflineSize {
+            file_count,
+            total_size: to_shorthand(size),
+            total_size_u64: size,
+        })
+    }
+
+    /// What has been offloaded from `folder`, a directory under the project
+    /// folder at `root_path` as listed by the scan.
+    fn of_folder(config: &Config, root_path: &str, folder: &str) -> Option<Self> {
+        let folder = folder.replace("\\", "/");
+        let relative = folder
+            .strip_prefix(root_path)
+            .map_or(folder.as_str(), |relative| relative.trim_start_matches('/'));
+        Self::from_totals(config.tiering.offline_within(relative))
+    }
+}
+
+/// Runtime of the clips in a queried folder, read from their headers (see
+/// `QuerySettings::footage`). Video and audio are kept apart, as sound
+/// recorded alongside the picture would otherwise count twice.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Footage {
+    pub clip_count: usize,
+    pub video_runtime: String,
+    #[serde(skip_serializing)]
+    pub video_runtime_ms: u64,
+    pub audio_runtime: String,
+    #[serde(skip_serializing)]
+    pub audio_runtime_ms: u64,
+    /// Clips per format, e.g. "3840x2160 25fps 48000Hz 2ch".
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub formats: BTreeMap<String, usize>,
+    /// Video and audio files in containers nanopm can't read yet (BRAW, R3D,
+    /// MXF...). They count towards no runtime.
+    #[serde(default)]
+    pub unsupported_count: usize,
+    /// Clips that could not be read, with the reason.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unreadable: Vec<String>,
+}
+
+impl Footage {
+    /// Reads every video and audio file (by the `[media]` table) the scan
+    /// found in `folders` and below. `None` unless `settings.footage` is set.
+    fn of_folders<'a>(
+        tree: &ScanTree,
+        folders: impl IntoIterator<Item = &'a str>,
+        config: &Config,
+        settings: &QuerySettings,
+    ) -> Option<Self> {
+        if !settings.footage {
+            return None;
+        }
+        let clips: Vec<_> = folders
+            .into_iter()
+            .flat_map(|folder| tree.files(folder).unwrap_or_default())
+            .filter_map(|file| match config.media.kind_of(&file) {
+                kind @ (MediaKind::Video | MediaKind::Audio) => Some((file, kind)),
+                _ => None,
+            })
+            .collect();
+
+        let read: Vec<_> = clips
+            .par_iter()
+            .map(|(clip, kind)| (clip, kind, metadata::read(clip)))
+            .collect();
+        let mut footage = Footage {
+            clip_count: 0,
+            video_runtime: String::new(),
+            video_runtime_ms: 0,
+            audio_runtime: String::new(),
+            audio_runtime_ms: 0,
+            formats: BTreeMap::new(),
+            unsupported_count: 0,
+            unreadable: Vec::new(),
+        };
+        for (clip, kind, info) in read {
+            match info {
+                Ok(Some(info)) => {
+                    footage.clip_count += 1;
+                    let runtime_ms = info.duration.unwrap_or_default().as_millis() as u64;
+                    match kind {
+                        MediaKind::Video => footage.video_runtime_ms += runtime_ms,
+                        _ => footage.audio_runtime_ms += runtime_ms,
+                    }
+                    if let Some(format) = info.format() {
+                        *footage.formats.entry(format).or_default() += 1;
+                    }
+                }
+                Ok(None) => footage.unsupported_count += 1,
+                Err(e) => footage.unreadable.push(format!(
+                    "{}: {}",
+                    clip.to_string_lossy().replace("\\", "/"),
+                    e
+                )),
+            }
+        }
+        footage.video_runtime = format_runtime(footage.video_runtime_ms);
+        footage.audio_runtime = format_runtime(footage.audio_runtime_ms);
+        Some(footage)
+    }
+}
+
+pub fn query(mut query_info: QueryInfo) -> Result<(), QueryError> {
+    if let Some(options) = query_info.watch.take() {
+        return watch::watch(query_info, &options);
+    }
+    let start_time = Instant::now();
+    let query_results = run_query(
+        &query_info.root,
+        &query_info.query,
+        &query_info.config,
+        &query_info.settings,
+        start_time,
+    )?;
+    write_query_results(
+        query_results,
+        query_info.settings,
+        query_info.query,
+        start_time,
+    )
+}
+
+/// Runs `query` against the project under `root` and returns its results
+/// without printing or writing them. Missing folders are still reported on
+/// stdout unless `settings.quiet` is set.
+pub fn run_query(
+    root: &Path,
+    query: &Query,
+    config: &Config,
+    settings: &QuerySettings,
+    start_time: Instant,
+) -> Result<Vec<QueryResult>, QueryError> {
+    if *query == Query::None {
+        return Err(QueryError::InvalidQuery(
+            "No query type specified".to_string(),
+        ));
+    }
+    // Every query type reads from this one walk of the project.
+    let path = project_path(root, config);
+    let tree = if settings.no_cache {
+        ScanTree::scan(path)?
+    } else {
+        ScanTree::scan_cached(path, root.join(scan::CACHE_FILE))?
+    };
+
+    match query {
+        Query::General(sort_type) => query_general(&tree, sort_type, config, settings, start_time),
         Query::Partial(types, sort_type) => {
-            query_partial(
-                types,
-                sort_type,
-                query_info.config,
-                query_info.settings,
-                start_time,
-            )?;
+            query_partial(&tree, types, sort_type, config, settings, start_time)
         }
         Query::Folder(folders, sort_type) => {
-            query_folders(
-                folders,
-                sort_type,
-                query_info.config,
-                query_info.settings,
-                start_time,
-            )?;
-        }
-        Query::None => {
-            return Err(QueryError::InvalidQuery(
-                "No query type specified".to_string(),
-            ));
+            query_folders(&tree, folders, sort_type, config, settings, start_time)
         }
+        Query::None => unreachable!(),
     }
-    Ok(())
+}
+
+fn project_path(root: &Path, config: &Config) -> String {
+    root.join(&config.setup.name)
+        .to_string_lossy()
+        .replace("\\", "/")
+}
+
+fn runtime_ms(settings: &QuerySettings, start_time: Instant) -> Option<u64> {
+    if settings.include_runtime {
+        Some(start_time.elapsed().as_millis() as u64)
+    } else {
+        None
+    }
+}
+
+/// Directories in `tree` picked by `matcher`, in scan order.
+fn matching_directories<'a>(
+    tree: &'a ScanTree,
+    matcher: &'a FolderMatcher,
+    config: &'a Config,
+) -> impl Iterator<Item = &'a str> {
+    let (root_path, _) = tree.root();
+    tree.directories().filter(move |directory| {
+        matcher.matches(&project_relative_path(root_path, directory, config))
+    })
+}
+
+/// `directory`, listed by a scan of the project folder at `root_path`, as a
+/// path starting with the project folder (`Demo/02_RUSHES`).
+fn project_relative_path(root_path: &str, directory: &str, config: &Config) -> String {
+    let directory = directory.replace("\\", "/");
+    let below = directory.strip_prefix(root_path).unwrap_or(&directory);
+    format!("{}{}", config.setup.name, below)
 }
 
 pub fn query_partial(
-    types_to_query: Vec<QueryType>,
-    sort_type: SortType,
-    config: Config,
-    settings: QuerySettings,
+    tree: &ScanTree,
+    types_to_query: &[QueryType],
+    sort_type: &SortType,
+    config: &Config,
+    settings: &QuerySettings,
     start_time: Instant,
-) -> Result<(), QueryError> {
+) -> Result<Vec<QueryResult>, QueryError> {
     let mut query_results: Vec<QueryResult> = Vec::new();
 
     for query_type in types_to_query {
         let mut new_query_results: Vec<QueryResult> = match query_type {
-            QueryType::Root => vec![query_root(&config, &settings)?],
-            QueryType::Days | QueryType::Cams | QueryType::Sound => {
-                query_iterable(&config, &settings, query_type)?
+            QueryType::Root => vec![query_root(tree, config, settings, start_time)],
+            QueryType::Days | QueryType::Cams | QueryType::Sound | QueryType::Iterable(_) => {
+                query_iterable(tree, config, settings, query_type.clone(), start_time)?
             }
         };
         query_results.append(&mut new_query_results);
     }
 
-    apply_sorting(&mut query_results, &sort_type);
-
-    write_query_results(
-        query_results,
-        settings,
-        Query::Partial(Vec::new(), SortType::None),
-        start_time,
-    )?;
-    Ok(())
+    apply_sorting(&mut query_results, sort_type);
+    Ok(query_results)
 }
 
 pub fn query_general(
-    sort_type: SortType,
-    config: Config,
-    settings: QuerySettings,
+    tree: &ScanTree,
+    sort_type: &SortType,
+    config: &Config,
+    settings: &QuerySettings,
     start_time: Instant,
-) -> Result<(), QueryError> {
+) -> Result<Vec<QueryResult>, QueryError> {
     let folders: &Vec<String> = &config.general_query_params;
-    let root_path = format!("./{}", config.setup.name);
-
-    let all_files = get_dir_content(&root_path)?;
+    let (root_path, _) = tree.root();
 
     let mut query_results = Vec::new();
 
     for folder in folders {
+        let matcher = FolderMatcher::new(&FolderPattern::parse(folder), config)?;
         let mut found_file = false;
-        for file in &all_files.directories {
-            let file_name_with_separator = format!("{}%", file);
-            if file_name_with_separator.contains(&format!("{}%", folder)) {
-                let file_data = get_dir_content(file)?;
-                found_file = true;
-                query_results.push(QueryResult::GeneralResult(GeneralResult {
-                    path: file.replace("\\", "/"),
-                    folder_name: folder.clone(),
-                    file_count: file_data.files.len(),
-                    total_size: to_shorthand(file_data.dir_size),
-                    total_size_u64: file_data.dir_size,
-                    runtime_ms: if settings.include_runtime {
-                        Some(start_time.elapsed().as_millis() as u64)
-                    } else {
-                        None
-                    },
-                }));
-            }
+        for file in matching_directories(tree, &matcher, config) {
+            let totals = tree.totals(file).unwrap_or_default();
+            found_file = true;
+            query_results.push(QueryResult::GeneralResult(GeneralResult {
+                path: file.replace("\\", "/"),
+                folder_name: folder.clone(),
+                file_count: totals.file_count,
+                total_size: to_shorthand(totals.size),
+                total_size_u64: totals.size,
+                runtime_ms: runtime_ms(settings, start_time),
+                offline: OfflineSize::of_folder(config, root_path, file),
+                breakdown: BreakdownEntry::of_folders(tree, [file], config, settings),
+            }));
         }
         if !found_file && !settings.quiet {
             println!(
@@ -314,66 +922,68 @@ pub fn query_general(
         }
     }
 
-    apply_sorting(&mut query_results, &sort_type);
-
-    query_results.insert(0, query_root(&config, &settings)?);
+    apply_sorting(&mut query_results, sort_type);
 
-    write_query_results(
-        query_results,
-        settings,
-        Query::General(sort_type),
-        start_time,
-    )?;
-    Ok(())
+    query_results.insert(0, query_root(tree, config, settings, start_time));
+    Ok(query_results)
 }
 
 pub fn query_iterable(
+    tree: &ScanTree,
     config: &Config,
     settings: &QuerySettings,
     query_type: QueryType,
+    start_time: Instant,
 ) -> Result<Vec<QueryResult>, QueryError> {
-    let root_path = format!("./{}", config.setup.name);
-    let all_files = get_dir_content(&root_path)?;
+    let (root_path, _) = tree.root();
 
     let mut query_results = Vec::new();
 
-    let (count, pattern) = match query_type {
-        QueryType::Days => (config.setup.days, String::from("[Iter]_DAY[Iter]")),
-        QueryType::Cams => (config.setup.cameras, String::from("[Iter]_[Char]_CAM")),
-        QueryType::Sound => (
-            config.setup.sound_sources,
-            String::from("[Iter]_[Char]_REC"),
-        ),
+    let setup = &config.setup;
+    let id = match &query_type {
+        QueryType::Days => iterable::DAYS,
+        QueryType::Cams => iterable::CAMS,
+        QueryType::Sound => iterable::SOUND_SOURCES,
+        QueryType::Iterable(id) => id.as_str(),
         QueryType::Root => {
             return Err(QueryError::InvalidQuery(
                 "Root query should not be handled here".to_string(),
             ));
         }
     };
+    // Outside of a day, cameras and sound sources cover the highest count of
+    // any day, so day overrides don't report phantom missing folders.
+    let iterable = iterable::resolve(id, config, None).ok_or_else(|| {
+        QueryError::InvalidQuery(format!("\"{}\" is not a declared iterable", id))
+    })?;
+    let count = iterable.count;
 
     if settings.unique_entries {
         for i in 1..=count {
-            let file_to_query = pattern
-                .replace("[Iter]", &format!("{:02}", i))
-                .replace("[Char]", &num_to_char(i).to_string());
+            let file_to_query = iterable.folder_name(i);
+            let matcher = FolderMatcher::name(&file_to_query);
 
             let mut found_file = false;
-            for file in &all_files.directories {
-                let file_name_with_separator = format!("{}%", file);
-                if file_name_with_separator.contains(&format!("{}%", file_to_query)) {
-                    let file_data = get_dir_content(file)?;
-                    found_file = true;
-
-                    let result = create_query_result(
-                        query_type.clone(),
-                        i,
-                        Some(file.replace("\\", "/")),
-                        file_data.files.len(),
-                        file_data.dir_size,
-                        settings,
-                    );
-                    query_results.push(result);
+            for file in matching_directories(tree, &matcher, config) {
+                let totals = tree.totals(file).unwrap_or_default();
+                found_file = true;
+
+                let mut result = create_query_result(
+                    setup,
+                    query_type.clone(),
+                    i,
+                    Some(file.replace("\\", "/")),
+                    totals.file_count,
+                    totals.size,
+                    runtime_ms(settings, start_time),
+                );
+                *result.offline_mut() = OfflineSize::of_folder(config, root_path, file);
+                *result.breakdown_mut() =
+                    BreakdownEntry::of_folders(tree, [file], config, settings);
+                if let Some(footage) = result.footage_mut() {
+                    *footage = Footage::of_folders(tree, [file], config, settings);
                 }
+                query_results.push(result);
             }
             if !found_file && !settings.quiet {
                 println!(
@@ -384,33 +994,42 @@ pub fn query_iterable(
         }
     } else {
         for i in 1..=count {
-            let file_to_query = pattern
-                .replace("[Iter]", &format!("{:02}", i))
-                .replace("[Char]", &num_to_char(i).to_string());
+            let file_to_query = iterable.folder_name(i);
+            let matcher = FolderMatcher::name(&file_to_query);
 
-            let mut found_file = false;
+            let mut found_files = Vec::new();
             let mut file_count: usize = 0;
             let mut total_size: u64 = 0;
-
-            for file in &all_files.directories {
-                let file_name_with_separator = format!("{}%", file);
-                if file_name_with_separator.contains(&format!("{}%", file_to_query)) {
-                    found_file = true;
-                    let file_data = get_dir_content(file)?;
-                    file_count += file_data.files.len();
-                    total_size += file_data.dir_size;
+            let mut offline = (0, 0);
+
+            for file in matching_directories(tree, &matcher, config) {
+                let totals = tree.totals(file).unwrap_or_default();
+                found_files.push(file);
+                file_count += totals.file_count;
+                total_size += totals.size;
+                if let Some(size) = OfflineSize::of_folder(config, root_path, file) {
+                    offline.0 += size.file_count;
+                    offline.1 += size.total_size_u64;
                 }
             }
 
-            if found_file {
-                let result = create_query_result(
+            if !found_files.is_empty() {
+                let mut result = create_query_result(
+                    setup,
                     query_type.clone(),
                     i,
                     None,
                     file_count,
                     total_size,
-                    settings,
+                    runtime_ms(settings, start_time),
                 );
+                *result.offline_mut() = OfflineSize::from_totals(offline);
+                if let Some(footage) = result.footage_mut() {
+                    *footage =
+                        Footage::of_folders(tree, found_files.iter().copied(), config, settings);
+                }
+                *result.breakdown_mut() =
+                    BreakdownEntry::of_folders(tree, found_files, config, settings);
                 query_results.push(result);
             } else if !settings.quiet {
                 println!(
@@ -424,105 +1043,126 @@ pub fn query_iterable(
     Ok(query_results)
 }
 
+/// "A Cam (1)" for unnamed units, "A_FX6 (1)" once the unit has a name.
+fn unit_display_name(names: &[String], index: usize, kind: &str) -> String {
+    match names.get(index.wrapping_sub(1)) {
+        Some(name) => format!("{} ({})", name, index),
+        None => format!("{} {} ({})", num_to_letters(index), kind, index),
+    }
+}
+
 fn create_query_result(
+    setup: &ProjectSetup,
     query_type: QueryType,
     index: usize,
     path: Option<String>,
     file_count: usize,
     total_size: u64,
-    settings: &QuerySettings,
+    runtime_ms: Option<u64>,
 ) -> QueryResult {
     match query_type {
-        QueryType::Days => QueryResult::DayResult(DayResult {
+        QueryType::Days => {
+            let shoot_day = setup.shoot_day(index);
+            QueryResult::DayResult(DayResult {
+                path,
+                day: format!("Day {}", index),
+                date: shoot_day.and_then(|d| d.date),
+                location: shoot_day.and_then(|d| d.location.clone()),
+                label: shoot_day.and_then(|d| d.label.clone()),
+                file_count,
+                total_size: to_shorthand(total_size),
+                total_size_u64: total_size,
+                runtime_ms,
+                offline: None,
+                breakdown: Vec::new(),
+                footage: None,
+            })
+        }
+        QueryType::Cams => QueryResult::CamResult(CamResult {
             path,
-            day: format!("Day {}", index),
+            camera: unit_display_name(&setup.camera_names, index, "Cam"),
             file_count,
             total_size: to_shorthand(total_size),
             total_size_u64: total_size,
-            runtime_ms: if settings.include_runtime {
-                Some(0)
-            } else {
-                None
-            },
+            runtime_ms,
+            offline: None,
+            breakdown: Vec::new(),
+            footage: None,
         }),
-        QueryType::Cams => QueryResult::CamResult(CamResult {
+        QueryType::Sound => QueryResult::SoundResult(SoundResult {
             path,
-            camera: format!("{} Cam ({})", num_to_char(index), index),
+            sound_source: unit_display_name(&setup.sound_source_names, index, "Rec"),
             file_count,
             total_size: to_shorthand(total_size),
             total_size_u64: total_size,
-            runtime_ms: if settings.include_runtime {
-                Some(0)
-            } else {
-                None
-            },
+            runtime_ms,
+            offline: None,
+            breakdown: Vec::new(),
+            footage: None,
         }),
-        QueryType::Sound => QueryResult::SoundResult(SoundResult {
+        QueryType::Iterable(id) => QueryResult::IterableResult(IterableResult {
             path,
-            sound_source: format!("{} Rec ({})", num_to_char(index), index),
+            entry: format!("{} {}", id, index),
             file_count,
             total_size: to_shorthand(total_size),
             total_size_u64: total_size,
-            runtime_ms: if settings.include_runtime {
-                Some(0)
-            } else {
-                None
-            },
+            runtime_ms,
+            offline: None,
+            breakdown: Vec::new(),
         }),
         QueryType::Root => panic!("Root should not be handled here"),
     }
 }
 
-pub fn query_root(config: &Config, settings: &QuerySettings) -> Result<QueryResult, QueryError> {
-    let root_path = format!("./{}", config.setup.name);
-    let all_files = get_dir_content(&root_path)?;
+pub fn query_root(
+    tree: &ScanTree,
+    config: &Config,
+    settings: &QuerySettings,
+    start_time: Instant,
+) -> QueryResult {
+    let (root_path, totals) = tree.root();
 
-    Ok(QueryResult::RootResult(RootResult {
+    QueryResult::RootResult(RootResult {
         project_name: config.setup.name.clone(),
-        file_count: all_files.files.len(),
-        total_size: to_shorthand(all_files.dir_size),
+        file_count: totals.file_count,
+        total_size: to_shorthand(totals.size),
+        total_size_u64: totals.size,
         shoot_days: config.setup.days,
-        camera_count: config.setup.cameras,
-        sound_source_count: config.setup.sound_sources,
-        runtime_ms: if settings.include_runtime {
-            Some(0)
-        } else {
-            None
-        },
-    }))
+        camera_count: config.setup.max_cameras(),
+        sound_source_count: config.setup.max_sound_sources(),
+        runtime_ms: runtime_ms(settings, start_time),
+        offline: OfflineSize::from_totals(config.tiering.offline_within("")),
+        breakdown: BreakdownEntry::of_folders(tree, [root_path], config, settings),
+    })
 }
 
 pub fn query_folders(
-    folders: Vec<String>,
-    sort_type: SortType,
-    config: Config,
-    settings: QuerySettings,
+    tree: &ScanTree,
+    folders: &[FolderPattern],
+    sort_type: &SortType,
+    config: &Config,
+    settings: &QuerySettings,
     start_time: Instant,
-) -> Result<(), QueryError> {
-    let root_path = format!("./{}", config.setup.name);
-    let all_files = get_dir_content(&root_path)?;
+) -> Result<Vec<QueryResult>, QueryError> {
+    let (root_path, _) = tree.root();
 
     let mut query_results = Vec::new();
 
     for folder in folders {
+        let matcher = FolderMatcher::new(folder, config)?;
         let mut found_file = false;
-        for file in &all_files.directories {
-            let file_name_with_separator = format!("{}%", file);
-            if file_name_with_separator.contains(&format!("{}%", folder)) {
-                let file_data = get_dir_content(file)?;
-                found_file = true;
-                query_results.push(QueryResult::FolderResult(FolderResult {
-                    path: file.replace("\\", "/"),
-                    file_count: file_data.files.len(),
-                    total_size: to_shorthand(file_data.dir_size),
-                    total_size_u64: file_data.dir_size,
-                    runtime_ms: if settings.include_runtime {
-                        Some(start_time.elapsed().as_millis() as u64)
-                    } else {
-                        None
-                    },
-                }));
-            }
+        for file in matching_directories(tree, &matcher, config) {
+            let totals = tree.totals(file).unwrap_or_default();
+            found_file = true;
+            query_results.push(QueryResult::FolderResult(FolderResult {
+                path: file.replace("\\", "/"),
+                file_count: totals.file_count,
+                total_size: to_shorthand(totals.size),
+                total_size_u64: totals.size,
+                runtime_ms: runtime_ms(settings, start_time),
+                offline: OfflineSize::of_folder(config, root_path, file),
+                breakdown: BreakdownEntry::of_folders(tree, [file], config, settings),
+            }));
         }
         if !found_file && !settings.quiet {
             println!(
@@ -532,21 +1172,14 @@ pub fn query_folders(
         }
     }
 
-    apply_sorting(&mut query_results, &sort_type);
-
-    write_query_results(
-        query_results,
-        settings,
-        Query::Folder(Vec::new(), SortType::None),
-        start_time,
-    )?;
-    Ok(())
+    apply_sorting(&mut query_results, sort_type);
+    Ok(query_results)
 }
 
-fn apply_sorting(query_results: &mut Vec<QueryResult>, sort_type: &SortType) {
+fn apply_sorting(query_results: &mut [QueryResult], sort_type: &SortType) {
     match sort_type {
         SortType::BySize => {
-            query_results.sort_by(|a, b| b.get_size_for_sorting().cmp(&a.get_size_for_sorting()));
+            query_results.sort_by_key(|r| std::cmp::Reverse(r.get_size_for_sorting()));
         }
         SortType::ByDefaultOrder => {}
         SortType::None => {}
@@ -559,40 +1192,61 @@ pub fn write_query_results(
     query_type: Query,
     start_time: Instant,
 ) -> Result<(), QueryError> {
-    let mut full_text = String::new();
-    for query_result in query_results {
-        let text = query_result.get_result_string();
-        full_text.push_str(&format!("{}\n", text));
-    }
-
-    let explanation_string = get_explanation_string(&query_type, &settings);
-    let timestamp_string = get_timestamp_string(&settings);
-    let runtime_string = if settings.include_runtime {
-        format!(
-            "Total Query Runtime: {}ms\n\n",
-            start_time.elapsed().as_millis()
-        )
-    } else {
-        String::new()
-    };
-
     let export_path = get_export_path(&settings);
-
-    println!("\n{}{}{}", explanation_string, runtime_string, full_text);
+    let (display, content) =
+        render_query_results(query_results, &settings, &query_type, start_time)?;
+    println!("{}", display);
 
     if settings.write {
-        write_to_file(
-            &export_path,
-            &timestamp_string,
-            &explanation_string,
-            &runtime_string,
-            &full_text,
-        )?;
+        write_to_file(&export_path, &content)?;
     }
 
     Ok(())
 }
 
+/// Renders results in `settings.format`, returning what to print and what to
+/// write to the export file (which also carries the timestamp in text mode).
+pub fn render_query_results(
+    query_results: Vec<QueryResult>,
+    settings: &QuerySettings,
+    query_type: &Query,
+    start_time: Instant,
+) -> Result<(String, String), QueryError> {
+    Ok(match settings.format {
+        OutputFormat::Text => {
+            let mut full_text = String::new();
+            for query_result in query_results {
+                let text = query_result.get_result_string();
+                full_text.push_str(&format!("{}\n", text));
+            }
+
+            let explanation_string = get_explanation_string(query_type, settings);
+            let timestamp_string = get_timestamp_string(settings);
+            let runtime_string = if settings.include_runtime {
+                format!(
+                    "Total Query Runtime: {}ms\n\n",
+                    start_time.elapsed().as_millis()
+                )
+            } else {
+                String::new()
+            };
+
+            (
+                format!("\n{}{}{}", explanation_string, runtime_string, full_text),
+                format!(
+                    "{}{}{}{}",
+                    timestamp_string, explanation_string, runtime_string, full_text
+                ),
+            )
+        }
+        format => {
+            let document = QueryDocument::new(&query_results, settings, query_type, start_time);
+            let text = document.render(format)?;
+            (text.trim_end().to_string(), text)
+        }
+    })
+}
+
 fn get_explanation_string(query_type: &Query, settings: &QuerySettings) -> String {
     match query_type {
         Query::Partial(_, _) => {
@@ -628,27 +1282,25 @@ fn get_timestamp_string(settings: &QuerySettings) -> String {
     }
 }
 
-fn get_export_path(settings: &QuerySettings) -> String {
+pub fn get_export_path(settings: &QuerySettings) -> String {
+    let extension = settings.format.extension();
     match &settings.output_name {
-        Some(path) => format!("{}.txt", path),
+        Some(path) => format!("{}.{}", path, extension),
         None => {
             let system_time = SystemTime::now();
             let datetime: DateTime<Utc> = system_time.into();
             format!(
-                "Query_{}.txt",
-                datetime.format("%d.%m.%Y_%T").to_string().replace(":", ".")
+                "Query_{}.{}",
+                datetime.format("%d.%m.%Y_%T").to_string().replace(":", "."),
+                extension
             )
         }
     }
 }
 
-fn write_to_file(
-    export_path: &str,
-    timestamp_string: &str,
-    explanation_string: &str,
-    runtime_string: &str,
-    full_text: &str,
-) -> Result<(), QueryError> {
+/// Writes `content` to `export_path`, asking before overwriting. Returns
+/// whether the file was written.
+pub fn write_to_file(export_path: &str, content: &str) -> Result<bool, QueryError> {
     if std::fs::exists(export_path)? {
         println!(
             "A file with the name {} already exists! Overwrite? (Y/N)",
@@ -658,17 +1310,13 @@ fn write_to_file(
         io::stdin().read_line(&mut response)?;
         if response.trim().to_lowercase() != "y" && response.trim().to_lowercase() != "yes" {
             println!("Did not overwrite existing file.");
-            return Ok(());
+            return Ok(false);
         }
     }
 
-    let content = format!(
-        "{}{}{}{}",
-        timestamp_string, explanation_string, runtime_string, full_text
-    );
     std::fs::write(export_path, content)?;
     println!("Query result written to: {}", export_path);
-    Ok(())
+    Ok(true)
 }
 
 pub fn to_shorthand(bytes: u64) -> String {
@@ -703,8 +1351,7 @@ pub fn to_shorthand(bytes: u64) -> String {
         decimal_points
     };
 
-    let ib = (current_number * 10f64.powi(decimal_points as i32)).round()
-        / 10f64.powi(decimal_points as i32);
+    let ib = (current_number * 10f64.powi(decimal_points)).round() / 10f64.powi(decimal_points);
 
     let b = bytes as f64 / 1000_f64.powi((expo_10 / 3) as i32);
 
@@ -725,6 +1372,8 @@ pub fn to_shorthand(bytes: u64) -> String {
 
 #[cfg(test)]
 mod tests {
+    use std::fs;
+
     use super::*;
 
     #[test]
@@ -746,20 +1395,177 @@ mod tests {
 
     #[test]
     fn test_get_export_path() {
-        let mut settings = QuerySettings::default();
-        settings.output_name = Some("test_output".to_string());
+        let mut settings = QuerySettings {
+            output_name: Some("test_output".to_string()),
+            ..Default::default()
+        };
         assert_eq!(get_export_path(&settings), "test_output.txt");
 
         settings.output_name = None;
         let path = get_export_path(&settings);
         assert!(path.starts_with("Query_"));
         assert!(path.ends_with(".txt"));
+
+        settings.format = OutputFormat::Json;
+        settings.output_name = Some("test_output".to_string());
+        assert_eq!(get_export_path(&settings), "test_output.json");
     }
 
     #[test]
-    fn test_create_query_result() {
+    fn test_query_document_formats() {
         let settings = QuerySettings::default();
-        let result = create_query_result(QueryType::Days, 1, None, 10, 1024, &settings);
+        let results = vec![create_query_result(
+            &ProjectSetup::new(),
+            QueryType::Cams,
+            2,
+            None,
+            3,
+            2048,
+            None,
+        )];
+        let document = QueryDocument::new(
+            &results,
+            &settings,
+            &Query::Partial(Vec::new(), SortType::None),
+            Instant::now(),
+        );
+
+        let json = document.render(OutputFormat::Json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["schema_version"], QUERY_SCHEMA_VERSION);
+        assert_eq!(parsed["results"][0]["kind"], "camera");
+        assert_eq!(parsed["results"][0]["total_size_bytes"], 2048);
+
+        let toml_text = document.render(OutputFormat::Toml).unwrap();
+        assert!(toml_text.contains("[[results]]"));
+        assert!(toml_text.contains("total_size_bytes = 2048"));
+
+        let csv_text = document.render(OutputFormat::Csv).unwrap();
+        let mut lines = csv_text.lines();
+        assert_eq!(lines.next().unwrap(), QueryRecord::CSV_HEADERS.join(","));
+        let mut headed = csv::Writer::from_writer(Vec::new());
+        headed
+            .serialize(&document.results[0].csv_rows()[0])
+            .unwrap();
+        let headed = String::from_utf8(headed.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            headed.lines().next().unwrap(),
+            QueryRecord::CSV_HEADERS.join(",")
+        );
+        assert!(lines.next().unwrap().starts_with("camera,B Cam (2),,3,"));
+
+        assert!(document.render(OutputFormat::Text).is_err());
+    }
+
+    #[test]
+    fn test_breakdown_by_extension_and_kind() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let clips = dir.path().join("Demo/01_VIDEO/01_A_CAM");
+        fs::create_dir_all(&clips).unwrap();
+        fs::write(clips.join("A001.MOV"), vec![0u8; 300]).unwrap();
+        fs::write(clips.join("A002.mov"), vec![0u8; 200]).unwrap();
+        fs::write(clips.join("A001.XML"), vec![0u8; 10]).unwrap();
+        fs::write(clips.join("README"), vec![0u8; 5]).unwrap();
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        let tree = ScanTree::scan(dir.path().join("Demo")).unwrap();
+        let folder = tree.directories().last().unwrap();
+
+        let mut settings = QuerySettings {
+            breakdown: Some(Breakdown::Extension),
+            ..Default::default()
+        };
+        let extensions = BreakdownEntry::of_folders(&tree, [folder], &config, &settings);
+        let classes: Vec<_> = extensions.iter().map(|e| e.class.as_str()).collect();
+        assert_eq!(classes, vec![".mov", ".xml", "(none)"]);
+        assert_eq!(
+            (extensions[0].file_count, extensions[0].total_size_u64),
+            (2, 500)
+        );
+
+        settings.breakdown = Some(Breakdown::Kind);
+        let kinds = BreakdownEntry::of_folders(&tree, [folder], &config, &settings);
+        let classes: Vec<_> = kinds.iter().map(|e| e.class.as_str()).collect();
+        assert_eq!(classes, vec!["video", "sidecar", "other"]);
+
+        let mut result = query_root(&tree, &config, &settings, Instant::now());
+        assert_eq!(result.breakdown_mut().len(), 3);
+        assert!(result.clone().get_result_string().contains("[[breakdown]]"));
+        let document = QueryDocument::new(
+            &[result],
+            &settings,
+            &Query::Partial(vec![QueryType::Root], SortType::None),
+            Instant::now(),
+        );
+        let json = document.render(OutputFormat::Json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["results"][0]["breakdown"][0]["class"], "video");
+        assert_eq!(
+            parsed["results"][0]["breakdown"][0]["total_size_bytes"],
+            500
+        );
+        let csv_text = document.render(OutputFormat::Csv).unwrap();
+        assert_eq!(csv_text.lines().count(), 5);
+        assert!(
+            csv_text
+                .lines()
+                .nth(2)
+                .unwrap()
+                .starts_with("root,Demo,,2,")
+        );
+        assert!(csv_text.lines().nth(2).unwrap().ends_with(",video"));
+    }
+
+    #[test]
+    fn test_footage_of_folders() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let sound = dir.path().join("02_AUDIO/01_A_REC");
+        fs::create_dir_all(&sound).unwrap();
+        // Two seconds of 48kHz 16 bit mono.
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0".to_vec();
+        wav.extend_from_slice(&48000u32.to_le_bytes());
+        wav.extend_from_slice(&96000u32.to_le_bytes());
+        wav.extend_from_slice(b"\x02\0\x10\0data");
+        wav.extend_from_slice(&192000u32.to_le_bytes());
+        wav.resize(wav.len() + 192000, 0);
+        fs::write(sound.join("ZOOM0001.WAV"), &wav).unwrap();
+        fs::write(sound.join("ZOOM0002.WAV"), &wav).unwrap();
+        fs::write(sound.join("ZOOM0003.WAV"), &wav[..40]).unwrap();
+        fs::write(sound.join("A001.BRAW"), vec![0u8; 64]).unwrap();
+        fs::write(sound.join("ZOOM0001.XML"), b"<xml/>").unwrap();
+        let folder = sound.to_string_lossy();
+
+        let config = Config::new_config();
+        let tree = ScanTree::scan(dir.path()).unwrap();
+        let mut settings = QuerySettings::default();
+        assert!(Footage::of_folders(&tree, [folder.as_ref()], &config, &settings).is_none());
+
+        settings.footage = true;
+        let footage = Footage::of_folders(&tree, [folder.as_ref()], &config, &settings).unwrap();
+        assert_eq!(footage.clip_count, 2);
+        assert_eq!(footage.audio_runtime_ms, 4000);
+        assert_eq!(footage.audio_runtime, "0:00:04");
+        assert_eq!(footage.video_runtime_ms, 0);
+        assert_eq!(footage.formats.get("48000Hz 1ch"), Some(&2));
+        assert_eq!(footage.unsupported_count, 1);
+        assert_eq!(footage.unreadable.len(), 1);
+        assert!(footage.unreadable[0].contains("ZOOM0003.WAV"));
+
+        let mut result =
+            create_query_result(&ProjectSetup::new(), QueryType::Sound, 1, None, 5, 0, None);
+        *result.footage_mut().unwrap() = Some(footage);
+        let record = result.to_record();
+        assert_eq!(record.clip_count, Some(2));
+        assert_eq!(record.audio_runtime_ms, Some(4000));
+        assert_eq!(record.video_runtime_ms, Some(0));
+        assert_eq!(record.unreadable_clip_count, Some(1));
+        assert!(result.get_result_string().contains("[footage]"));
+    }
+
+    #[test]
+    fn test_create_query_result() {
+        let setup = ProjectSetup::new();
+        let result = create_query_result(&setup, QueryType::Days, 1, None, 10, 1024, None);
 
         match result {
             QueryResult::DayResult(day_result) => {
@@ -771,6 +1577,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_create_query_result_dates_days() {
+        let mut setup = ProjectSetup::new();
+        setup.set_shoot_day(2, |entry| {
+            entry.date = NaiveDate::from_ymd_opt(2026, 10, 17);
+            entry.location = Some("Pier 9".to_string());
+        });
+        let result = create_query_result(&setup, QueryType::Days, 2, None, 0, 0, None);
+        assert_eq!(result.to_record().date.as_deref(), Some("2026-10-17"));
+        let text = result.get_result_string();
+        assert!(text.contains("date = \"2026-10-17\""));
+        assert!(text.contains("location = \"Pier 9\""));
+    }
+
+    #[test]
+    fn test_create_query_result_names_units() {
+        let mut setup = ProjectSetup::new();
+        setup.sound_source_names = vec!["BOOM".to_string()];
+        match create_query_result(&setup, QueryType::Sound, 1, None, 0, 0, None) {
+            QueryResult::SoundResult(sound_result) => {
+                assert_eq!(sound_result.sound_source, "BOOM (1)")
+            }
+            _ => panic!("Expected SoundResult"),
+        }
+        match create_query_result(&setup, QueryType::Cams, 27, None, 0, 0, None) {
+            QueryResult::CamResult(cam_result) => assert_eq!(cam_result.camera, "AA Cam (27)"),
+            _ => panic!("Expected CamResult"),
+        }
+    }
+
+    #[test]
+    fn test_offline_size_of_folder() {
+        let mut config = Config::new_config();
+        config
+            .tiering
+            .offline
+            .push(crate::util::tier::OfflineFolder {
+                path: "02_RUSHES/01_DAY01".to_string(),
+                location: "/cold/Demo/02_RUSHES/01_DAY01".to_string(),
+                file_count: 4,
+                size: 2048,
+                offloaded: String::new(),
+            });
+
+        let rushes = OfflineSize::of_folder(&config, "./Demo", "./Demo/02_RUSHES").unwrap();
+        assert_eq!(rushes.file_count, 4);
+        assert_eq!(rushes.total_size_u64, 2048);
+        assert!(OfflineSize::of_folder(&config, "./Demo", "./Demo/02_RUSHES/01_DAY01").is_some());
+        assert!(OfflineSize::of_folder(&config, "./Demo", "./Demo/02_RUSHES/02_DAY02").is_none());
+        assert!(OfflineSize::of_folder(&config, "./Demo", "./Demo/04_PRE-RENDERS").is_none());
+
+        let mut result = create_query_result(&config.setup, QueryType::Days, 1, None, 1, 100, None);
+        *result.offline_mut() = Some(rushes);
+        let record = result.to_record();
+        assert_eq!(record.offline_file_count, Some(4));
+        assert_eq!(record.offline_size_bytes, Some(2048));
+    }
+
     #[test]
     fn test_query_error_display() {
         let query_error = QueryError::InvalidQuery("Bad query".to_string());
@@ -792,6 +1656,8 @@ mod tests {
                 total_size: "1MB".to_string(),
                 total_size_u64: 1048576,
                 runtime_ms: None,
+                offline: None,
+                breakdown: Vec::new(),
             }),
             QueryResult::FolderResult(FolderResult {
                 path: "test2".to_string(),
@@ -799,6 +1665,8 @@ mod tests {
                 total_size: "2MB".to_string(),
                 total_size_u64: 2097152,
                 runtime_ms: None,
+                offline: None,
+                breakdown: Vec::new(),
             }),
         ];
 
@@ -0,0 +1,548 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::media;
+
+/// Where queries keep the scan of the project folder between runs, relative
+/// to the directory holding the config.
+pub const CACHE_FILE: &str = ".nanopm/cache";
+
+/// Bumped whenever `CachedDir` changes shape. Caches of another version are
+/// thrown away and rebuilt.
+const CACHE_VERSION: u32 = 4;
+
+/// Directories (and their files) modified this close to a scan may still be
+/// being written to, e.g. by a card copy, so their cached totals are never
+/// trusted. Also covers coarse mtimes such as FAT's two seconds.
+const SETTLE_TIME: Duration = Duration::from_secs(10);
+
+/// File count and size of a directory, everything below it included.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DirTotals {
+    pub file_count: usize,
+    /// Like `fs_extra::dir::get_dir_content`, this counts the directories'
+    /// own entries as well as the files, so query sizes stay comparable with
+    /// older reports.
+    pub size: u64,
+}
+
+/// Files by their lowercase extension ("" for none). Only the files' sizes
+/// count here, not the directories'.
+pub type Extensions = BTreeMap<String, DirTotals>;
+
+impl DirTotals {
+    pub fn add(&mut self, other: DirTotals) {
+        self.file_count += other.file_count;
+        self.size += other.size;
+    }
+}
+
+/// Every directory under a path, walked once with subtrees scanned in
+/// parallel, and the totals of each. Queries look folders up in here instead
+/// of walking the disk again for every match.
+#[derive(Debug, Clone)]
+pub struct ScanTree {
+    /// In the same depth-first order `get_dir_content` lists them, starting
+    /// with the scanned path itself.
+    directories: Vec<(String, DirTotals)>,
+    /// Lines up with `directories`.
+    extensions: Vec<Extensions>,
+    /// Names of the files directly inside each directory, lining up with
+    /// `directories`.
+    files: Vec<Vec<String>>,
+    index: HashMap<String, usize>,
+}
+
+/// What the cache remembers of one directory, keyed by its path relative to
+/// the scanned one. Only the directory's own entries are stored, so a change
+/// deep down never leaves a stale total further up.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct CachedDir {
+    stamp: Stamp,
+    /// The directory's own size and the files directly inside it.
+    own: DirTotals,
+    own_extensions: Extensions,
+    /// The files directly inside it.
+    files: Vec<CachedFile>,
+    /// Names of the directories directly inside it, in `read_dir` order.
+    subdirectories: Vec<String>,
+}
+
+/// A file in a cached directory. Rewriting a file in place (a render
+/// exported again under the same name) leaves its directory's mtime alone,
+/// so the files of a cached directory are looked at again on every scan.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+struct CachedFile {
+    name: String,
+    size: u64,
+    mtime: (u64, u32),
+}
+
+/// When a directory was last modified and which inode it is. Adding, removing
+/// or renaming an entry changes the mtime; replacing the directory changes
+/// the inode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+struct Stamp {
+    mtime: (u64, u32),
+    inode: u64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ScanCache {
+    version: u32,
+    directories: HashMap<String, CachedDir>,
+}
+
+struct Node {
+    path: String,
+    key: String,
+    totals: DirTotals,
+    extensions: Extensions,
+    files: Vec<String>,
+    /// Set when nothing in the directory was modified within `SETTLE_TIME`
+    /// of the scan, so it may be cached.
+    cached: Option<CachedDir>,
+    children: Vec<Node>,
+}
+
+/// One directory's own entries, read from disk or rebuilt from the cache.
+struct Listing {
+    own: DirTotals,
+    own_extensions: Extensions,
+    files: Vec<CachedFile>,
+    settled: bool,
+    subdirectories: Vec<(String, PathBuf, fs::Metadata)>,
+}
+
+impl ScanTree {
+    /// Walks the whole of `path`.
+    pub fn scan(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::scan_with(path.as_ref(), &HashMap::new())?.0)
+    }
+
+    /// Like [`ScanTree::scan`], but only reads the directories that changed
+    /// since the scan saved in `cache_file`, then saves this one there. A
+    /// missing, unreadable or outdated cache just means a full scan, and
+    /// failing to save it never fails the scan.
+    pub fn scan_cached(path: impl AsRef<Path>, cache_file: impl AsRef<Path>) -> io::Result<Self> {
+        let cache_file = cache_file.as_ref();
+        let previous = load_cache(cache_file);
+        let (tree, directories) = Self::scan_with(path.as_ref(), &previous)?;
+        // A read-only share still gets its query, just without the speedup.
+        let _ = save_cache(
+            cache_file,
+            &ScanCache {
+                version: CACHE_VERSION,
+                directories,
+            },
+        );
+        Ok(tree)
+    }
+
+    fn scan_with(
+        path: &Path,
+        cache: &HashMap<String, CachedDir>,
+    ) -> io::Result<(Self, HashMap<String, CachedDir>)> {
+        let settled_before = SystemTime::now() - SETTLE_TIME;
+        let root = scan_dir(
+            path.to_path_buf(),
+            String::new(),
+            &fs::metadata(path)?,
+            cache,
+            settled_before,
+        )?;
+
+        let mut tree = ScanTree {
+            directories: Vec::new(),
+            extensions: Vec::new(),
+            files: Vec::new(),
+            index: HashMap::new(),
+        };
+        let mut cached = HashMap::new();
+        flatten(root, &mut tree, &mut cached);
+        tree.index = tree
+            .directories
+            .iter()
+            .enumerate()
+            .map(|(i, (path, _))| (path.clone(), i))
+            .collect();
+
+        Ok((tree, cached))
+    }
+
+    /// The scanned path and its totals.
+    pub fn root(&self) -> (&str, DirTotals) {
+        let (path, totals) = &self.directories[0];
+        (path, *totals)
+    }
+
+    /// Every directory path, the scanned one first, joined the way
+    /// `Path::join` does on this platform.
+    pub fn directories(&self) -> impl Iterator<Item = &str> {
+        self.directories.iter().map(|(path, _)| path.as_str())
+    }
+
+    /// Totals of a directory listed by [`ScanTree::directories`].
+    pub fn totals(&self, path: &str) -> Option<DirTotals> {
+        self.index.get(path).map(|&i| self.directories[i].1)
+    }
+
+    /// The files in a directory listed by [`ScanTree::directories`], and
+    /// everything below it, by extension.
+    pub fn extensions(&self, path: &str) -> Option<&Extensions> {
+        self.index.get(path).map(|&i| &self.extensions[i])
+    }
+
+    /// Paths of the files in a directory listed by [`ScanTree::directories`],
+    /// and everything below it.
+    pub fn files(&self, path: &str) -> Option<Vec<PathBuf>> {
+        let start = *self.index.get(path)?;
+        // Depth-first, so a directory's subtree directly follows it.
+        let files = self.directories[start..]
+            .iter()
+            .zip(&self.files[start..])
+            .take_while(|((directory, _), _)| Path::new(directory).starts_with(path))
+            .flat_map(|((directory, _), names)| {
+                names
+                    .iter()
+                    .map(move |name| Path::new(directory).join(name))
+            })
+            .collect();
+        Some(files)
+    }
+}
+
+fn scan_dir(
+    path: PathBuf,
+    key: String,
+    metadata: &fs::Metadata,
+    cache: &HashMap<String, CachedDir>,
+    settled_before: SystemTime,
+) -> io::Result<Node> {
+    let stamp = Stamp::of(metadata);
+    let listing = match cache
+        .get(&key)
+        .filter(|cached| cached.stamp == stamp)
+        .and_then(|cached| cached_listing(&path, cached))
+    {
+        Some(listing) => listing,
+        None => list_dir(&path, metadata, settled_before)?,
+    };
+
+    let cached = listing.settled.then(|| CachedDir {
+        stamp,
+        own: listing.own,
+        own_extensions: listing.own_extensions.clone(),
+        files: listing.files.clone(),
+        subdirectories: listing
+            .subdirectories
+            .iter()
+            .map(|(name, _, _)| name.clone())
+            .collect(),
+    });
+
+    // Collecting keeps read_dir's order however the work was split.
+    let children = listing
+        .subdirectories
+        .into_par_iter()
+        .map(|(name, path, metadata)| {
+            let key = if key.is_empty() {
+                name
+            } else {
+                format!("{}/{}", key, name)
+            };
+            scan_dir(path, key, &metadata, cache, settled_before)
+        })
+        .collect::<io::Result<Vec<Node>>>()?;
+    let mut totals = listing.own;
+    let mut extensions = listing.own_extensions;
+    for child in &children {
+        totals.add(child.totals);
+        for (extension, child_totals) in &child.extensions {
+            extensions
+                .entry(extension.clone())
+                .or_default()
+                .add(*child_totals);
+        }
+    }
+
+    Ok(Node {
+        path: path.to_string_lossy().into_owned(),
+        key,
+        totals,
+        extensions,
+        files: listing.files.into_iter().map(|file| file.name).collect(),
+        cached,
+        children,
+    })
+}
+
+fn list_dir(
+    path: &Path,
+    metadata: &fs::Metadata,
+    settled_before: SystemTime,
+) -> io::Result<Listing> {
+    let mut listing = Listing {
+        own: DirTotals {
+            file_count: 0,
+            size: metadata.len(),
+        },
+        own_extensions: Extensions::new(),
+        files: Vec::new(),
+        settled: is_settled(metadata, settled_before),
+        subdirectories: Vec::new(),
+    };
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let mut entry_metadata = entry.metadata()?;
+        // Follow links like `get_dir_content` does.
+        if entry_metadata.file_type().is_symlink() {
+            entry_metadata = fs::metadata(entry.path())?;
+        }
+        if entry_metadata.is_dir() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            listing
+                .subdirectories
+                .push((name, entry.path(), entry_metadata));
+        } else {
+            // Appending to a file doesn't touch its directory's mtime, so a
+            // directory with a file still being written is never cached.
+            listing.settled &= is_settled(&entry_metadata, settled_before);
+            let file = DirTotals {
+                file_count: 1,
+                size: entry_metadata.len(),
+            };
+            listing.own.add(file);
+            listing
+                .own_extensions
+                .entry(media::extension(Path::new(&entry.file_name())))
+                .or_default()
+                .add(file);
+            listing.files.push(CachedFile {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                size: entry_metadata.len(),
+                mtime: mtime(&entry_metadata),
+            });
+        }
+    }
+
+    Ok(listing)
+}
+
+/// Rebuilds a listing from an unchanged directory's cache entry. Only its
+/// files and subdirectories are looked at, and if a file changed or a
+/// subdirectory can't be looked at (it was swapped for a file or isn't valid
+/// UTF-8), the directory is read after all.
+fn cached_listing(path: &Path, cached: &CachedDir) -> Option<Listing> {
+    let unchanged = cached.files.iter().all(|file| {
+        fs::metadata(path.join(&file.name))
+            .is_ok_and(|metadata| metadata.len() == file.size && mtime(&metadata) == file.mtime)
+    });
+    if !unchanged {
+        return None;
+    }
+    let subdirectories = cached
+        .subdirectories
+        .iter()
+        .map(|name| {
+            let path = path.join(name);
+            let metadata = fs::metadata(&path).ok().filter(fs::Metadata::is_dir)?;
+            Some((name.clone(), path, metadata))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Listing {
+        own: cached.own,
+        own_extensions: cached.own_extensions.clone(),
+        files: cached.files.clone(),
+        settled: true,
+        subdirectories,
+    })
+}
+
+fn is_settled(metadata: &fs::Metadata, settled_before: SystemTime) -> bool {
+    metadata
+        .modified()
+        .is_ok_and(|modified| modified < settled_before)
+}
+
+impl Stamp {
+    fn of(metadata: &fs::Metadata) -> Self {
+        Stamp {
+            mtime: mtime(metadata),
+            inode: inode(metadata),
+        }
+    }
+}
+
+fn mtime(metadata: &fs::Metadata) -> (u64, u32) {
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    (mtime.as_secs(), mtime.subsec_nanos())
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+/// Windows only exposes file IDs on nightly, so the mtime has to do there.
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+fn flatten(node: Node, tree: &mut ScanTree, cached: &mut HashMap<String, CachedDir>) {
+    tree.directories.push((node.path, node.totals));
+    tree.extensions.push(node.extensions);
+    tree.files.push(node.files);
+    if let Some(entry) = node.cached {
+        cached.insert(node.key, entry);
+    }
+    for child in node.children {
+        flatten(child, tree, cached);
+    }
+}
+
+fn load_cache(cache_file: &Path) -> HashMap<String, CachedDir> {
+    fs::read(cache_file)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<ScanCache>(&bytes).ok())
+        .filter(|cache| cache.version == CACHE_VERSION)
+        .map(|cache| cache.directories)
+        .unwrap_or_default()
+}
+
+/// Written next to the cache and renamed over it, so a query running at the
+/// same time (e.g. under `--watch`) never reads half a cache.
+fn save_cache(cache_file: &Path, cache: &ScanCache) -> io::Result<()> {
+    if let Some(parent) = cache_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let bytes = serde_json::to_vec(cache).map_err(io::Error::other)?;
+    let temporary = cache_file.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&temporary, bytes)?;
+    fs::rename(&temporary, cache_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use fs_extra::dir::get_dir_content;
+
+    use super::*;
+
+    #[test]
+    fn test_scan_matches_get_dir_content() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let dir = dir.path();
+        for day in ["01_DAY01", "02_DAY02"] {
+            for cam in ["01_A_CAM", "02_B_CAM"] {
+                let folder = dir.join(day).join("01_VIDEO").join(cam);
+                fs::create_dir_all(&folder).unwrap();
+                fs::write(folder.join("clip.mov"), vec![0u8; 1000]).unwrap();
+            }
+        }
+        fs::create_dir_all(dir.join("03_EMPTY")).unwrap();
+
+        let root = dir.to_string_lossy().into_owned();
+        let tree = ScanTree::scan(&root).unwrap();
+        let expected = get_dir_content(&root).unwrap();
+
+        assert_eq!(
+            tree.directories().collect::<Vec<_>>(),
+            expected
+                .directories
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+        );
+        for directory in &expected.directories {
+            let content = get_dir_content(directory).unwrap();
+            let totals = tree.totals(directory).unwrap();
+            assert_eq!(totals.file_count, content.files.len());
+            assert_eq!(totals.size, content.dir_size);
+            let mut files = tree.files(directory).unwrap();
+            files.sort();
+            let mut expected_files: Vec<PathBuf> =
+                content.files.iter().map(PathBuf::from).collect();
+            expected_files.sort();
+            assert_eq!(files, expected_files);
+        }
+        assert_eq!(tree.root().1.file_count, 4);
+        assert_eq!(
+            tree.extensions(&root).unwrap().get("mov"),
+            Some(&DirTotals {
+                file_count: 4,
+                size: 4000
+            })
+        );
+        assert!(tree.totals("not scanned").is_none());
+        assert!(tree.files("not scanned").is_none());
+        assert!(ScanTree::scan(dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_scan_cached_refreshes_changed_directories() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let dir = dir.path();
+        let project = dir.join("Demo");
+        let cache_file = dir.join(CACHE_FILE);
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        for day in ["01_DAY01", "02_DAY02"] {
+            let folder = project.join(day).join("01_VIDEO").join("01_A_CAM");
+            fs::create_dir_all(&folder).unwrap();
+            fs::write(folder.join("clip.mov"), vec![0u8; 1000]).unwrap();
+        }
+        // Backdated so nothing looks like it is still being written to.
+        for entry in walkdir::WalkDir::new(&project) {
+            fs::File::open(entry.unwrap().path())
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+        }
+
+        let tree = ScanTree::scan_cached(&project, &cache_file).unwrap();
+        assert_eq!(tree.root().1, ScanTree::scan(&project).unwrap().root().1);
+        assert!(load_cache(&cache_file).contains_key("01_DAY01/01_VIDEO/01_A_CAM"));
+
+        let changed = project.join("02_DAY02/01_VIDEO/01_A_CAM");
+        fs::write(changed.join("clip_2.mov"), vec![0u8; 500]).unwrap();
+        let tree = ScanTree::scan_cached(&project, &cache_file).unwrap();
+        let fresh = ScanTree::scan(&project).unwrap();
+        assert_eq!(tree.root().1.file_count, 3);
+        for directory in fresh.directories() {
+            assert_eq!(tree.totals(directory), fresh.totals(directory));
+            assert_eq!(tree.extensions(directory), fresh.extensions(directory));
+            assert_eq!(tree.files(directory), fresh.files(directory));
+        }
+        // Just written to, so it is read again next time too.
+        let cache = load_cache(&cache_file);
+        assert!(cache.contains_key("01_DAY01/01_VIDEO/01_A_CAM"));
+        assert!(!cache.contains_key("02_DAY02/01_VIDEO/01_A_CAM"));
+
+        // Rewritten in place, which leaves the directory's mtime alone.
+        let rewritten = project.join("01_DAY01/01_VIDEO/01_A_CAM/clip.mov");
+        fs::write(&rewritten, vec![0u8; 100_000]).unwrap();
+        let tree = ScanTree::scan_cached(&project, &cache_file).unwrap();
+        let fresh = ScanTree::scan(&project).unwrap();
+        assert_eq!(tree.root().1, fresh.root().1);
+
+        fs::remove_dir_all(project.join("01_DAY01")).unwrap();
+        fs::write(&cache_file, "not a cache").unwrap();
+        let tree = ScanTree::scan_cached(&project, &cache_file).unwrap();
+        assert_eq!(tree.root().1.file_count, 2);
+        assert!(!load_cache(&cache_file).is_empty());
+    }
+}
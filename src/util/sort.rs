@@ -0,0 +1,737 @@
+use std::{
+    collections::{BTreeMap, btree_map},
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, offset::Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use super::{
+    config::{Config, ConfigError, SortAction, SortInfo},
+    iterable,
+    manifest::{self, HashAlgorithm, MANIFEST_FILE_NAME, Manifest, ManifestEntry, VerifyError},
+    media::{MediaKind, extension},
+    metadata,
+};
+use crate::folder_entries;
+
+/// Every sort run, so `nanopm sort --undo` can put the files back. Lives next
+/// to the scan cache.
+pub const JOURNAL_FILE: &str = ".nanopm/sort_journal.toml";
+
+#[derive(Debug)]
+pub enum SortError {
+    IoError(std::io::Error),
+    WalkDirError(walkdir::Error),
+    ConfigError(ConfigError),
+    VerifyError(VerifyError),
+    InvalidRule(String),
+    InvalidSort(String),
+    WouldOverwrite(Vec<String>),
+    NothingToUndo,
+}
+
+impl std::fmt::Display for SortError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortError::IoError(e) => write!(f, "IO error: {}", e),
+            SortError::WalkDirError(e) => write!(f, "Filesystem error: {}", e),
+            SortError::ConfigError(e) => write!(f, "Config error: {}", e),
+            SortError::VerifyError(e) => write!(f, "Verification error: {}", e),
+            SortError::InvalidRule(msg) => write!(f, "Invalid sort rule: {}", msg),
+            SortError::InvalidSort(msg) => write!(f, "Invalid sort: {}", msg),
+            SortError::WouldOverwrite(paths) => write!(
+                f,
+                "Refusing to overwrite {} existing file(s):\n    {}",
+                paths.len(),
+                paths.join("\n    ")
+            ),
+            SortError::NothingToUndo => write!(f, "There is no sort to undo"),
+        }
+    }
+}
+
+impl std::error::Error for SortError {}
+
+impl From<std::io::Error> for SortError {
+    fn from(error: std::io::Error) -> Self {
+        SortError::IoError(error)
+    }
+}
+
+impl From<walkdir::Error> for SortError {
+    fn from(error: walkdir::Error) -> Self {
+        SortError::WalkDirError(error)
+    }
+}
+
+impl From<ConfigError> for SortError {
+    fn from(error: ConfigError) -> Self {
+        SortError::ConfigError(error)
+    }
+}
+
+impl From<VerifyError> for SortError {
+    fn from(error: VerifyError) -> Self {
+        SortError::VerifyError(error)
+    }
+}
+
+/// The `[sort]` table of the config: where loose media gets dropped and the
+/// rules that file it away.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct SortTable {
+    /// The drop folder, relative to the project folder.
+    pub inbox: String,
+    /// Tried in order, the first match wins.
+    pub rules: Vec<SortRule>,
+}
+
+impl Default for SortTable {
+    fn default() -> Self {
+        SortTable {
+            inbox: "00_INBOX".to_string(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl SortTable {
+    pub fn is_default(&self) -> bool {
+        *self == SortTable::default()
+    }
+}
+
+/// Matches files by every condition it sets and files them into a folder
+/// from the file structure.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct SortRule {
+    /// Extensions without the dot, any case.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    /// The kind of file by the `[media]` table.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<MediaKind>,
+    /// A regex on the file name, e.g. "^A\\d{3}C\\d{3}" for A cam clips.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// The ID of the folder to file into, e.g. "cams".
+    pub folder: String,
+    /// Which of the folder's iterable entries, for folders named after one
+    /// (2 is B_CAM for "cams").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// The shoot day, for folders inside %days. Without one, the day comes
+    /// from the file's recording date and the calendar in `[setup]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<usize>,
+}
+
+/// A rule with its regex compiled.
+struct Matcher<'a> {
+    rule: &'a SortRule,
+    pattern: Option<Regex>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(rule: &'a SortRule) -> Result<Self, SortError> {
+        let pattern = match &rule.pattern {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| {
+                SortError::InvalidRule(format!("\"{}\" is not a valid regex: {}", pattern, e))
+            })?),
+            None => None,
+        };
+        Ok(Matcher { rule, pattern })
+    }
+
+    fn matches(&self, file: &Path, config: &Config) -> bool {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let extension = extension(file);
+        (self.rule.extensions.is_empty()
+            || self
+                .rule
+                .extensions
+                .iter()
+                .any(|listed| listed.eq_ignore_ascii_case(&extension)))
+            && self
+                .rule
+                .kind
+                .is_none_or(|kind| config.media.kind(&extension) == kind)
+            && self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(&name))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct SortMove {
+    /// Both relative to the directory holding the config, always with `/`.
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct SortRun {
+    pub sorted: String,
+    pub moves: Vec<SortMove>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SortJournal {
+    #[serde(default)]
+    pub runs: Vec<SortRun>,
+}
+
+impl SortJournal {
+    pub fn load(root: &Path) -> Result<Self, SortError> {
+        let path = root.join(JOURNAL_FILE);
+        if !path.exists() {
+            return Ok(SortJournal::default());
+        }
+        toml::from_str(&fs::read_to_string(&path)?).map_err(|e| {
+            SortError::InvalidSort(format!("Could not read {}: {}", path.display(), e))
+        })
+    }
+
+    pub fn save(&self, root: &Path) -> Result<(), SortError> {
+        let path = root.join(JOURNAL_FILE);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string(self)
+            .map_err(|e| ConfigError::ParseError(format!("Failed to serialize journal: {}", e)))?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SortReport {
+    pub moves: Vec<SortMove>,
+    /// Files no rule matched, or whose day could not be worked out.
+    pub unmatched: Vec<String>,
+}
+
+/// Works out where every file in `inbox` goes, without moving anything.
+pub fn plan(root: &Path, config: &Config, inbox: &Path) -> Result<SortReport, SortError> {
+    if !inbox.is_dir() {
+        return Err(SortError::InvalidSort(format!(
+            "The inbox \"{}\" is not a directory",
+            inbox.display()
+        )));
+    }
+    let matchers = config
+        .sort
+        .rules
+        .iter()
+        .map(Matcher::new)
+        .collect::<Result<Vec<_>, _>>()?;
+    let relative = |path: &Path| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace("\\", "/")
+    };
+
+    let mut report = SortReport::default();
+    for entry in WalkDir::new(inbox).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let file = entry.path();
+        let Some(matcher) = matchers.iter().find(|m| m.matches(file, config)) else {
+            report
+                .unmatched
+                .push(format!("{} (no rule matches)", relative(file)));
+            continue;
+        };
+        let day = match matcher.rule.day {
+            Some(day) => Some(day),
+            None => metadata::recorded_date(file).and_then(|date| config.setup.day_on(date)),
+        };
+        match destination(config, matcher.rule, day) {
+            Ok(folder) => report.moves.push(SortMove {
+                from: relative(file),
+                to: format!("{}/{}", folder, entry.file_name().to_string_lossy()),
+            }),
+            Err(reason) => report
+                .unmatched
+                .push(format!("{} ({})", relative(file), reason)),
+        }
+    }
+    Ok(report)
+}
+
+/// The folder `rule` files into on `day`, relative to the directory holding
+/// the config.
+fn destination(config: &Config, rule: &SortRule, day: Option<usize>) -> Result<String, String> {
+    let folder = config
+        .file_structure
+        .folders_list
+        .iter()
+        .find(|folder| folder.id == rule.folder)
+        .ok_or_else(|| format!("unknown folder ID \"{}\"", rule.folder))?;
+    let entries = folder_entries(config, &rule.folder).map_err(|e| e.to_string())?;
+    let in_days = entries.iter().any(|(_, entry_day)| entry_day.is_some());
+    if in_days && day.is_none() {
+        return Err(
+            if config
+                .setup
+                .calendar
+                .iter()
+                .any(|entry| entry.date.is_some())
+            {
+                "recorded on a day not in the calendar".to_string()
+            } else {
+                "no day, set one on the rule or date the days with update --date".to_string()
+            },
+        );
+    }
+    if let Some(day) = day.filter(|_| in_days)
+        && day > config.setup.days
+    {
+        return Err(format!(
+            "day {} is outside of the project's {} day(s)",
+            day, config.setup.days
+        ));
+    }
+    let name = match folder.name.strip_prefix('%') {
+        Some(id) => {
+            let index = rule
+                .index
+                .ok_or_else(|| format!("\"{}\" needs an index", rule.folder))?;
+            let iterable = iterable::resolve(id, config, day)
+                .filter(|iterable| (1..=iterable.count).contains(&index))
+                .ok_or_else(|| match day {
+                    Some(day) => format!("day {} has no {} {}", day, id, index),
+                    None => format!("there is no {} {}", id, index),
+                })?;
+            Some(iterable.folder_name(index))
+        }
+        None => None,
+    };
+
+    entries
+        .into_iter()
+        .find(|(path, entry_day)| {
+            (!in_days || *entry_day == day)
+                && name
+                    .as_ref()
+                    .is_none_or(|name| path.rsplit('/').next() == Some(name))
+        })
+        .map(|(path, _)| path)
+        .ok_or_else(|| match day.filter(|_| in_days) {
+            Some(day) => format!("day {} has no \"{}\" folder", day, rule.folder),
+            None => format!("the file structure has no \"{}\" folder", rule.folder),
+        })
+}
+
+/// Moves the planned files, recording them in the journal first so even an
+/// interrupted sort can be undone.
+fn apply(
+    root: &Path,
+    config: &Config,
+    moves: &[SortMove],
+    hash_algorithm: HashAlgorithm,
+) -> Result<(), SortError> {
+    let mut conflicts: Vec<String> = moves
+        .iter()
+        .filter(|m| root.join(&m.to).exists())
+        .map(|m| m.to.clone())
+        .collect();
+    for (i, m) in moves.iter().enumerate() {
+        if moves[..i].iter().any(|earlier| earlier.to == m.to) {
+            conflicts.push(m.to.clone());
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(SortError::WouldOverwrite(conflicts));
+    }
+
+    let mut journal = SortJournal::load(root)?;
+    journal.runs.push(SortRun {
+        sorted: DateTime::<Utc>::from(SystemTime::now()).to_rfc3339(),
+        moves: moves.to_vec(),
+    });
+    journal.save(root)?;
+
+    let mut manifests = DayManifests::new(root, config, hash_algorithm)?;
+    let moved = moves.iter().try_for_each(|m| {
+        move_file(&root.join(&m.from), &root.join(&m.to))?;
+        manifests.moved(m)
+    });
+    // Whatever did move is recorded even if a later file failed.
+    manifests.write()?;
+    moved
+}
+
+/// Puts the files of the last sort back. Files that have since moved again
+/// are left alone and stay in the journal.
+fn undo(
+    root: &Path,
+    config: &Config,
+    dry_run: bool,
+    hash_algorithm: HashAlgorithm,
+) -> Result<SortReport, SortError> {
+    let mut journal = SortJournal::load(root)?;
+    let run = journal.runs.pop().ok_or(SortError::NothingToUndo)?;
+
+    let mut report = SortReport::default();
+    let mut remaining = Vec::new();
+    let mut manifests = DayManifests::new(root, config, hash_algorithm)?;
+    for m in run.moves.iter().rev() {
+        let (from, to) = (root.join(&m.from), root.join(&m.to));
+        if !to.is_file() {
+            // Never moved, or moved on since.
+            continue;
+        }
+        if from.exists() {
+            report
+                .unmatched
+                .push(format!("{} (\"{}\" exists again)", m.to, m.from));
+            remaining.push(m.clone());
+            continue;
+        }
+        let back = SortMove {
+            from: m.to.clone(),
+            to: m.from.clone(),
+        };
+        if !dry_run {
+            if let Err(e) = move_file(&to, &from) {
+                manifests.write()?;
+                return Err(e);
+            }
+            manifests.moved(&back)?;
+        }
+        report.moves.push(back);
+    }
+
+    if !dry_run {
+        manifests.write()?;
+        if !remaining.is_empty() {
+            remaining.reverse();
+            journal.runs.push(SortRun {
+                sorted: run.sorted,
+                moves: remaining,
+            });
+        }
+        journal.save(root)?;
+    }
+    Ok(report)
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<(), SortError> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    // Across volumes.
+    let copied = fs::copy(from, to)
+        .map_err(SortError::from)
+        .and_then(|size| {
+            if fs::metadata(from)?.len() == size {
+                Ok(())
+            } else {
+                Err(SortError::InvalidSort(format!(
+                    "Copying {} did not complete, the original was left in place",
+                    from.display()
+                )))
+            }
+        });
+    if let Err(e) = copied {
+        // Left in place, the partial copy would block the retry as an overwrite.
+        let _ = fs::remove_file(to);
+        return Err(e);
+    }
+    fs::remove_file(from)?;
+    Ok(())
+}
+
+/// The day manifests (see `manifest`) of the files a sort moves. Files sorted
+/// into a shoot day are hashed and recorded the way ingest records them, so
+/// `verify` checks them instead of reporting them as extra.
+struct DayManifests<'a> {
+    root: &'a Path,
+    day_folders: Vec<String>,
+    hash_algorithm: HashAlgorithm,
+    loaded: BTreeMap<String, Manifest>,
+}
+
+impl<'a> DayManifests<'a> {
+    fn new(
+        root: &'a Path,
+        config: &Config,
+        hash_algorithm: HashAlgorithm,
+    ) -> Result<Self, SortError> {
+        Ok(DayManifests {
+            root,
+            day_folders: manifest::day_folders(config, None)?,
+            hash_algorithm,
+            loaded: BTreeMap::new(),
+        })
+    }
+
+    /// Moves the entry of a file that was moved from or into a day folder.
+    fn moved(&mut self, m: &SortMove) -> Result<(), SortError> {
+        if let Some((day_folder, relative)) = self.day_folder_of(&m.from) {
+            self.manifest(&day_folder)?
+                .files
+                .retain(|entry| entry.path != relative);
+        }
+        if let Some((day_folder, _)) = self.day_folder_of(&m.to) {
+            let folder = self.root.join(&day_folder);
+            let file = self.root.join(&m.to);
+            let hash = manifest::hash_file(&file, self.hash_algorithm)?;
+            let entry = ManifestEntry::from_file(&file, &folder, hash, self.hash_algorithm)?;
+            self.manifest(&day_folder)?.record(entry);
+        }
+        Ok(())
+    }
+
+    /// The day folder `path` sits in and the path relative to it.
+    fn day_folder_of(&self, path: &str) -> Option<(String, String)> {
+        self.day_folders.iter().find_map(|day_folder| {
+            let relative = path.strip_prefix(day_folder)?.strip_prefix('/')?;
+            Some((day_folder.clone(), relative.to_string()))
+        })
+    }
+
+    fn manifest(&mut self, day_folder: &str) -> Result<&mut Manifest, SortError> {
+        Ok(match self.loaded.entry(day_folder.to_string()) {
+            btree_map::Entry::Occupied(entry) => entry.into_mut(),
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(Manifest::load_or_new(&self.root.join(day_folder))?)
+            }
+        })
+    }
+
+    /// Writes every manifest that was touched, except new ones left empty.
+    fn write(&mut self) -> Result<(), SortError> {
+        for (day_folder, manifest) in &mut self.loaded {
+            let folder = self.root.join(day_folder);
+            if !manifest.files.is_empty() || folder.join(MANIFEST_FILE_NAME).exists() {
+                manifest.write(&folder)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn run(sort_info: SortInfo) -> Result<SortReport, SortError> {
+    let SortInfo {
+        root,
+        config,
+        dry_run,
+        action,
+        hash_algorithm,
+    } = sort_info;
+
+    let report = match &action {
+        SortAction::Sort(inbox) => {
+            let inbox = inbox
+                .clone()
+                .unwrap_or_else(|| default_inbox(&root, &config));
+            let report = plan(&root, &config, &inbox)?;
+            if !dry_run {
+                apply(&root, &config, &report.moves, hash_algorithm)?;
+            }
+            report
+        }
+        SortAction::Undo => undo(&root, &config, dry_run, hash_algorithm)?,
+    };
+
+    let verb = match (dry_run, action) {
+        (true, _) => "Would move",
+        (false, SortAction::Sort(_)) => "Moved",
+        (false, SortAction::Undo) => "Moved back",
+    };
+    for m in &report.moves {
+        println!("{} {} -> {}", verb, m.from, m.to);
+    }
+    for file in &report.unmatched {
+        println!("Left {}", file);
+    }
+    println!(
+        "{} {} file(s), left {}.",
+        verb,
+        report.moves.len(),
+        report.unmatched.len()
+    );
+    Ok(report)
+}
+
+/// The `[sort] inbox` of the project under `root`.
+pub fn default_inbox(root: &Path, config: &Config) -> PathBuf {
+    root.join(&config.setup.name).join(&config.sort.inbox)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(folder: &str, index: Option<usize>, day: Option<usize>) -> SortRule {
+        SortRule {
+            extensions: Vec::new(),
+            kind: None,
+            pattern: None,
+            folder: folder.to_string(),
+            index,
+            day,
+        }
+    }
+
+    #[test]
+    fn test_destination() {
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.setup.days = 3;
+
+        assert_eq!(
+            destination(&config, &rule("cams", Some(2), Some(3)), Some(3)).unwrap(),
+            "Demo/02_RUSHES/03_DAY03/01_VIDEO/02_B_CAM"
+        );
+        assert_eq!(
+            destination(&config, &rule("music", None, None), None).unwrap(),
+            "Demo/03_EXTERNAL/03_MUSIC"
+        );
+        assert!(destination(&config, &rule("cams", None, None), Some(1)).is_err());
+        assert!(destination(&config, &rule("cams", Some(1), None), None).is_err());
+        assert!(destination(&config, &rule("cams", Some(5), None), Some(1)).is_err());
+        assert!(destination(&config, &rule("nope", None, None), None).is_err());
+        assert_eq!(
+            destination(&config, &rule("cams", Some(1), Some(9)), Some(9)).unwrap_err(),
+            "day 9 is outside of the project's 3 day(s)"
+        );
+    }
+
+    #[test]
+    fn test_sort_and_undo() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.setup.set_shoot_day(1, |entry| {
+            entry.date = Some(chrono::Local::now().date_naive())
+        });
+        config.sort.rules = vec![
+            SortRule {
+                pattern: Some("^A\\d{3}C\\d{3}".to_string()),
+                ..rule("cams", Some(1), None)
+            },
+            SortRule {
+                kind: Some(MediaKind::Audio),
+                ..rule("soundsources", Some(1), Some(2))
+            },
+            SortRule {
+                extensions: vec!["jpg".to_string()],
+                ..rule("images", None, None)
+            },
+        ];
+        let inbox = default_inbox(root, &config);
+        fs::create_dir_all(inbox.join("card")).unwrap();
+        fs::write(inbox.join("card/A001C003.MOV"), b"clip").unwrap();
+        fs::write(inbox.join("ZOOM0001.WAV"), b"audio").unwrap();
+        fs::write(inbox.join("still.JPG"), b"image").unwrap();
+        fs::write(inbox.join("notes.txt"), b"notes").unwrap();
+
+        let report = plan(root, &config, &inbox).unwrap();
+        let moves: Vec<_> = report.moves.iter().map(|m| m.to.as_str()).collect();
+        assert_eq!(
+            moves,
+            vec![
+                "Demo/02_RUSHES/02_DAY02/02_AUDIO/01_A_REC/ZOOM0001.WAV",
+                "Demo/02_RUSHES/01_DAY01/01_VIDEO/01_A_CAM/A001C003.MOV",
+                "Demo/03_EXTERNAL/02_IMAGES/still.JPG",
+            ]
+        );
+        assert_eq!(
+            report.unmatched,
+            vec!["Demo/00_INBOX/notes.txt (no rule matches)"]
+        );
+
+        let info = |action| SortInfo {
+            root: root.to_path_buf(),
+            config: config.clone(),
+            dry_run: false,
+            action,
+            hash_algorithm: HashAlgorithm::XxHash64,
+        };
+        run(SortInfo {
+            dry_run: true,
+            ..info(SortAction::Sort(None))
+        })
+        .unwrap();
+        assert!(inbox.join("ZOOM0001.WAV").is_file());
+
+        run(info(SortAction::Sort(None))).unwrap();
+        assert!(!inbox.join("ZOOM0001.WAV").exists());
+        assert!(
+            root.join("Demo/02_RUSHES/01_DAY01/01_VIDEO/01_A_CAM/A001C003.MOV")
+                .is_file()
+        );
+
+        // Sorting the same files in again would overwrite them.
+        fs::write(inbox.join("still.JPG"), b"another").unwrap();
+        assert!(matches!(
+            run(info(SortAction::Sort(None))),
+            Err(SortError::WouldOverwrite(_))
+        ));
+        fs::remove_file(inbox.join("still.JPG")).unwrap();
+
+        let report = run(info(SortAction::Undo)).unwrap();
+        assert_eq!(report.moves.len(), 3);
+        assert_eq!(fs::read(inbox.join("card/A001C003.MOV")).unwrap(), b"clip");
+        assert!(SortJournal::load(root).unwrap().runs.is_empty());
+        assert!(matches!(
+            run(info(SortAction::Undo)),
+            Err(SortError::NothingToUndo)
+        ));
+    }
+
+    #[test]
+    fn test_sorted_files_verify() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.sort.rules = vec![rule("cams", Some(1), Some(1))];
+        let inbox = default_inbox(root, &config);
+        fs::create_dir_all(&inbox).unwrap();
+        fs::write(inbox.join("A001C003.MOV"), b"clip").unwrap();
+        let info = |action| SortInfo {
+            root: root.to_path_buf(),
+            config: config.clone(),
+            dry_run: false,
+            action,
+            hash_algorithm: HashAlgorithm::XxHash64,
+        };
+        let verify = || {
+            manifest::verify(crate::util::config::VerifyInfo {
+                root: root.to_path_buf(),
+                day: None,
+                config: config.clone(),
+            })
+        };
+
+        run(info(SortAction::Sort(None))).unwrap();
+        assert_eq!(verify().unwrap().verified, 1);
+
+        run(info(SortAction::Undo)).unwrap();
+        let day_folder = root.join("Demo/02_RUSHES/01_DAY01");
+        let manifest = Manifest::read(&day_folder.join(MANIFEST_FILE_NAME)).unwrap();
+        assert!(manifest.files.is_empty());
+        assert_eq!(verify().unwrap().verified, 0);
+    }
+}
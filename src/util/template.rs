@@ -0,0 +1,436 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Query,
+    config::{Config, FileStructure, Folder, TemplateAction, TemplatesInfo},
+    migration,
+    project::CONFIG_FILE,
+};
+
+pub const DEFAULT_TEMPLATE: &str = "default";
+const BUILTIN_TEMPLATES: [&str; 5] = [
+    DEFAULT_TEMPLATE,
+    "music-video",
+    "documentary",
+    "commercial",
+    "podcast",
+];
+
+#[derive(Debug)]
+pub enum TemplateError {
+    IoError(std::io::Error),
+    ParseError(String),
+    NotFound(String),
+    AlreadyExists(String),
+    InvalidName(String),
+    NoTemplateDir,
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::IoError(e) => write!(f, "IO error: {}", e),
+            TemplateError::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            TemplateError::NotFound(name) => write!(
+                f,
+                "No template named \"{}\" (see \"nanopm templates list\")",
+                name
+            ),
+            TemplateError::AlreadyExists(path) => write!(
+                f,
+                "\"{}\" already exists, use --force to overwrite it",
+                path
+            ),
+            TemplateError::InvalidName(name) => write!(
+                f,
+                "Template name \"{}\" may only contain letters, digits, '_' and '-'",
+                name
+            ),
+            TemplateError::NoTemplateDir => write!(
+                f,
+                "Could not find a config directory, set $XDG_CONFIG_HOME or $HOME"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl From<std::io::Error> for TemplateError {
+    fn from(error: std::io::Error) -> Self {
+        TemplateError::IoError(error)
+    }
+}
+
+/// A reusable project layout: the file structure and general query folders
+/// `new --template` seeds a project with.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Template {
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip)]
+    pub builtin: bool,
+    #[serde(default)]
+    pub description: String,
+    pub general_query_params: Vec<String>,
+    pub file_structure: FileStructure,
+}
+
+impl Template {
+    pub fn from_config(name: &str, description: &str, config: &Config) -> Self {
+        Template {
+            name: name.to_string(),
+            builtin: false,
+            description: description.to_string(),
+            general_query_params: config.general_query_params.clone(),
+            file_structure: config.file_structure.clone(),
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String, TemplateError> {
+        toml::to_string(self).map_err(|e| TemplateError::ParseError(e.to_string()))
+    }
+
+    fn read(path: &Path) -> Result<Self, TemplateError> {
+        let text = fs::read_to_string(path)?;
+        let mut template: Template = toml::from_str(&text)
+            .map_err(|e| TemplateError::ParseError(format!("{}: {}", path.display(), e)))?;
+        template.name = template_name(path).unwrap_or_default();
+        Ok(template)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/nanopm/templates`, falling back to `~/.config` and then
+/// `%APPDATA%` on Windows.
+pub fn user_templates_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(config_home.join("nanopm").join("templates"))
+}
+
+/// Finds `name` among the user's templates first, so a user template can
+/// replace a built-in one of the same name.
+pub fn load_template(name: &str, user_dir: Option<&Path>) -> Result<Template, TemplateError> {
+    if let Some(dir) = user_dir {
+        let path = dir.join(format!("{}.toml", name));
+        if path.is_file() {
+            return Template::read(&path);
+        }
+    }
+    builtin_template(name).ok_or_else(|| TemplateError::NotFound(name.to_string()))
+}
+
+/// Every available template, sorted by name. User templates hide built-ins
+/// with the same name.
+pub fn list_templates(user_dir: Option<&Path>) -> Result<Vec<Template>, TemplateError> {
+    let mut templates: Vec<Template> = Vec::new();
+    if let Some(dir) = user_dir
+        && dir.is_dir()
+    {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                templates.push(Template::read(&path)?);
+            }
+        }
+    }
+    for name in BUILTIN_TEMPLATES {
+        if !templates.iter().any(|template| template.name == name)
+            && let Some(template) = builtin_template(name)
+        {
+            templates.push(template);
+        }
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+pub fn export_template(
+    template: &Template,
+    user_dir: &Path,
+    force: bool,
+) -> Result<PathBuf, TemplateError> {
+    if template.name.is_empty()
+        || !template
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(TemplateError::InvalidName(template.name.clone()));
+    }
+    let path = user_dir.join(format!("{}.toml", template.name));
+    if path.exists() && !force {
+        return Err(TemplateError::AlreadyExists(
+            path.to_string_lossy().replace("\\", "/"),
+        ));
+    }
+    fs::create_dir_all(user_dir)?;
+    fs::write(&path, template.to_toml()?)?;
+    Ok(path)
+}
+
+pub fn run(templates_info: TemplatesInfo) -> Result<(), TemplateError> {
+    let user_dir = user_templates_dir();
+    match templates_info.action {
+        TemplateAction::List => {
+            for template in list_templates(user_dir.as_deref())? {
+                println!(
+                    "{:<16} {:<9} {}",
+                    template.name,
+                    if template.builtin { "built-in" } else { "user" },
+                    template.description
+                );
+            }
+        }
+        TemplateAction::Show(name) => {
+            let template = load_template(&name, user_dir.as_deref())?;
+            println!("{}", template.to_toml()?.trim_end());
+        }
+        TemplateAction::Export {
+            name,
+            description,
+            force,
+        } => {
+            let (config, _) = migration::load_config(&templates_info.root.join(CONFIG_FILE), false)
+                .map_err(|e| TemplateError::ParseError(e.to_string()))?;
+            let template = Template::from_config(&name, &description, &config);
+            let user_dir = user_dir.ok_or(TemplateError::NoTemplateDir)?;
+            let path = export_template(&template, &user_dir, force)?;
+            println!(
+                "Exported the structure of \"{}\" as template \"{}\" to \"{}\"",
+                config.setup.name,
+                name,
+                path.to_string_lossy().replace("\\", "/")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn template_name(path: &Path) -> Option<String> {
+    Some(path.file_stem()?.to_string_lossy().to_string())
+}
+
+fn builtin_template(name: &str) -> Option<Template> {
+    let (description, folders_list, general_query_params) = match name {
+        DEFAULT_TEMPLATE => {
+            return Some(Template {
+                name: name.to_string(),
+                builtin: true,
+                description: "General purpose shoot with documentation, rushes and external media"
+                    .to_string(),
+                general_query_params: Query::get_default_general_query(),
+                file_structure: FileStructure::get_default_structure(),
+            });
+        }
+        "music-video" => (
+            "Music video with playback track and stems",
+            vec![
+                Folder::new("doc", None, "01_DOCUMENTATION"),
+                Folder::new("rushes", None, "02_RUSHES"),
+                Folder::new("track", None, "03_TRACK"),
+                Folder::new("external", None, "04_EXTERNAL"),
+                Folder::new("prerenders", None, "05_PRE-RENDERS"),
+                Folder::new("finals", None, "06_FINALS"),
+                Folder::new("treatment", Some("doc"), "01_TREATMENT"),
+                Folder::new("lyrics", Some("doc"), "02_LYRICS"),
+                Folder::new("shotlist", Some("doc"), "03_SHOTLIST"),
+                Folder::new("days", Some("rushes"), "%days"),
+                Folder::new("video", Some("days"), "01_VIDEO"),
+                Folder::new("playback", Some("days"), "02_PLAYBACK"),
+                Folder::new("cams", Some("video"), "%cams"),
+                Folder::new("master", Some("track"), "01_MASTER"),
+                Folder::new("stems", Some("track"), "02_STEMS"),
+                Folder::new("graphics", Some("external"), "01_GRAPHICS"),
+                Folder::new("vfx", Some("external"), "02_VFX"),
+            ],
+            vec![
+                "01_TREATMENT",
+                "01_VIDEO",
+                "02_PLAYBACK",
+                "01_MASTER",
+                "02_STEMS",
+                "01_GRAPHICS",
+                "02_VFX",
+                "05_PRE-RENDERS",
+                "06_FINALS",
+            ],
+        ),
+        "documentary" => (
+            "Documentary with research, releases and archival material",
+            vec![
+                Folder::new("doc", None, "01_DOCUMENTATION"),
+                Folder::new("rushes", None, "02_RUSHES"),
+                Folder::new("archival", None, "03_ARCHIVAL"),
+                Folder::new("external", None, "04_EXTERNAL"),
+                Folder::new("prerenders", None, "05_PRE-RENDERS"),
+                Folder::new("finals", None, "06_FINALS"),
+                Folder::new("research", Some("doc"), "01_RESEARCH"),
+                Folder::new("releases", Some("doc"), "02_RELEASES"),
+                Folder::new("transcripts", Some("doc"), "03_TRANSCRIPTS"),
+                Folder::new("days", Some("rushes"), "%days"),
+                Folder::new("video", Some("days"), "01_VIDEO"),
+                Folder::new("audio", Some("days"), "02_AUDIO"),
+                Folder::new("cams", Some("video"), "%cams"),
+                Folder::new("soundsources", Some("audio"), "%soundsources"),
+                Folder::new("footage", Some("archival"), "01_FOOTAGE"),
+                Folder::new("photos", Some("archival"), "02_PHOTOS"),
+                Folder::new("documents", Some("archival"), "03_DOCUMENTS"),
+                Folder::new("graphics", Some("external"), "01_GRAPHICS"),
+                Folder::new("music", Some("external"), "02_MUSIC"),
+                Folder::new("sfx", Some("external"), "03_SFX"),
+            ],
+            vec![
+                "01_RESEARCH",
+                "03_TRANSCRIPTS",
+                "01_VIDEO",
+                "02_AUDIO",
+                "01_FOOTAGE",
+                "02_PHOTOS",
+                "01_GRAPHICS",
+                "02_MUSIC",
+                "05_PRE-RENDERS",
+                "06_FINALS",
+            ],
+        ),
+        "commercial" => (
+            "Commercial with brief, storyboard and client deliverables",
+            vec![
+                Folder::new("doc", None, "01_DOCUMENTATION"),
+                Folder::new("rushes", None, "02_RUSHES"),
+                Folder::new("external", None, "03_EXTERNAL"),
+                Folder::new("prerenders", None, "04_PRE-RENDERS"),
+                Folder::new("finals", None, "05_FINALS"),
+                Folder::new("brief", Some("doc"), "01_BRIEF"),
+                Folder::new("script", Some("doc"), "02_SCRIPT"),
+                Folder::new("storyboard", Some("doc"), "03_STORYBOARD"),
+                Folder::new("days", Some("rushes"), "%days"),
+                Folder::new("video", Some("days"), "01_VIDEO"),
+                Folder::new("audio", Some("days"), "02_AUDIO"),
+                Folder::new("cams", Some("video"), "%cams"),
+                Folder::new("soundsources", Some("audio"), "%soundsources"),
+                Folder::new("graphics", Some("external"), "01_GRAPHICS"),
+                Folder::new("logos", Some("external"), "02_LOGOS"),
+                Folder::new("music", Some("external"), "03_MUSIC"),
+                Folder::new("sfx", Some("external"), "04_SFX"),
+                Folder::new("master", Some("finals"), "01_MASTER"),
+                Folder::new("deliverables", Some("finals"), "02_DELIVERABLES"),
+            ],
+            vec![
+                "01_BRIEF",
+                "03_STORYBOARD",
+                "01_VIDEO",
+                "02_AUDIO",
+                "01_GRAPHICS",
+                "02_LOGOS",
+                "03_MUSIC",
+                "04_PRE-RENDERS",
+                "01_MASTER",
+                "02_DELIVERABLES",
+            ],
+        ),
+        "podcast" => (
+            "Podcast with per-session audio and video recordings",
+            vec![
+                Folder::new("doc", None, "01_DOCUMENTATION"),
+                Folder::new("recordings", None, "02_RECORDINGS"),
+                Folder::new("external", None, "03_EXTERNAL"),
+                Folder::new("edits", None, "04_EDITS"),
+                Folder::new("finals", None, "05_FINALS"),
+                Folder::new("shownotes", Some("doc"), "01_SHOW_NOTES"),
+                Folder::new("guests", Some("doc"), "02_GUESTS"),
+                Folder::new("days", Some("recordings"), "%days"),
+                Folder::new("audio", Some("days"), "01_AUDIO"),
+                Folder::new("video", Some("days"), "02_VIDEO"),
+                Folder::new("soundsources", Some("audio"), "%soundsources"),
+                Folder::new("cams", Some("video"), "%cams"),
+                Folder::new("music", Some("external"), "01_MUSIC"),
+                Folder::new("sfx", Some("external"), "02_SFX"),
+                Folder::new("artwork", Some("external"), "03_ARTWORK"),
+            ],
+            vec![
+                "01_SHOW_NOTES",
+                "01_AUDIO",
+                "02_VIDEO",
+                "01_MUSIC",
+                "03_ARTWORK",
+                "04_EDITS",
+                "05_FINALS",
+            ],
+        ),
+        _ => return None,
+    };
+
+    Some(Template {
+        name: name.to_string(),
+        builtin: true,
+        description: description.to_string(),
+        general_query_params: general_query_params.into_iter().map(String::from).collect(),
+        file_structure: FileStructure {
+            folders_list,
+            iterables: Vec::new(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_templates_generate() {
+        for name in BUILTIN_TEMPLATES {
+            let template = builtin_template(name).unwrap();
+            let mut config = Config::new_config();
+            config.file_structure = template.file_structure;
+            config.general_query_params = template.general_query_params;
+            assert!(
+                crate::generate_folder_paths(&config).is_ok(),
+                "template \"{}\" has a broken parent chain",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_export_and_load_user_template() {
+        let user_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::new_config();
+        config.general_query_params = vec!["01_VIDEO".to_string()];
+        let template = Template::from_config("my-shoot", "Our usual layout", &config);
+
+        export_template(&template, user_dir.path(), false).unwrap();
+        assert!(matches!(
+            export_template(&template, user_dir.path(), false),
+            Err(TemplateError::AlreadyExists(_))
+        ));
+
+        let loaded = load_template("my-shoot", Some(user_dir.path())).unwrap();
+        assert_eq!(loaded.description, "Our usual layout");
+        assert_eq!(loaded.general_query_params, vec!["01_VIDEO"]);
+        assert_eq!(
+            loaded.file_structure.folders_list,
+            config.file_structure.folders_list
+        );
+
+        let names: Vec<String> = list_templates(Some(user_dir.path()))
+            .unwrap()
+            .into_iter()
+            .map(|template| template.name)
+            .collect();
+        assert!(names.contains(&"my-shoot".to_string()));
+        assert!(names.contains(&"documentary".to_string()));
+
+        let bad_name = Template::from_config("../escape", "", &config);
+        assert!(export_template(&bad_name, user_dir.path(), false).is_err());
+        assert!(load_template("missing", Some(user_dir.path())).is_err());
+    }
+}
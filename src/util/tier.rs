@@ -0,0 +1,557 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, offset::Utc};
+use fs_extra::dir::{CopyOptions, DirContent, get_dir_content};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::{Config, ConfigError, TierAction, TierInfo},
+    project::CONFIG_FILE,
+    query::to_shorthand,
+};
+use crate::folder_entries;
+
+/// Left in every offloaded folder, saying where its contents went.
+pub const OFFLINE_STUB: &str = "nanopm_offline.toml";
+
+#[derive(Debug)]
+pub enum TierError {
+    IoError(std::io::Error),
+    ConfigError(ConfigError),
+    FsExtraError(fs_extra::error::Error),
+    NoOfflineRoot,
+    OfflineRootMissing(PathBuf),
+    InvalidSelection(String),
+    NotFound(PathBuf),
+    AlreadyExists(PathBuf),
+    NotOffline(String),
+    Incomplete(PathBuf),
+}
+
+impl std::fmt::Display for TierError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TierError::IoError(e) => write!(f, "IO error: {}", e),
+            TierError::ConfigError(e) => write!(f, "Config error: {}", e),
+            TierError::FsExtraError(e) => write!(f, "File system error: {}", e),
+            TierError::NoOfflineRoot => write!(
+                f,
+                "No offline root is set, pass --to <PATH> once to record one in the config"
+            ),
+            TierError::OfflineRootMissing(path) => write!(
+                f,
+                "The offline root {} is not available, is the volume mounted?",
+                path.display()
+            ),
+            TierError::InvalidSelection(msg) => write!(f, "{}", msg),
+            TierError::NotFound(path) => write!(f, "{} does not exist", path.display()),
+            TierError::AlreadyExists(path) => {
+                write!(
+                    f,
+                    "{} already exists, refusing to overwrite it",
+                    path.display()
+                )
+            }
+            TierError::NotOffline(msg) => write!(f, "Nothing to recall: {}", msg),
+            TierError::Incomplete(path) => write!(
+                f,
+                "Copying {} did not complete, the original was left in place",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TierError {}
+
+impl From<std::io::Error> for TierError {
+    fn from(error: std::io::Error) -> Self {
+        TierError::IoError(error)
+    }
+}
+
+impl From<ConfigError> for TierError {
+    fn from(error: ConfigError) -> Self {
+        TierError::ConfigError(error)
+    }
+}
+
+impl From<fs_extra::error::Error> for TierError {
+    fn from(error: fs_extra::error::Error) -> Self {
+        TierError::FsExtraError(error)
+    }
+}
+
+/// The `[tiering]` table of the config: where offloaded folders go and which
+/// folders currently live there.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Tiering {
+    /// A slower volume to offload to, e.g. "/Volumes/ARCHIVE".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline_root: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub offline: Vec<OfflineFolder>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct OfflineFolder {
+    /// Relative to the project folder, always with `/`.
+    pub path: String,
+    /// Where the folder's contents are now.
+    pub location: String,
+    pub file_count: usize,
+    pub size: u64,
+    pub offloaded: String,
+}
+
+impl Tiering {
+    pub fn is_empty(&self) -> bool {
+        self.offline_root.is_none() && self.offline.is_empty()
+    }
+
+    /// Files and bytes offloaded from `relative` (relative to the project
+    /// folder, "" for all of it) or anything inside it.
+    pub fn offline_within(&self, relative: &str) -> (usize, u64) {
+        self.offline
+            .iter()
+            .filter(|folder| relative.is_empty() || is_within(&folder.path, relative))
+            .fold((0, 0), |(files, size), folder| {
+                (files + folder.file_count, size + folder.size)
+            })
+    }
+}
+
+/// Picks folders by folder ID, optionally only those in some shoot days.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TierSelection {
+    pub id: String,
+    /// Empty for every day.
+    pub days: Vec<usize>,
+}
+
+impl TierSelection {
+    /// The selected paths, relative to the project folder.
+    fn paths(&self, config: &Config) -> Result<Vec<String>, TierError> {
+        let prefix = format!("{}/", config.setup.name);
+        let mut paths = Vec::new();
+        for (path, day) in folder_entries(config, &self.id)? {
+            if !self.days.is_empty() {
+                let Some(day) = day else {
+                    return Err(TierError::InvalidSelection(format!(
+                        "Folder \"{}\" is not inside a shoot day, drop --days",
+                        self.id
+                    )));
+                };
+                if !self.days.contains(&day) {
+                    continue;
+                }
+            }
+            paths.push(path.strip_prefix(&prefix).unwrap_or(&path).to_string());
+        }
+        if paths.is_empty() {
+            return Err(TierError::InvalidSelection(format!(
+                "No \"{}\" folders fall on the selected days",
+                self.id
+            )));
+        }
+        Ok(paths)
+    }
+}
+
+/// Parses shoot days like "3", "1-3" or "1-3,5".
+pub fn parse_days(arg: &str) -> Result<Vec<usize>, String> {
+    let invalid = || format!("\"{}\" is not a valid day range! Expected e.g. 1-3,5", arg);
+    let mut days = Vec::new();
+    for part in arg.split(',').map(str::trim) {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+        let start: usize = start.trim().parse().map_err(|_| invalid())?;
+        let end: usize = end.trim().parse().map_err(|_| invalid())?;
+        if start == 0 || end < start {
+            return Err(invalid());
+        }
+        days.extend(start..=end);
+    }
+    days.sort_unstable();
+    days.dedup();
+    Ok(days)
+}
+
+#[derive(Debug, Default)]
+pub struct TierReport {
+    pub moved: Vec<OfflineFolder>,
+    /// Selected folders that were already offline.
+    pub skipped: Vec<String>,
+}
+
+/// Moves the selected folders to the offline root, leaving an empty folder
+/// with a stub in their place, and records them in the config. The config
+/// is saved after every folder so an interrupted offload keeps its record.
+pub fn offload(
+    root: &Path,
+    config: &mut Config,
+    selection: &TierSelection,
+    offline_root: Option<&Path>,
+) -> Result<TierReport, TierError> {
+    if let Some(offline_root) = offline_root {
+        let offline_root = fs::canonicalize(offline_root)
+            .map_err(|_| TierError::OfflineRootMissing(offline_root.to_path_buf()))?;
+        config.tiering.offline_root = Some(offline_root.to_string_lossy().into_owned());
+    }
+    let offline_root = PathBuf::from(
+        config
+            .tiering
+            .offline_root
+            .clone()
+            .ok_or(TierError::NoOfflineRoot)?,
+    );
+    // Never fall back to writing into an empty mount point.
+    if !offline_root.is_dir() {
+        return Err(TierError::OfflineRootMissing(offline_root));
+    }
+    let project_folder = root.join(&config.setup.name);
+
+    // Check everything before moving anything.
+    let mut report = TierReport::default();
+    let mut to_move = Vec::new();
+    for path in selection.paths(config)? {
+        if config
+            .tiering
+            .offline
+            .iter()
+            .any(|folder| folder.path == path)
+        {
+            report.skipped.push(path);
+            continue;
+        }
+        if let Some(folder) = config
+            .tiering
+            .offline
+            .iter()
+            .find(|folder| is_within(&folder.path, &path) || is_within(&path, &folder.path))
+        {
+            return Err(TierError::InvalidSelection(format!(
+                "{} overlaps {}, which is already offline",
+                path, folder.path
+            )));
+        }
+        let source = project_folder.join(&path);
+        if !source.is_dir() {
+            return Err(TierError::NotFound(source));
+        }
+        let location = offline_root.join(&config.setup.name).join(&path);
+        if location.exists() {
+            return Err(TierError::AlreadyExists(location));
+        }
+        to_move.push((path, source, location));
+    }
+
+    for (path, source, location) in to_move {
+        let content = get_dir_content(&source)?;
+        move_folder(&source, &location)?;
+
+        let folder = OfflineFolder {
+            path,
+            location: location.to_string_lossy().into_owned(),
+            file_count: content.files.len(),
+            size: content.dir_size,
+            offloaded: DateTime::<Utc>::from(SystemTime::now()).to_rfc3339(),
+        };
+        fs::create_dir_all(&source)?;
+        let stub = toml::to_string(&folder)
+            .map_err(|e| ConfigError::ParseError(format!("Failed to serialize stub: {}", e)))?;
+        fs::write(source.join(OFFLINE_STUB), stub)?;
+
+        config.tiering.offline.push(folder.clone());
+        save_config(root, config)?;
+        report.moved.push(folder);
+    }
+
+    Ok(report)
+}
+
+/// Brings offloaded folders inside the selection (or all of them) back into
+/// the project and drops them from the config.
+pub fn recall(
+    root: &Path,
+    config: &mut Config,
+    selection: Option<&TierSelection>,
+) -> Result<TierReport, TierError> {
+    let offline: Vec<OfflineFolder> = match selection {
+        Some(selection) => {
+            let paths = selection.paths(config)?;
+            config
+                .tiering
+                .offline
+                .iter()
+                .filter(|folder| paths.iter().any(|path| is_within(&folder.path, path)))
+                .cloned()
+                .collect()
+        }
+        None => config.tiering.offline.clone(),
+    };
+    if offline.is_empty() {
+        return Err(TierError::NotOffline(match selection {
+            Some(selection) => format!("no \"{}\" folders are offline", selection.id),
+            None => "no folders are offline".to_string(),
+        }));
+    }
+
+    let project_folder = root.join(&config.setup.name);
+    let offline_root = config.tiering.offline_root.clone().map(PathBuf::from);
+    if let Some(offline_root) = &offline_root
+        && !offline_root.is_dir()
+    {
+        return Err(TierError::OfflineRootMissing(offline_root.clone()));
+    }
+    let mut report = TierReport::default();
+    for folder in offline {
+        let location = PathBuf::from(&folder.location);
+        if !location.is_dir() {
+            return Err(TierError::NotFound(location));
+        }
+        let destination = project_folder.join(&folder.path);
+        let stub = destination.join(OFFLINE_STUB);
+        if stub.is_file() {
+            fs::remove_file(&stub)?;
+        }
+        // Lets an untouched stub folder be replaced with a plain rename.
+        let _ = fs::remove_dir(&destination);
+        move_folder(&location, &destination)?;
+
+        // Tidy up the now empty parents on the offline volume.
+        if let Some(offline_root) = &offline_root {
+            for parent in location.ancestors().skip(1) {
+                if parent == offline_root || fs::remove_dir(parent).is_err() {
+                    break;
+                }
+            }
+        }
+
+        config
+            .tiering
+            .offline
+            .retain(|other| other.path != folder.path);
+        save_config(root, config)?;
+        report.moved.push(folder);
+    }
+
+    Ok(report)
+}
+
+/// Renames `from` to `to`, or copies it across when they sit on different
+/// volumes. The original is only removed once every file arrived whole.
+fn move_folder(from: &Path, to: &Path) -> Result<(), TierError> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if !to.exists() && fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    let content = get_dir_content(from)?;
+    let copy_of = |file: &String| {
+        let file = Path::new(file);
+        to.join(file.strip_prefix(from).unwrap_or(file))
+    };
+    // Left in place, a failed copy would block every retry, but files that
+    // were in `to` before aren't this copy's to remove.
+    let created = !to.exists();
+    let added: Vec<PathBuf> = content
+        .files
+        .iter()
+        .map(copy_of)
+        .filter(|copy| !copy.exists())
+        .collect();
+    if let Err(e) = copy_folder(from, to, &content, copy_of) {
+        if created {
+            let _ = fs::remove_dir_all(to);
+        } else {
+            for copy in &added {
+                let _ = fs::remove_file(copy);
+            }
+        }
+        return Err(e);
+    }
+    fs::remove_dir_all(from)?;
+    Ok(())
+}
+
+fn copy_folder(
+    from: &Path,
+    to: &Path,
+    content: &DirContent,
+    copy_of: impl Fn(&String) -> PathBuf,
+) -> Result<(), TierError> {
+    fs::create_dir_all(to)?;
+    let options = CopyOptions {
+        content_only: true,
+        ..CopyOptions::new()
+    };
+    fs_extra::dir::copy(from, to, &options)?;
+    for file in &content.files {
+        let copy = copy_of(file);
+        if fs::metadata(&copy).map(|m| m.len()).ok() != Some(fs::metadata(file)?.len()) {
+            return Err(TierError::Incomplete(from.to_path_buf()));
+        }
+    }
+    Ok(())
+}
+
+fn is_within(path: &str, parent: &str) -> bool {
+    path == parent || path.starts_with(&format!("{}/", parent))
+}
+
+fn save_config(root: &Path, config: &Config) -> Result<(), TierError> {
+    Config::write_config(config, &root.join(CONFIG_FILE).to_string_lossy())?;
+    Ok(())
+}
+
+pub fn run(tier_info: TierInfo) -> Result<TierReport, TierError> {
+    let TierInfo {
+        root,
+        mut config,
+        action,
+    } = tier_info;
+
+    let report = match &action {
+        TierAction::Offload { selection, to } => {
+            offload(&root, &mut config, selection, to.as_deref())?
+        }
+        TierAction::Recall(selection) => recall(&root, &mut config, selection.as_ref())?,
+    };
+
+    let verb = match action {
+        TierAction::Offload { .. } => "Offloaded",
+        TierAction::Recall(_) => "Recalled",
+    };
+    for folder in &report.moved {
+        println!(
+            "{} {} ({} file(s), {})",
+            verb,
+            folder.path,
+            folder.file_count,
+            to_shorthand(folder.size)
+        );
+        println!("    -> {}", folder.location);
+    }
+    for path in &report.skipped {
+        println!("{} is already offline, skipped", path);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_folder_paths;
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(parse_days("2").unwrap(), vec![2]);
+        assert_eq!(parse_days("1-3, 5,3").unwrap(), vec![1, 2, 3, 5]);
+        assert!(parse_days("0-2").is_err());
+        assert!(parse_days("3-1").is_err());
+        assert!(parse_days("a").is_err());
+    }
+
+    #[test]
+    fn test_offload_and_recall() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let dir = dir.path();
+        let root = dir.join("project");
+        fs::create_dir_all(&root).unwrap();
+
+        let mut config = Config::new_config();
+        config.setup.name = "Demo".to_string();
+        config.setup.days = 3;
+        for path in generate_folder_paths(&config).unwrap() {
+            fs::create_dir_all(root.join(path)).unwrap();
+        }
+        let clip = root.join("Demo/02_RUSHES/01_DAY01/01_VIDEO/01_A_CAM/clip.mov");
+        fs::write(&clip, b"rushes").unwrap();
+
+        let days = TierSelection {
+            id: "days".to_string(),
+            days: vec![1, 2],
+        };
+        assert!(matches!(
+            offload(&root, &mut config, &days, None),
+            Err(TierError::NoOfflineRoot)
+        ));
+
+        assert!(matches!(
+            offload(&root, &mut config, &days, Some(&dir.join("cold"))),
+            Err(TierError::OfflineRootMissing(_))
+        ));
+        fs::create_dir_all(dir.join("cold")).unwrap();
+        let report = offload(&root, &mut config, &days, Some(&dir.join("cold"))).unwrap();
+        assert_eq!(report.moved.len(), 2);
+        assert!(!clip.exists());
+        assert!(
+            root.join("Demo/02_RUSHES/01_DAY01")
+                .join(OFFLINE_STUB)
+                .is_file()
+        );
+        assert!(
+            dir.join("cold/Demo/02_RUSHES/01_DAY01/01_VIDEO/01_A_CAM/clip.mov")
+                .is_file()
+        );
+        // Sizes come from get_dir_content like query's, so they include the
+        // directories themselves.
+        let (files, size) = config.tiering.offline_within("02_RUSHES");
+        assert_eq!(files, 1);
+        assert!(size >= 6);
+        assert_eq!(config.tiering.offline_within(""), (files, size));
+        assert_eq!(config.tiering.offline_within("04_PRE-RENDERS"), (0, 0));
+
+        let again = offload(&root, &mut config, &days, None).unwrap();
+        assert_eq!(again.skipped.len(), 2);
+        let video = TierSelection {
+            id: "video".to_string(),
+            days: vec![1],
+        };
+        assert!(matches!(
+            offload(&root, &mut config, &video, None),
+            Err(TierError::InvalidSelection(_))
+        ));
+
+        let report = recall(&root, &mut config, None).unwrap();
+        assert_eq!(report.moved.len(), 2);
+        assert!(clip.is_file());
+        assert!(
+            !root
+                .join("Demo/02_RUSHES/01_DAY01")
+                .join(OFFLINE_STUB)
+                .exists()
+        );
+        assert!(config.tiering.offline.is_empty());
+        assert!(!dir.join("cold/Demo").exists());
+        assert!(matches!(
+            recall(&root, &mut config, None),
+            Err(TierError::NotOffline(_))
+        ));
+    }
+
+    #[test]
+    fn test_move_folder_removes_a_failed_copy() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::create_dir_all(from.join("sub")).unwrap();
+        fs::write(from.join("a.mov"), b"a").unwrap();
+        fs::write(from.join("sub/b.mov"), b"b").unwrap();
+        fs::write(from.join("c.mov"), b"c").unwrap();
+        // Already there, so the copy fails on it.
+        fs::create_dir_all(&to).unwrap();
+        fs::write(to.join("c.mov"), b"kept").unwrap();
+
+        assert!(move_folder(&from, &to).is_err());
+        assert!(from.join("sub/b.mov").is_file());
+        assert!(!to.join("a.mov").exists());
+        assert!(!to.join("sub/b.mov").exists());
+        assert_eq!(fs::read(to.join("c.mov")).unwrap(), b"kept");
+    }
+}
@@ -1,5 +1,7 @@
+use super::migration::CONFIG_VERSION;
+
 pub fn get_version() -> String {
-    String::from("v2")
+    format!("v{}", CONFIG_VERSION)
 }
 
 pub fn format_duration(millis: u64) -> String {
@@ -15,6 +17,17 @@ pub fn format_duration(millis: u64) -> String {
     }
 }
 
+/// Hours of footage as "H:MM:SS", e.g. "12:04:09".
+pub fn format_runtime(millis: u64) -> String {
+    let seconds = millis / 1000;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
 pub fn sanitize_filename(filename: &str) -> String {
     filename
         .chars()
@@ -48,7 +61,7 @@ mod tests {
 
     #[test]
     fn test_get_version() {
-        assert_eq!(get_version(), "v2");
+        assert_eq!(get_version(), "v3");
     }
 
     #[test]
@@ -58,6 +71,13 @@ mod tests {
         assert_eq!(format_duration(65000), "1m 5s");
     }
 
+    #[test]
+    fn test_format_runtime() {
+        assert_eq!(format_runtime(999), "0:00:00");
+        assert_eq!(format_runtime(65000), "0:01:05");
+        assert_eq!(format_runtime(43449000), "12:04:09");
+    }
+
     #[test]
     fn test_sanitize_filename() {
         assert_eq!(sanitize_filename("test<file>"), "test_file_");
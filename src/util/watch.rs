@@ -0,0 +1,231 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use notify::{
+    Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher,
+    event::{AccessKind, AccessMode},
+};
+
+use super::{
+    config::QueryInfo,
+    query::{QueryError, get_export_path, render_query_results, run_query, write_to_file},
+};
+
+/// How often to scan for changes when file system notifications don't work,
+/// e.g. on some network shares.
+pub const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchOptions {
+    /// How long changes are collected before the query re-runs. A steady
+    /// stream of changes re-runs the query once per window.
+    pub debounce: Duration,
+    /// Polls at this interval instead of using file system notifications.
+    pub poll_interval: Option<Duration>,
+}
+
+/// Runs the query, then re-runs it and redraws the console every time files
+/// in the project folder change, until interrupted.
+pub fn watch(query_info: QueryInfo, options: &WatchOptions) -> Result<(), QueryError> {
+    let QueryInfo {
+        root,
+        query,
+        mut settings,
+        config,
+        ..
+    } = query_info;
+
+    // Pinned, so every redraw rewrites the same file instead of a new
+    // timestamped one.
+    let export_path = get_export_path(&settings);
+    let watched = fs::canonicalize(root.join(&config.setup.name))?;
+    let ignored = absolute_export_path(&export_path);
+
+    let (sender, receiver) = mpsc::channel();
+    let (_watcher, mode) = start_watcher(&watched, sender, options)?;
+
+    let mut written = false;
+    loop {
+        print!("{}", CLEAR_SCREEN);
+        let start_time = Instant::now();
+        let rendered = run_query(&root, &query, &config, &settings, start_time)
+            .and_then(|results| render_query_results(results, &settings, &query, start_time));
+        match rendered {
+            Ok((display, content)) => {
+                println!("{}", display);
+                if settings.write {
+                    if written {
+                        fs::write(&export_path, &content)?;
+                        println!("Query result written to: {}", export_path);
+                    } else {
+                        written = write_to_file(&export_path, &content)?;
+                        // Declining the overwrite once stops the rewrites too.
+                        settings.write = written;
+                    }
+                }
+            }
+            // Folders can vanish mid-scan while media is being moved around.
+            Err(e) => eprintln!("Query failed: {}", e),
+        }
+        println!(
+            "\nWatching {} ({}), last updated {}. Press Ctrl+C to stop.",
+            watched.display(),
+            mode,
+            Local::now().format("%T")
+        );
+
+        if !wait_for_changes(&receiver, options.debounce, ignored.as_deref()) {
+            return Ok(());
+        }
+    }
+}
+
+fn start_watcher(
+    path: &Path,
+    sender: mpsc::Sender<notify::Result<Event>>,
+    options: &WatchOptions,
+) -> Result<(Box<dyn Watcher>, String), QueryError> {
+    let poll = |interval: Duration, sender| -> Result<(Box<dyn Watcher>, String), QueryError> {
+        let config = notify::Config::default().with_poll_interval(interval);
+        let mut watcher = PollWatcher::new(sender, config)?;
+        watcher.watch(path, RecursiveMode::Recursive)?;
+        let mode = format!("polling every {}s", interval.as_secs_f32());
+        Ok((Box::new(watcher), mode))
+    };
+
+    if let Some(interval) = options.poll_interval {
+        return poll(interval, sender);
+    }
+
+    let notified = RecommendedWatcher::new(sender.clone(), notify::Config::default()).and_then(
+        |mut watcher| {
+            watcher.watch(path, RecursiveMode::Recursive)?;
+            Ok(watcher)
+        },
+    );
+    match notified {
+        Ok(watcher) => Ok((Box::new(watcher), "file system notifications".to_string())),
+        Err(e) => {
+            eprintln!(
+                "File system notifications are unavailable ({}), polling instead.",
+                e
+            );
+            poll(FALLBACK_POLL_INTERVAL, sender)
+        }
+    }
+}
+
+/// Blocks until a relevant change arrives, then keeps collecting changes for
+/// `debounce` so a burst of them (a card being copied in) only re-runs the
+/// query once per window. Returns false once the watcher has gone away.
+fn wait_for_changes(
+    receiver: &Receiver<notify::Result<Event>>,
+    debounce: Duration,
+    ignored: Option<&Path>,
+) -> bool {
+    loop {
+        match receiver.recv() {
+            Ok(Ok(event)) if is_relevant(&event, ignored) => break,
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => eprintln!("Watch error: {}", e),
+            Err(_) => return false,
+        }
+    }
+
+    let deadline = Instant::now() + debounce;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        match receiver.recv_timeout(remaining) {
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => return true,
+            Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+}
+
+/// Reads (including the query's own scan) and writes to the export file
+/// don't count as changes.
+fn is_relevant(event: &Event, ignored: Option<&Path>) -> bool {
+    match event.kind {
+        EventKind::Access(AccessKind::Close(AccessMode::Write)) => !only_ignored(event, ignored),
+        EventKind::Access(_) => false,
+        _ => !only_ignored(event, ignored),
+    }
+}
+
+fn only_ignored(event: &Event, ignored: Option<&Path>) -> bool {
+    ignored.is_some_and(|ignored| event.paths.iter().all(|path| path == ignored))
+}
+
+fn absolute_export_path(export_path: &str) -> Option<PathBuf> {
+    let export_path = Path::new(export_path);
+    let parent = match export_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(
+        fs::canonicalize(parent)
+            .ok()?
+            .join(export_path.file_name()?),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use notify::event::{CreateKind, ModifyKind};
+
+    use super::*;
+
+    #[test]
+    fn test_is_relevant() {
+        let ignored = PathBuf::from("/shoot/Query.json");
+        let event = |kind, path: &str| Event::new(kind).add_path(PathBuf::from(path));
+
+        assert!(is_relevant(
+            &event(EventKind::Create(CreateKind::File), "/shoot/Demo/clip.mov"),
+            Some(&ignored)
+        ));
+        assert!(!is_relevant(
+            &event(EventKind::Access(AccessKind::Any), "/shoot/Demo/clip.mov"),
+            Some(&ignored)
+        ));
+        assert!(!is_relevant(
+            &event(EventKind::Modify(ModifyKind::Any), "/shoot/Query.json"),
+            Some(&ignored)
+        ));
+    }
+
+    #[test]
+    fn test_wait_for_changes_debounces_bursts() {
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..1000 {
+            let path = PathBuf::from(format!("/shoot/Demo/clip_{}.mov", i));
+            sender
+                .send(Ok(
+                    Event::new(EventKind::Create(CreateKind::File)).add_path(path)
+                ))
+                .unwrap();
+        }
+
+        assert!(wait_for_changes(&receiver, Duration::from_millis(20), None));
+        // The whole burst was folded into that one wake-up.
+        assert!(receiver.try_recv().is_err());
+
+        drop(sender);
+        assert!(!wait_for_changes(
+            &receiver,
+            Duration::from_millis(20),
+            None
+        ));
+    }
+}
@@ -0,0 +1,640 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{
+        OutputFormat, Query, QuerySettings, QueryType, SortType, WorkspaceAction, WorkspaceInfo,
+    },
+    project::{Project, ProjectError},
+    query::{self, QUERY_SCHEMA_VERSION, QueryError, QueryRecord, QueryResult, to_shorthand},
+    util::util::get_version,
+};
+
+pub const WORKSPACE_FILE: &str = "nanopm_workspace.toml";
+/// Overrides workspace discovery, like `--workspace`.
+pub const WORKSPACE_ENV: &str = "NANOPM_WORKSPACE";
+
+#[derive(Debug)]
+pub enum WorkspaceError {
+    IoError(std::io::Error),
+    ParseError(String),
+    ProjectError(ProjectError),
+    QueryError(QueryError),
+    NotFound(PathBuf),
+    AlreadyExists(PathBuf),
+    AlreadyRegistered(String),
+    NotRegistered(String),
+}
+
+impl std::fmt::Display for WorkspaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkspaceError::IoError(e) => write!(f, "IO error: {}", e),
+            WorkspaceError::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            WorkspaceError::ProjectError(e) => write!(f, "{}", e),
+            WorkspaceError::QueryError(e) => write!(f, "{}", e),
+            WorkspaceError::NotFound(start) => write!(
+                f,
+                "No {} found in \"{}\" or any of its parent directories, run nanopm workspace \
+                 init first or point --workspace (or {}) at one",
+                WORKSPACE_FILE,
+                display(start),
+                WORKSPACE_ENV
+            ),
+            WorkspaceError::AlreadyExists(path) => {
+                write!(f, "A workspace already exists at \"{}\"", display(path))
+            }
+            WorkspaceError::AlreadyRegistered(path) => {
+                write!(f, "\"{}\" is already part of the workspace", path)
+            }
+            WorkspaceError::NotRegistered(path) => {
+                write!(f, "\"{}\" is not part of the workspace", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WorkspaceError {}
+
+impl From<std::io::Error> for WorkspaceError {
+    fn from(error: std::io::Error) -> Self {
+        WorkspaceError::IoError(error)
+    }
+}
+
+impl From<ProjectError> for WorkspaceError {
+    fn from(error: ProjectError) -> Self {
+        WorkspaceError::ProjectError(error)
+    }
+}
+
+impl From<QueryError> for WorkspaceError {
+    fn from(error: QueryError) -> Self {
+        WorkspaceError::QueryError(error)
+    }
+}
+
+/// A registry of project roots, usually at the top of a shared volume.
+/// Roots inside the registry's directory are stored relative to it so the
+/// volume can be mounted anywhere.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Workspace {
+    #[serde(skip)]
+    file: PathBuf,
+    pub version: String,
+    #[serde(default)]
+    pub projects: Vec<String>,
+}
+
+/// A registered project that could not be opened, e.g. an unmounted drive.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct UnavailableProject {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ProjectTotals {
+    pub path: String,
+    pub project_name: String,
+    pub file_count: usize,
+    pub total_size: String,
+    pub total_size_bytes: u64,
+    pub shoot_days: usize,
+    pub camera_count: usize,
+    pub sound_source_count: usize,
+}
+
+/// Root query totals of every project plus their sum, biggest project first.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct WorkspaceTotals {
+    pub project_count: usize,
+    pub file_count: usize,
+    pub total_size: String,
+    pub total_size_bytes: u64,
+    pub shoot_days: usize,
+    pub camera_count: usize,
+    pub sound_source_count: usize,
+    pub projects: Vec<ProjectTotals>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unavailable: Vec<UnavailableProject>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProjectQueryResults {
+    pub path: String,
+    pub project_name: String,
+    pub results: Vec<QueryResult>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceQuery {
+    pub query: Query,
+    pub projects: Vec<ProjectQueryResults>,
+    pub unavailable: Vec<UnavailableProject>,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkspaceQueryDocument<'a> {
+    schema_version: u32,
+    nanopm_version: String,
+    projects: Vec<ProjectQueryDocument<'a>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    unavailable: &'a [UnavailableProject],
+}
+
+#[derive(Debug, Serialize)]
+struct ProjectQueryDocument<'a> {
+    project: &'a str,
+    path: &'a str,
+    results: Vec<QueryRecord>,
+}
+
+impl Workspace {
+    /// Starts an empty registry in `dir`.
+    pub fn init(dir: &Path) -> Result<Self, WorkspaceError> {
+        let file = dir.join(WORKSPACE_FILE);
+        if file.exists() {
+            return Err(WorkspaceError::AlreadyExists(file));
+        }
+        let workspace = Workspace {
+            file,
+            version: get_version(),
+            projects: Vec::new(),
+        };
+        workspace.save()?;
+        Ok(workspace)
+    }
+
+    pub fn load(file: &Path) -> Result<Self, WorkspaceError> {
+        let text = fs::read_to_string(file)?;
+        let mut workspace: Workspace =
+            toml::from_str(&text).map_err(|e| WorkspaceError::ParseError(e.to_string()))?;
+        workspace.file = file.to_path_buf();
+        Ok(workspace)
+    }
+
+    /// Loads `explicit` (the registry or the directory holding it) if given,
+    /// otherwise the nearest registry at or above `start`.
+    pub fn locate(explicit: Option<&Path>, start: &Path) -> Result<Self, WorkspaceError> {
+        let file = match explicit {
+            Some(path) if path.is_dir() => path.join(WORKSPACE_FILE),
+            Some(path) => path.to_path_buf(),
+            None => start
+                .ancestors()
+                .map(|dir| dir.join(WORKSPACE_FILE))
+                .find(|file| file.is_file())
+                .ok_or_else(|| WorkspaceError::NotFound(start.to_path_buf()))?,
+        };
+        if !file.is_file() {
+            return Err(WorkspaceError::NotFound(file));
+        }
+        Self::load(&file)
+    }
+
+    pub fn save(&self) -> Result<(), WorkspaceError> {
+        let text = toml::to_string(self).map_err(|e| WorkspaceError::ParseError(e.to_string()))?;
+        fs::write(&self.file, text)?;
+        Ok(())
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    fn dir(&self) -> &Path {
+        match self.file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        }
+    }
+
+    /// Registers the project at `root`, returning the path it was stored as.
+    pub fn add(&mut self, root: &Path) -> Result<String, WorkspaceError> {
+        Project::open_read_only(root)?;
+        let entry = self.entry_for(root)?;
+        if self.projects.contains(&entry) {
+            return Err(WorkspaceError::AlreadyRegistered(entry));
+        }
+        self.projects.push(entry.clone());
+        Ok(entry)
+    }
+
+    /// Unregisters a project, given either its stored path or its root.
+    pub fn remove(&mut self, path: &Path) -> Result<String, WorkspaceError> {
+        let stored = path.to_string_lossy().replace("\\", "/");
+        let index = self
+            .projects
+            .iter()
+            .position(|entry| *entry == stored)
+            .or_else(|| {
+                let entry = self.entry_for(path).ok()?;
+                self.projects.iter().position(|other| *other == entry)
+            })
+            .ok_or(WorkspaceError::NotRegistered(stored))?;
+        Ok(self.projects.remove(index))
+    }
+
+    fn entry_for(&self, root: &Path) -> Result<String, WorkspaceError> {
+        let root = fs::canonicalize(root)?;
+        let dir = fs::canonicalize(self.dir())?;
+        let entry = match root.strip_prefix(&dir) {
+            Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => root,
+        };
+        Ok(entry.to_string_lossy().replace("\\", "/"))
+    }
+
+    pub fn project_root(&self, entry: &str) -> PathBuf {
+        self.dir().join(entry)
+    }
+
+    /// Opens every registered project without migrating its config on disk.
+    pub fn projects(&self) -> Vec<(String, Result<Project, ProjectError>)> {
+        self.projects
+            .iter()
+            .map(|entry| {
+                let project = Project::open_read_only(self.project_root(entry));
+                (entry.clone(), project)
+            })
+            .collect()
+    }
+
+    /// Runs `query` in every project that can be opened.
+    pub fn query(
+        &self,
+        query: &Query,
+        settings: &QuerySettings,
+    ) -> Result<WorkspaceQuery, WorkspaceError> {
+        let mut workspace_query = WorkspaceQuery {
+            query: query.clone(),
+            projects: Vec::new(),
+            unavailable: Vec::new(),
+        };
+        for (path, project) in self.projects() {
+            match project.and_then(|project| {
+                let results = project.query(query, settings)?;
+                Ok((project.config().setup.name.clone(), results))
+            }) {
+                Ok((project_name, results)) => workspace_query.projects.push(ProjectQueryResults {
+                    path,
+                    project_name,
+                    results,
+                }),
+                Err(error) => workspace_query.unavailable.push(UnavailableProject {
+                    path,
+                    error: error.to_string(),
+                }),
+            }
+        }
+        Ok(workspace_query)
+    }
+
+    /// Root query totals per project and for the whole workspace.
+    pub fn totals(&self) -> Result<WorkspaceTotals, WorkspaceError> {
+        let settings = QuerySettings {
+            quiet: true,
+            ..Default::default()
+        };
+        let root_query = Query::Partial(vec![QueryType::Root], SortType::None);
+        let workspace_query = self.query(&root_query, &settings)?;
+
+        let mut projects: Vec<ProjectTotals> = workspace_query
+            .projects
+            .into_iter()
+            .filter_map(|project| {
+                project.results.into_iter().find_map(|result| match result {
+                    QueryResult::RootResult(root) => Some(ProjectTotals {
+                        path: project.path.clone(),
+                        project_name: root.project_name,
+                        file_count: root.file_count,
+                        total_size: root.total_size,
+                        total_size_bytes: root.total_size_u64,
+                        shoot_days: root.shoot_days,
+                        camera_count: root.camera_count,
+                        sound_source_count: root.sound_source_count,
+                    }),
+                    _ => None,
+                })
+            })
+            .collect();
+        projects.sort_by_key(|project| std::cmp::Reverse(project.total_size_bytes));
+
+        let total_size_bytes = projects.iter().map(|p| p.total_size_bytes).sum();
+        Ok(WorkspaceTotals {
+            project_count: projects.len(),
+            file_count: projects.iter().map(|p| p.file_count).sum(),
+            total_size: to_shorthand(total_size_bytes),
+            total_size_bytes,
+            shoot_days: projects.iter().map(|p| p.shoot_days).sum(),
+            camera_count: projects.iter().map(|p| p.camera_count).sum(),
+            sound_source_count: projects.iter().map(|p| p.sound_source_count).sum(),
+            projects,
+            unavailable: workspace_query.unavailable,
+        })
+    }
+}
+
+impl WorkspaceTotals {
+    pub fn render(&self, format: OutputFormat) -> Result<String, WorkspaceError> {
+        match format {
+            OutputFormat::Text => {
+                let mut text = format!(
+                    "[Workspace Totals]\nproject_count = {}\nfile_count = {}\ntotal_size = \
+                     \"{}\"\nshoot_days = {}\ncamera_count = {}\nsound_source_count = {}\n",
+                    self.project_count,
+                    self.file_count,
+                    self.total_size,
+                    self.shoot_days,
+                    self.camera_count,
+                    self.sound_source_count
+                );
+                for project in &self.projects {
+                    text.push_str(&format!(
+                        "\n[Project Totals]\n{}",
+                        toml::to_string(project)
+                            .map_err(|e| WorkspaceError::ParseError(e.to_string()))?
+                    ));
+                }
+                text.push_str(&render_unavailable(&self.unavailable));
+                Ok(text)
+            }
+            OutputFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| WorkspaceError::ParseError(e.to_string())),
+            OutputFormat::Toml => {
+                toml::to_string(self).map_err(|e| WorkspaceError::ParseError(e.to_string()))
+            }
+            OutputFormat::Csv => {
+                let mut text = String::from(
+                    "kind,path,project_name,file_count,total_size,total_size_bytes,shoot_days,\
+                     camera_count,sound_source_count\n",
+                );
+                for p in &self.projects {
+                    text.push_str(&format!(
+                        "project,{},{},{},{},{},{},{},{}\n",
+                        p.path,
+                        p.project_name,
+                        p.file_count,
+                        p.total_size,
+                        p.total_size_bytes,
+                        p.shoot_days,
+                        p.camera_count,
+                        p.sound_source_count
+                    ));
+                }
+                text.push_str(&format!(
+                    "workspace,,,{},{},{},{},{},{}\n",
+                    self.file_count,
+                    self.total_size,
+                    self.total_size_bytes,
+                    self.shoot_days,
+                    self.camera_count,
+                    self.sound_source_count
+                ));
+                Ok(text)
+            }
+        }
+    }
+}
+
+impl WorkspaceQuery {
+    pub fn render(&self, format: OutputFormat) -> Result<String, WorkspaceError> {
+        match format {
+            OutputFormat::Text => {
+                let mut text = String::new();
+                for project in &self.projects {
+                    text.push_str(&format!(
+                        "==== {} ({}) ====\n\n",
+                        project.project_name, project.path
+                    ));
+                    for result in &project.results {
+                        text.push_str(&format!("{}\n", result.clone().get_result_string()));
+                    }
+                }
+                text.push_str(&render_unavailable(&self.unavailable));
+                Ok(text)
+            }
+            OutputFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                let headers =
+                    std::iter::once("project").chain(QueryRecord::CSV_HEADERS.iter().copied());
+                writer
+                    .write_record(headers)
+                    .map_err(|e| WorkspaceError::ParseError(e.to_string()))?;
+                for project in &self.projects {
+                    for result in &project.results {
+                        let record = result.to_record();
+                        for row in record.csv_rows() {
+                            writer
+                                .serialize((&project.project_name, row))
+                                .map_err(|e| WorkspaceError::ParseError(e.to_string()))?;
+                        }
+                    }
+                }
+                let bytes = writer
+                    .into_inner()
+                    .map_err(|e| WorkspaceError::ParseError(e.to_string()))?;
+                String::from_utf8(bytes).map_err(|e| WorkspaceError::ParseError(e.to_string()))
+            }
+            format => {
+                let document = WorkspaceQueryDocument {
+                    schema_version: QUERY_SCHEMA_VERSION,
+                    nanopm_version: get_version(),
+                    projects: self
+                        .projects
+                        .iter()
+                        .map(|project| ProjectQueryDocument {
+                            project: &project.project_name,
+                            path: &project.path,
+                            results: project.results.iter().map(QueryResult::to_record).collect(),
+                        })
+                        .collect(),
+                    unavailable: &self.unavailable,
+                };
+                if format == OutputFormat::Json {
+                    serde_json::to_string_pretty(&document)
+                        .map_err(|e| WorkspaceError::ParseError(e.to_string()))
+                } else {
+                    toml::to_string(&document)
+                        .map_err(|e| WorkspaceError::ParseError(e.to_string()))
+                }
+            }
+        }
+    }
+}
+
+fn render_unavailable(unavailable: &[UnavailableProject]) -> String {
+    unavailable
+        .iter()
+        .map(|project| format!("\nUnavailable: {} ({})\n", project.path, project.error))
+        .collect()
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().replace("\\", "/")
+}
+
+pub fn run(workspace_info: WorkspaceInfo) -> Result<(), WorkspaceError> {
+    let explicit = workspace_info.workspace.as_deref();
+    let current_dir = std::env::current_dir()?;
+
+    match workspace_info.action {
+        WorkspaceAction::Init => {
+            let dir = explicit.unwrap_or(&current_dir);
+            let workspace = Workspace::init(dir)?;
+            println!("Created workspace \"{}\"", display(workspace.file()));
+        }
+        WorkspaceAction::Add(roots) => {
+            let mut workspace = Workspace::locate(explicit, &current_dir)?;
+            for root in roots {
+                let entry = workspace.add(&root)?;
+                println!("Added \"{}\" to the workspace", entry);
+            }
+            workspace.save()?;
+        }
+        WorkspaceAction::Remove(paths) => {
+            let mut workspace = Workspace::locate(explicit, &current_dir)?;
+            for path in paths {
+                let entry = workspace.remove(&path)?;
+                println!("Removed \"{}\" from the workspace", entry);
+            }
+            workspace.save()?;
+        }
+        WorkspaceAction::List => {
+            let workspace = Workspace::locate(explicit, &current_dir)?;
+            println!(
+                "{} project(s) in \"{}\"",
+                workspace.projects.len(),
+                display(workspace.file())
+            );
+            for (path, project) in workspace.projects() {
+                match project {
+                    Ok(project) => {
+                        let setup = &project.config().setup;
+                        println!(
+                            "{:<24} {:<32} {} day(s), {} camera(s), {} sound source(s)",
+                            setup.name,
+                            path,
+                            setup.days,
+                            setup.max_cameras(),
+                            setup.max_sound_sources()
+                        );
+                    }
+                    Err(error) => println!("{:<24} {:<32} unavailable: {}", "?", path, error),
+                }
+            }
+        }
+        WorkspaceAction::Query(query, settings) => {
+            let workspace = Workspace::locate(explicit, &current_dir)?;
+            let start_time = Instant::now();
+            let workspace_query = workspace.query(&query, &settings)?;
+            let mut text = workspace_query.render(settings.format)?;
+            if settings.include_runtime {
+                text = format!(
+                    "Total Query Runtime: {}ms\n\n{}",
+                    start_time.elapsed().as_millis(),
+                    text
+                );
+            }
+            println!("{}", text.trim_end());
+            if settings.write {
+                query::write_to_file(&query::get_export_path(&settings), &text)?;
+            }
+        }
+        WorkspaceAction::Totals(format) => {
+            let workspace = Workspace::locate(explicit, &current_dir)?;
+            println!("{}", workspace.totals()?.render(format)?.trim_end());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn create_project(root: &Path, name: &str, days: usize) {
+        let mut config = Config::new_config();
+        config.setup.name = name.to_string();
+        config.setup.days = days;
+        Project::create(root, config).unwrap();
+    }
+
+    #[test]
+    fn test_registry_add_and_remove() {
+        let volume = tempfile::TempDir::new().unwrap();
+        create_project(&volume.path().join("shoot_a"), "A", 1);
+
+        let mut workspace = Workspace::init(volume.path()).unwrap();
+        assert!(Workspace::init(volume.path()).is_err());
+        assert_eq!(
+            workspace.add(&volume.path().join("shoot_a")).unwrap(),
+            "shoot_a"
+        );
+        assert!(workspace.add(&volume.path().join("shoot_a")).is_err());
+        assert!(workspace.add(volume.path()).is_err());
+        workspace.save().unwrap();
+
+        let nested = volume.path().join("shoot_a/A/02_RUSHES");
+        let mut reloaded = Workspace::locate(None, &nested).unwrap();
+        assert_eq!(reloaded.projects, vec!["shoot_a"]);
+        assert_eq!(reloaded.remove(Path::new("shoot_a")).unwrap(), "shoot_a");
+        assert!(reloaded.remove(Path::new("shoot_a")).is_err());
+    }
+
+    #[test]
+    fn test_totals_aggregate_projects() {
+        let volume = tempfile::TempDir::new().unwrap();
+        create_project(&volume.path().join("small"), "Small", 1);
+        create_project(&volume.path().join("big"), "Big", 3);
+        fs::write(
+            volume.path().join("big/Big/02_RUSHES/01_DAY01/clip.mov"),
+            vec![0; 4096],
+        )
+        .unwrap();
+
+        let mut workspace = Workspace::init(volume.path()).unwrap();
+        workspace.add(&volume.path().join("small")).unwrap();
+        workspace.add(&volume.path().join("big")).unwrap();
+        workspace.projects.push("unmounted".to_string());
+
+        let totals = workspace.totals().unwrap();
+        assert_eq!(totals.project_count, 2);
+        assert_eq!(totals.projects[0].project_name, "Big");
+        assert_eq!(totals.shoot_days, 4);
+        assert_eq!(totals.camera_count, 4);
+        assert_eq!(totals.file_count, 1);
+        assert_eq!(totals.unavailable.len(), 1);
+
+        let csv_text = totals.render(OutputFormat::Csv).unwrap();
+        assert!(
+            csv_text
+                .lines()
+                .last()
+                .unwrap()
+                .starts_with("workspace,,,1,")
+        );
+
+        let query = workspace
+            .query(
+                &Query::Partial(vec![QueryType::Days], SortType::None),
+                &QuerySettings {
+                    quiet: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let csv_text = query.render(OutputFormat::Csv).unwrap();
+        assert!(csv_text.starts_with("project,kind,name,"));
+        assert!(csv_text.contains("\nBig,day,Day 3,"));
+    }
+}